@@ -0,0 +1,2 @@
+drop table if exists liquidity_changes cascade;
+alter table markets drop column if exists subsidy;
@@ -0,0 +1,14 @@
+-- coins put up by the owner, separate from liquidity (the LMSR b-parameter)
+ALTER TABLE markets ADD COLUMN subsidy DECIMAL(16, 4) NOT NULL DEFAULT 0.0000 CHECK (subsidy >= 0);
+UPDATE markets SET subsidy = liquidity;
+ALTER TABLE markets ALTER COLUMN subsidy DROP DEFAULT;
+
+CREATE TABLE liquidity_changes (
+    id SERIAL PRIMARY KEY,
+    market_id INT NOT NULL REFERENCES markets(id),
+    user_id TEXT NOT NULL REFERENCES users(id),
+    amount DECIMAL(16, 4) NOT NULL, -- positive if subsidy added, negative if withdrawn
+    liquidity_before DECIMAL(16, 4) NOT NULL,
+    liquidity_after DECIMAL(16, 4) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
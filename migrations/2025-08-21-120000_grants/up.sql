@@ -0,0 +1,25 @@
+CREATE TABLE grants (
+    id SERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    amount DECIMAL(16, 4) NOT NULL, -- coins put into circulation, negative if taken out
+    reason TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+-- balances may have been edited directly, so instead of the starting balance
+-- each user gets an opening grant of whatever they hold now: their balance and
+-- what they paid into open markets, as subsidy or through trades
+INSERT INTO grants (user_id, amount, reason)
+SELECT users.id,
+    users.balance
+    + COALESCE((
+        SELECT sum(markets.subsidy) FROM markets
+        WHERE markets.owner_id = users.id AND NOT markets.is_resolved
+    ), 0)
+    - COALESCE((
+        SELECT sum(trades.balance_change) FROM trades
+        JOIN markets ON markets.id = trades.market_id
+        WHERE trades.user_id = users.id AND NOT markets.is_resolved
+    ), 0),
+    'opening balance'
+FROM users;
@@ -0,0 +1,2 @@
+alter table markets drop column if exists resolution_kind;
+alter table markets drop column if exists resolution_probs;
@@ -0,0 +1,20 @@
+-- coins paid per share of each outcome, NULL when unresolved or resolved N/A
+ALTER TABLE markets ADD COLUMN resolution_probs DECIMAL(16, 4)[] DEFAULT NULL
+CHECK (array_position(resolution_probs, NULL) IS NULL);
+
+-- how the market resolved: to one outcome, to a probability for each outcome,
+-- or N/A; NULL when unresolved
+ALTER TABLE markets ADD COLUMN resolution_kind TEXT DEFAULT NULL
+CHECK (resolution_kind IN ('outcome', 'probabilities', 'na'));
+
+UPDATE markets
+SET resolution_probs = ARRAY(
+    SELECT CASE WHEN i - 1 = resolution THEN 1.0000 ELSE 0.0000 END
+    FROM generate_subscripts(bought_shares, 1) AS i
+    ORDER BY i
+)
+WHERE is_resolved AND resolution IS NOT NULL;
+
+UPDATE markets
+SET resolution_kind = CASE WHEN resolution IS NULL THEN 'na' ELSE 'outcome' END
+WHERE is_resolved;
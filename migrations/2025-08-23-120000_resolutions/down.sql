@@ -0,0 +1,2 @@
+drop table if exists payouts cascade;
+drop table if exists resolutions cascade;
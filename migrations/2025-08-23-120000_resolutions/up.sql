@@ -0,0 +1,16 @@
+CREATE TABLE resolutions (
+    id SERIAL PRIMARY KEY,
+    market_id INT NOT NULL REFERENCES markets(id),
+    resolution INT DEFAULT NULL,
+    resolution_probs DECIMAL(16, 4)[] DEFAULT NULL, -- both NULL means N/A
+    resolution_kind TEXT NOT NULL CHECK (resolution_kind IN ('outcome', 'probabilities', 'na')),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    unresolved_at TIMESTAMP DEFAULT NULL -- set when the payouts were reversed
+);
+
+CREATE TABLE payouts (
+    id SERIAL PRIMARY KEY,
+    resolution_id INT NOT NULL REFERENCES resolutions(id),
+    user_id TEXT NOT NULL REFERENCES users(id),
+    amount DECIMAL(16, 4) NOT NULL -- adds directly to balance
+);
@@ -0,0 +1,2 @@
+drop table if exists dispute_votes cascade;
+drop table if exists disputes cascade;
@@ -0,0 +1,24 @@
+CREATE TABLE disputes (
+    id SERIAL PRIMARY KEY,
+    market_id INT NOT NULL REFERENCES markets(id),
+    resolution_id INT NOT NULL REFERENCES resolutions(id),
+    opened_by TEXT NOT NULL REFERENCES users(id),
+    -- what the market should resolve to instead, all NULL meaning N/A
+    proposed_resolution INT DEFAULT NULL,
+    proposed_probs DECIMAL(16, 4)[] DEFAULT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    closes_at TIMESTAMP NOT NULL,
+    closed_at TIMESTAMP DEFAULT NULL,
+    overturned BOOLEAN DEFAULT NULL -- set once closed
+);
+
+CREATE UNIQUE INDEX disputes_one_open_per_market ON disputes (market_id) WHERE closed_at IS NULL;
+
+CREATE TABLE dispute_votes (
+    dispute_id INT NOT NULL REFERENCES disputes(id),
+    user_id TEXT NOT NULL REFERENCES users(id),
+    in_favor BOOLEAN NOT NULL, -- in favor of the proposed resolution
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (dispute_id, user_id)
+);
@@ -0,0 +1,14 @@
+CREATE TABLE roles (
+    id SERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    role TEXT NOT NULL CHECK (role IN ('admin', 'moderator')),
+    channel_id TEXT DEFAULT NULL, -- channel a moderator looks after, NULL for admins
+    granted_by TEXT DEFAULT NULL REFERENCES users(id), -- NULL when granted directly in the database
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK ((role = 'moderator') = (channel_id IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX roles_unique ON roles (user_id, role, COALESCE(channel_id, ''));
+
+-- the first admin has to be granted by hand:
+-- INSERT INTO roles (user_id, role) VALUES ('<slack id>', 'admin');
@@ -0,0 +1 @@
+drop table if exists allowance_claims cascade;
@@ -0,0 +1,8 @@
+CREATE TABLE allowance_claims (
+    user_id TEXT NOT NULL REFERENCES users(id),
+    period_start DATE NOT NULL, -- first day of the day or week claimed for
+    period_end DATE NOT NULL CHECK (period_end > period_start), -- the day after its last
+    amount DECIMAL(16, 4) NOT NULL CHECK (amount > 0),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, period_start)
+);
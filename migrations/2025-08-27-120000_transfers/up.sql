@@ -0,0 +1,12 @@
+CREATE TABLE transfers (
+    id SERIAL PRIMARY KEY,
+    from_user_id TEXT NOT NULL REFERENCES users(id),
+    to_user_id TEXT NOT NULL REFERENCES users(id),
+    amount DECIMAL(16, 4) NOT NULL CHECK (amount > 0),
+    memo TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK (from_user_id <> to_user_id)
+);
+
+CREATE INDEX transfers_from_user_id_idx ON transfers (from_user_id);
+CREATE INDEX transfers_to_user_id_idx ON transfers (to_user_id);
@@ -0,0 +1,21 @@
+CREATE TABLE settings (
+    id INT PRIMARY KEY CHECK (id = 1),
+    starting_balance DECIMAL(16, 4) NOT NULL CHECK (starting_balance >= 0),
+    min_liquidity DECIMAL(16, 4) NOT NULL CHECK (min_liquidity > 0),
+    max_liquidity DECIMAL(16, 4) CHECK (max_liquidity >= min_liquidity), -- NULL means no limit
+    min_trade_size DECIMAL(16, 4) NOT NULL CHECK (min_trade_size >= 0), -- in shares
+    default_fee_rate DECIMAL(6, 4) NOT NULL CHECK (default_fee_rate >= 0 AND default_fee_rate <= max_fee_rate),
+    max_fee_rate DECIMAL(6, 4) NOT NULL CHECK (max_fee_rate >= 0 AND max_fee_rate < 1),
+    max_open_markets_per_user INT CHECK (max_open_markets_per_user > 0), -- NULL means no limit
+    allowance_amount DECIMAL(16, 4) NOT NULL CHECK (allowance_amount >= 0),
+    allowance_period TEXT NOT NULL CHECK (allowance_period IN ('daily', 'weekly')),
+    allowance_net_worth_cap DECIMAL(16, 4), -- NULL means no cap
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+-- the values that used to be hardcoded
+INSERT INTO settings (
+    id, starting_balance, min_liquidity, max_liquidity, min_trade_size,
+    default_fee_rate, max_fee_rate, max_open_markets_per_user,
+    allowance_amount, allowance_period, allowance_net_worth_cap
+) VALUES (1, 1000.0000, 100.0000, NULL, 0.0000, 0.0000, 0.1000, NULL, 100.0000, 'daily', NULL);
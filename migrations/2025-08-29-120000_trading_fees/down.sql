@@ -0,0 +1,3 @@
+ALTER TABLE settings DROP COLUMN house_account;
+ALTER TABLE trades DROP COLUMN fee;
+ALTER TABLE markets DROP COLUMN fee_recipient, DROP COLUMN fee_rate;
@@ -0,0 +1,13 @@
+ALTER TABLE markets
+    ADD COLUMN fee_rate DECIMAL(6, 4) NOT NULL DEFAULT 0.0000 CHECK (fee_rate >= 0 AND fee_rate < 1),
+    ADD COLUMN fee_recipient TEXT REFERENCES users(id);
+
+UPDATE markets SET fee_recipient = owner_id;
+
+ALTER TABLE markets ALTER COLUMN fee_recipient SET NOT NULL;
+
+-- charged on top of balance_change, so it never touches the market's bankroll
+ALTER TABLE trades ADD COLUMN fee DECIMAL(16, 4) NOT NULL DEFAULT 0.0000 CHECK (fee >= 0);
+
+-- NULL means fees go to each market's owner
+ALTER TABLE settings ADD COLUMN house_account TEXT REFERENCES users(id);
@@ -0,0 +1,3 @@
+drop index if exists markets_search_idx;
+drop table if exists market_tags cascade;
+alter table markets drop column if exists category;
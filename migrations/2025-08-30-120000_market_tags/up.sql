@@ -0,0 +1,13 @@
+ALTER TABLE markets ADD COLUMN category TEXT;
+
+CREATE TABLE market_tags (
+    market_id INT NOT NULL REFERENCES markets(id),
+    tag TEXT NOT NULL CHECK (tag = lower(tag) AND tag <> ''),
+    PRIMARY KEY (market_id, tag)
+);
+
+CREATE INDEX market_tags_tag_idx ON market_tags (tag);
+
+-- must match the expression search_markets filters on
+CREATE INDEX markets_search_idx ON markets
+USING GIN (to_tsvector('english', title || ' ' || description));
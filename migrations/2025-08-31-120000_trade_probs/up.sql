@@ -0,0 +1,28 @@
+-- market probabilities right after the trade, what the trader's forecast is scored on
+ALTER TABLE trades ADD COLUMN probs_after DECIMAL(16, 4)[] DEFAULT NULL
+CHECK (array_position(probs_after, NULL) IS NULL);
+
+-- replay existing binary markets with the liquidity they had at the time of each trade;
+-- markets that had liquidity added are only approximated, as earlier shares were rescaled
+UPDATE trades
+SET probs_after = ARRAY[round(replayed.p_yes, 4), round(1 - replayed.p_yes, 4)]
+FROM (
+    SELECT
+        t.id,
+        (1 / (1 + exp(LEAST(GREATEST(
+            (sum(CASE WHEN t.share_index = 1 THEN t.shares_amount ELSE 0 END) OVER w
+                - sum(CASE WHEN t.share_index = 0 THEN t.shares_amount ELSE 0 END) OVER w)
+            / COALESCE(
+                (SELECT lc.liquidity_before FROM liquidity_changes lc
+                 WHERE lc.market_id = t.market_id AND lc.created_at > t.created_at
+                 ORDER BY lc.id LIMIT 1),
+                m.liquidity
+            ),
+        -50), 50))))::numeric AS p_yes
+    FROM trades t
+    JOIN markets m ON m.id = t.market_id
+    WINDOW w AS (PARTITION BY t.market_id ORDER BY t.id)
+) AS replayed
+WHERE trades.id = replayed.id;
+
+ALTER TABLE trades ALTER COLUMN probs_after SET NOT NULL;
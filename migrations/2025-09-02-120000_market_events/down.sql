@@ -0,0 +1,4 @@
+drop trigger if exists markets_notify on markets;
+drop trigger if exists trades_notify on trades;
+drop function if exists notify_market_event() cascade;
+drop function if exists notify_trade_event() cascade;
@@ -0,0 +1,50 @@
+-- payloads stay small, NOTIFY rejects anything over 8000 bytes;
+-- listeners fetch the rest of the market by id
+CREATE FUNCTION notify_trade_event() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('market_events', json_build_object(
+        'event', 'trade',
+        'market_id', NEW.market_id,
+        'trade_id', NEW.id,
+        'user_id', NEW.user_id,
+        'share_index', NEW.share_index,
+        'shares_amount', NEW.shares_amount,
+        'balance_change', NEW.balance_change,
+        'fee', NEW.fee,
+        'probs_after', NEW.probs_after,
+        'created_at', extract(epoch FROM NEW.created_at)::bigint
+    )::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION notify_market_event() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        PERFORM pg_notify('market_events', json_build_object(
+            'event', 'market_created',
+            'market_id', NEW.id,
+            'owner_id', NEW.owner_id,
+            'title', left(NEW.title, 200)
+        )::text);
+    ELSIF NEW.is_resolved AND NOT OLD.is_resolved THEN
+        PERFORM pg_notify('market_events', json_build_object(
+            'event', 'market_resolved',
+            'market_id', NEW.id,
+            'resolution', NEW.resolution,
+            'resolution_probs', NEW.resolution_probs
+        )::text);
+    ELSIF OLD.is_resolved AND NOT NEW.is_resolved THEN
+        PERFORM pg_notify('market_events', json_build_object(
+            'event', 'market_unresolved',
+            'market_id', NEW.id
+        )::text);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trades_notify AFTER INSERT ON trades
+    FOR EACH ROW EXECUTE FUNCTION notify_trade_event();
+CREATE TRIGGER markets_notify AFTER INSERT OR UPDATE OF is_resolved ON markets
+    FOR EACH ROW EXECUTE FUNCTION notify_market_event();
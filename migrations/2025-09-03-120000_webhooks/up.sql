@@ -0,0 +1,86 @@
+CREATE TABLE webhooks (
+    id SERIAL PRIMARY KEY,
+    url TEXT NOT NULL,
+    -- payloads are signed with it so receivers can check where they came from
+    secret TEXT NOT NULL,
+    -- event types to deliver, or every event when empty
+    events TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    disabled_at TIMESTAMP
+);
+
+CREATE TABLE webhook_deliveries (
+    id SERIAL PRIMARY KEY,
+    webhook_id INTEGER NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_status INTEGER,
+    last_error TEXT,
+    delivered_at TIMESTAMP,
+    -- set once the delivery ran out of attempts
+    failed_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX webhook_deliveries_pending_idx ON webhook_deliveries (next_attempt_at)
+    WHERE delivered_at IS NULL AND failed_at IS NULL;
+CREATE INDEX webhook_deliveries_webhook_id_idx ON webhook_deliveries (webhook_id);
+
+-- one place every market event goes through: listeners get it right away and
+-- webhooks get a delivery queued in the same transaction as the change
+CREATE FUNCTION emit_market_event(payload JSON) RETURNS VOID AS $$
+BEGIN
+    PERFORM pg_notify('market_events', payload::text);
+    INSERT INTO webhook_deliveries (webhook_id, event, payload)
+        SELECT id, payload->>'event', payload::text
+        FROM webhooks
+        WHERE disabled_at IS NULL
+            AND (cardinality(events) = 0 OR payload->>'event' = ANY(events));
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_trade_event() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM emit_market_event(json_build_object(
+        'event', 'trade',
+        'market_id', NEW.market_id,
+        'trade_id', NEW.id,
+        'user_id', NEW.user_id,
+        'share_index', NEW.share_index,
+        'shares_amount', NEW.shares_amount,
+        'balance_change', NEW.balance_change,
+        'fee', NEW.fee,
+        'probs_after', NEW.probs_after,
+        'created_at', extract(epoch FROM NEW.created_at)::bigint
+    ));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_market_event() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        PERFORM emit_market_event(json_build_object(
+            'event', 'market_created',
+            'market_id', NEW.id,
+            'owner_id', NEW.owner_id,
+            'title', left(NEW.title, 200)
+        ));
+    ELSIF NEW.is_resolved AND NOT OLD.is_resolved THEN
+        PERFORM emit_market_event(json_build_object(
+            'event', 'market_resolved',
+            'market_id', NEW.id,
+            'resolution', NEW.resolution,
+            'resolution_probs', NEW.resolution_probs
+        ));
+    ELSIF OLD.is_resolved AND NOT NEW.is_resolved THEN
+        PERFORM emit_market_event(json_build_object(
+            'event', 'market_unresolved',
+            'market_id', NEW.id
+        ));
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,15 @@
+-- lets clients retry a request without it taking effect twice; the result of
+-- the first request is kept so retries can return it
+CREATE TABLE idempotency_keys (
+    -- keys are each user's own, so one user's key never returns another's result
+    user_id TEXT NOT NULL REFERENCES users(id),
+    key TEXT NOT NULL,
+    action TEXT NOT NULL,
+    -- SHA-256 of the request, so a key reused for a different request fails
+    -- instead of returning the first one's result
+    request_hash TEXT NOT NULL,
+    -- JSON
+    result TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, key)
+);
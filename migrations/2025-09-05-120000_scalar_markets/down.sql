@@ -0,0 +1,6 @@
+ALTER TABLE disputes DROP COLUMN proposed_value;
+
+ALTER TABLE markets
+    DROP COLUMN resolution_value,
+    DROP COLUMN scalar_max,
+    DROP COLUMN scalar_min;
@@ -0,0 +1,13 @@
+-- scalar markets trade LONG (outcome 0) against SHORT (outcome 1) over a range,
+-- and resolve to a value in it, paying LONG the fraction of the range below it
+ALTER TABLE markets
+    ADD COLUMN scalar_min DECIMAL(20, 4),
+    ADD COLUMN scalar_max DECIMAL(20, 4),
+    ADD COLUMN resolution_value DECIMAL(20, 4),
+    ADD CONSTRAINT markets_scalar_range_check
+        CHECK ((scalar_min IS NULL) = (scalar_max IS NULL) AND scalar_min < scalar_max),
+    ADD CONSTRAINT markets_resolution_value_check
+        CHECK (resolution_value IS NULL OR (scalar_min IS NOT NULL AND resolution_value BETWEEN scalar_min AND scalar_max));
+
+-- disputes can propose a value for scalar markets
+ALTER TABLE disputes ADD COLUMN proposed_value DECIMAL(20, 4) DEFAULT NULL;
@@ -0,0 +1,14 @@
+drop table if exists market_outcomes cascade;
+
+ALTER TABLE disputes DROP COLUMN proposed_date;
+
+ALTER TABLE trades
+    DROP CONSTRAINT trades_share_index_check,
+    ADD CONSTRAINT trades_share_index_check CHECK (share_index >= 0 AND share_index < 2);
+
+ALTER TABLE markets
+    DROP COLUMN resolution_date,
+    DROP CONSTRAINT markets_bought_shares_check,
+    DROP CONSTRAINT markets_bought_shares_check2,
+    ADD CONSTRAINT markets_bought_shares_check CHECK (array_length(bought_shares, 1) = 2),
+    ADD CONSTRAINT markets_bought_shares_check2 CHECK (bought_shares[1] >= 0 AND bought_shares[2] >= 0);
@@ -0,0 +1,27 @@
+-- markets can have more than two outcomes, like the date ranges of a date market
+ALTER TABLE markets
+    DROP CONSTRAINT markets_bought_shares_check,
+    DROP CONSTRAINT markets_bought_shares_check2,
+    ADD CONSTRAINT markets_bought_shares_check CHECK (array_length(bought_shares, 1) >= 2),
+    ADD CONSTRAINT markets_bought_shares_check2 CHECK (0 <= ALL(bought_shares)),
+    -- the day a date market resolved to
+    ADD COLUMN resolution_date DATE;
+
+ALTER TABLE trades
+    DROP CONSTRAINT trades_share_index_check,
+    ADD CONSTRAINT trades_share_index_check CHECK (share_index >= 0);
+
+-- names markets' outcomes where YES and NO don't fit
+CREATE TABLE market_outcomes (
+    market_id INTEGER NOT NULL REFERENCES markets(id) ON DELETE CASCADE,
+    share_index INTEGER NOT NULL CHECK (share_index >= 0),
+    label TEXT NOT NULL,
+    -- for date markets, the days the outcome covers: from starts_on up to but
+    -- not including ends_on, with no end for the last one
+    starts_on DATE,
+    ends_on DATE CHECK (ends_on > starts_on),
+    PRIMARY KEY (market_id, share_index)
+);
+
+-- and a day for date markets
+ALTER TABLE disputes ADD COLUMN proposed_date DATE DEFAULT NULL;
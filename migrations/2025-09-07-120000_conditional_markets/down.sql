@@ -0,0 +1,9 @@
+ALTER TABLE resolutions
+    DROP COLUMN parent_resolution_id;
+
+DROP INDEX markets_parent_market_id_idx;
+
+ALTER TABLE markets
+    DROP CONSTRAINT markets_parent_check,
+    DROP COLUMN parent_outcome,
+    DROP COLUMN parent_market_id;
@@ -0,0 +1,13 @@
+-- a conditional market asks its question assuming an outcome of its parent
+-- market happens, and is resolved N/A when it doesn't
+ALTER TABLE markets
+    ADD COLUMN parent_market_id INTEGER REFERENCES markets(id),
+    ADD COLUMN parent_outcome INTEGER CHECK (parent_outcome >= 0),
+    ADD CONSTRAINT markets_parent_check CHECK ((parent_market_id IS NULL) = (parent_outcome IS NULL));
+
+CREATE INDEX markets_parent_market_id_idx ON markets (parent_market_id);
+
+-- the parent's resolution that resolved a conditional market N/A, so
+-- unresolving the parent reopens it
+ALTER TABLE resolutions
+    ADD COLUMN parent_resolution_id INTEGER REFERENCES resolutions(id);
@@ -0,0 +1 @@
+alter table disputes drop column if exists close_error;
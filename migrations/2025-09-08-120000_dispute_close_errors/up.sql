@@ -0,0 +1,2 @@
+-- why a dispute that couldn't be carried out was closed without overturning
+ALTER TABLE disputes ADD COLUMN close_error TEXT;
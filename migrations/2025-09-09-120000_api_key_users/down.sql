@@ -0,0 +1,3 @@
+alter table api_keys drop constraint if exists api_keys_user_check;
+alter table api_keys drop column if exists role;
+alter table api_keys drop column if exists user_id;
@@ -0,0 +1,10 @@
+-- a user key acts as its user, a service key acts as whichever user a
+-- request names, like the Slack bot does; keys made before keys had users
+-- are service keys so they keep working until they're revoked
+ALTER TABLE api_keys
+    ADD COLUMN user_id TEXT REFERENCES users(id),
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'service' CHECK (role IN ('user', 'service')),
+    ADD CONSTRAINT api_keys_user_check CHECK ((role = 'user') = (user_id IS NOT NULL));
+
+ALTER TABLE api_keys
+    ALTER COLUMN role DROP DEFAULT;
@@ -0,0 +1 @@
+alter table trades drop column if exists from_answer;
@@ -0,0 +1,8 @@
+-- adding an answer records its cost and the new answer's shares given to
+-- holders of Other as trades, which move coins and shares like trades do but
+-- aren't anyone's forecast, so statistics and scores leave them out; every
+-- trade says which it is when it's inserted
+ALTER TABLE trades
+    ADD COLUMN from_answer BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE trades
+    ALTER COLUMN from_answer DROP DEFAULT;
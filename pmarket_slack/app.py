@@ -1,14 +1,16 @@
 from datetime import datetime
 import os
+import re
 import json
 import schedule
+import threading
+import time
 from decimal import Decimal
 from dotenv import load_dotenv
 from slack_bolt import App
 from slack_bolt.adapter.socket_mode import SocketModeHandler
 import pmarket_slack.pmarket_slack as ps
 import pmarket_slack.views as views
-import pmarket_slack.utils as utils
 
 load_dotenv()
 
@@ -24,11 +26,93 @@ def handle_app_home_opened(event, client):
         view=view
     )
 
+@app.action("action_claim_allowance")
+def handle_claim_allowance(ack, body, client):
+    ack()
+    user_id = body["user"]["id"]
+    try:
+        amount = ps.claim_allowance(user_id)
+        text = f"You claimed {amount:.0f} :dollar:"
+    except Exception as e:
+        text = str(e)
+    client.chat_postMessage(channel=user_id, text=text)
+    client.views_publish(
+        user_id=user_id,
+        view=views.home_view(user_id)
+    )
+
 @app.command("/pmarket")
 def handle_pmarket_command(ack, command, client):
     user_id = command["user_id"]
     ps.try_create_user(user_id)
     ack()
+    if command["text"].strip() == "leaderboard":
+        client.chat_postEphemeral(
+            channel=command["channel_id"],
+            user=user_id,
+            blocks=views.leaderboard_view(ps.get_leaderboard())["blocks"],
+            text="Best forecasters"
+        )
+        return
+    if command["text"].startswith("resolve"):
+        # resolves a scalar market to a number, a date market to a day or a
+        # free-response market to an answer:
+        # /pmarket resolve <market id> <value, YYYY-MM-DD or answer>
+        args = command["text"].split(maxsplit=2)
+        try:
+            if len(args) != 3:
+                raise ValueError("Usage: /pmarket resolve <market id> <value, YYYY-MM-DD or answer>")
+            market_id = int(args[1])
+            market_type = ps.get_market_data(market_id)["market_type"]
+            if market_type == "free_response":
+                ps.resolve_market_answer(market_id, args[2], user_id, idempotency_key=command["trigger_id"])
+            elif market_type == "date":
+                ps.resolve_market_date(market_id, args[2], user_id, idempotency_key=command["trigger_id"])
+            else:
+                ps.resolve_market_value(market_id, Decimal(args[2]), user_id, idempotency_key=command["trigger_id"])
+            text = f"Resolved market {market_id} to {args[2]}"
+        except Exception as e:
+            text = str(e)
+        else:
+            market_data = ps.get_market_data(market_id)
+            msm = market_data["main_slack_msg"]
+            if msm["exists"]:
+                client.chat_update(
+                    channel=msm["channel_id"],
+                    ts=msm["ts"],
+                    blocks=views.pmarket_view(market_id)["blocks"],
+                    text=f"Resolution at market \"{market_data['title']}\"",
+                    metadata={
+                        "event_type": "pmarket_resolved",
+                        "event_payload": {
+                            "market_id": market_id,
+                        }
+                    }
+                )
+            update_conditional_markets(market_id)
+        client.chat_postEphemeral(
+            channel=command["channel_id"],
+            user=user_id,
+            text=text
+        )
+        return
+    if command["text"].startswith("search"):
+        # words starting with # are tags, the rest is the text query
+        words = command["text"][len("search"):].split()
+        tags = [word for word in words if word.startswith("#")]
+        query = " ".join(word for word in words if not word.startswith("#"))
+        try:
+            markets = ps.search_markets(query, tags)
+            blocks = views.search_results_view(query, markets)["blocks"]
+        except Exception as e:
+            blocks = [{"type": "section", "text": {"type": "mrkdwn", "text": str(e)}}]
+        client.chat_postEphemeral(
+            channel=command["channel_id"],
+            user=user_id,
+            blocks=blocks,
+            text=f"Markets matching \"{query}\""
+        )
+        return
     view = views.pmarket_add_view(
         command["text"], 
         channel_id=command["channel_id"],
@@ -40,8 +124,68 @@ def handle_pmarket_command(ack, command, client):
         view=view
     )
 
+@app.command("/tip")
+def handle_tip_command(ack, command, client):
+    ack()
+    user_id = command["user_id"]
+    ps.try_create_user(user_id)
+    # expects "@someone amount [memo]", slack sends the mention as <@U123|name>
+    match = re.match(r"^\s*<@(\w+)(?:\|[^>]*)?>\s+([0-9.]+)\s*(.*)$", command["text"])
+    if match is None:
+        client.chat_postEphemeral(
+            channel=command["channel_id"],
+            user=user_id,
+            text="Usage: /tip @someone amount [memo]"
+        )
+        return
+    to_user_id, amount, memo = match.groups()
+    try:
+        amount = Decimal(amount)
+        ps.transfer(user_id, to_user_id, amount, memo or None)
+    except Exception as e:
+        client.chat_postEphemeral(
+            channel=command["channel_id"],
+            user=user_id,
+            text=str(e)
+        )
+        return
+    text = f"<@{user_id}> sent you {amount:.2f} :dollar:"
+    if memo:
+        text += f": {memo}"
+    client.chat_postMessage(channel=to_user_id, text=text)
+    client.chat_postEphemeral(
+        channel=command["channel_id"],
+        user=user_id,
+        text=f"Sent {amount:.2f} :dollar: to <@{to_user_id}>"
+    )
+
+@app.command("/pmarket-config")
+def handle_config_command(ack, command, client):
+    ack()
+    user_id = command["user_id"]
+    # no arguments lists the settings, "key value" changes one
+    args = command["text"].split()
+    if len(args) == 2:
+        try:
+            ps.update_setting(user_id, args[0], args[1])
+            text = f"Set `{args[0]}` to `{args[1]}`"
+        except Exception as e:
+            text = str(e)
+    elif len(args) == 0:
+        settings = ps.get_settings()
+        text = "\n".join(
+            f"`{key}`: {value}" for key, value in settings.items() if key != "updated_at"
+        )
+    else:
+        text = "Usage: /pmarket-config [setting value]"
+    client.chat_postEphemeral(
+        channel=command["channel_id"],
+        user=user_id,
+        text=text
+    )
+
 @app.view("pmarket_add_view")
-def handle_pmarket_add(ack, body, view, say):
+def handle_pmarket_add(ack, body, view, say, client):
     values = list(view["state"]["values"].values())
     values = {k: v for d in values for k, v in d.items()}
     title = values["action_title_pmarket_add"]["value"]
@@ -49,14 +193,43 @@ def handle_pmarket_add(ack, body, view, say):
     if description is None:
         description = " "
     liquidity = float(values["action_liquidity_pmarket_add"]["value"])
-    if liquidity < 100:
+    fee_percent = values["action_fee_pmarket_add"].get("value")
+    fee_rate = Decimal(fee_percent) / 100 if fee_percent else None
+    # conditional markets are YES/NO, so their modal leaves out the other types
+    range_min = values.get("action_range_min_pmarket_add", {}).get("value")
+    range_max = values.get("action_range_max_pmarket_add", {}).get("value")
+    if (range_min is None) != (range_max is None):
         ack({
-            "response_action": "errors", 
+            "response_action": "errors",
+            "errors": {
+                "block_range_max_pmarket_add": "Give both the lowest and the highest answer, or neither"
+            }
+        })
+        return
+    date_start = values.get("action_date_start_pmarket_add", {}).get("selected_date")
+    date_end = values.get("action_date_end_pmarket_add", {}).get("selected_date")
+    selected_bucket = values.get("action_bucket_pmarket_add", {}).get("selected_option")
+    bucket_size = selected_bucket["value"] if selected_bucket else "week"
+    if (date_start is None) != (date_end is None):
+        ack({
+            "response_action": "errors",
             "errors": {
-                "block_liquidity_pmarket_add": "Liquidity must be at least 100"
+                "block_date_end_pmarket_add": "Give both the earliest and the latest date, or neither"
             }
         })
         return
+    answers = (values.get("action_answers_pmarket_add", {}).get("value") or "").splitlines()
+    answers = [answer.strip() for answer in answers if answer.strip()]
+    if sum([range_min is not None, date_start is not None, bool(answers)]) > 1:
+        ack({
+            "response_action": "errors",
+            "errors": {
+                "block_answers_pmarket_add": "A question is about a number, a date or open answers, pick one"
+            }
+        })
+        return
+    category = values["action_category_pmarket_add"].get("value")
+    tags = (values["action_tags_pmarket_add"].get("value") or "").replace(",", " ").split()
     remind_at = values["action_remind_pmarket_add"]["selected_date"]
     remind_at = datetime.strptime(remind_at, "%Y-%m-%d")
     if remind_at <= datetime.now():
@@ -78,15 +251,97 @@ def handle_pmarket_add(ack, body, view, say):
             }
         })
         return
-    ack()
     private_metadata = json.loads(view["private_metadata"])
-    market_id = ps.create_market(
-        title,
-        description,
-        user_id,
-        Decimal(liquidity),
-        remind_at
-    )
+    try:
+        if private_metadata.get("parent_market_id") is not None:
+            market_id = ps.create_conditional_market(
+                title,
+                description,
+                user_id,
+                Decimal(liquidity),
+                remind_at,
+                private_metadata["parent_market_id"],
+                private_metadata["parent_outcome"],
+                fee_rate,
+                idempotency_key=view["id"]
+            )
+        elif range_min is not None:
+            market_id = ps.create_scalar_market(
+                title,
+                description,
+                user_id,
+                Decimal(liquidity),
+                remind_at,
+                Decimal(range_min),
+                Decimal(range_max),
+                fee_rate,
+                idempotency_key=view["id"]
+            )
+        elif answers:
+            market_id = ps.create_free_response_market(
+                title,
+                description,
+                user_id,
+                Decimal(liquidity),
+                remind_at,
+                answers,
+                fee_rate,
+                idempotency_key=view["id"]
+            )
+        elif date_start is not None:
+            market_id = ps.create_date_market(
+                title,
+                description,
+                user_id,
+                Decimal(liquidity),
+                remind_at,
+                date_start,
+                date_end,
+                bucket_size,
+                fee_rate,
+                idempotency_key=view["id"]
+            )
+        else:
+            market_id = ps.create_market(
+                title,
+                description,
+                user_id,
+                Decimal(liquidity),
+                remind_at,
+                fee_rate,
+                # Slack retries submissions it didn't see acknowledged in time
+                idempotency_key=view["id"]
+            )
+    except Exception as e:
+        if "Fee" in str(e):
+            block_id = "block_fee_pmarket_add"
+        elif "range" in str(e):
+            block_id = "block_range_max_pmarket_add"
+        elif "date" in str(e) or "buckets" in str(e):
+            block_id = "block_date_end_pmarket_add"
+        elif "answer" in str(e):
+            block_id = "block_answers_pmarket_add"
+        elif "conditional" in str(e) or "outcome" in str(e):
+            block_id = "block_title_pmarket_add"
+        else:
+            block_id = "block_liquidity_pmarket_add"
+        ack({
+            "response_action": "errors",
+            "errors": {
+                block_id: str(e)
+            }
+        })
+        return
+    ack()
+    if category or tags:
+        try:
+            ps.categorize_market(market_id, user_id, category, tags)
+        except Exception as e:
+            client.chat_postEphemeral(
+                channel=private_metadata["channel_id"],
+                user=user_id,
+                text=f"Your market was created without tags: {e}"
+            )
     view = views.pmarket_view(market_id)
     res = say(
         channel=private_metadata["channel_id"],
@@ -104,17 +359,14 @@ def handle_pmarket_add(ack, body, view, say):
     ts = res["message"]["ts"]
     ps.create_market_slack_msg(market_id, channel_id, ts, True)
 
-def handle_general_trade(ack, body, buy_or_sell: bool, yes_or_no: bool):
+def handle_general_trade(ack, body, buy_or_sell: bool, share_index: int):
     ack()
     market_id = int(body['message']['metadata']['event_payload']['market_id'])
     channel_id = body["container"]["channel_id"]
     ts = body["container"]["message_ts"]
-    market_data = ps.get_market_data(market_id)
     user_id = body["user"]["id"]
-    user = ps.get_user_data(user_id)
-    balance = user["balance"]
-    user_position = ps.get_positions(market_id).get(user_id, [0, 0])
-    view = views.trade_view(market_data, balance, user_position, 0, buy_or_sell, yes_or_no, channel_id, ts)
+    ps.try_create_user(user_id)
+    view = views.trade_view(market_id, user_id, 0, buy_or_sell, share_index, channel_id, ts)
     app.client.views_open(
         trigger_id=body["trigger_id"],
         view=view
@@ -122,33 +374,57 @@ def handle_general_trade(ack, body, buy_or_sell: bool, yes_or_no: bool):
 
 @app.action("action_buy_yes")
 def handle_buy_yes(ack, body):
-    handle_general_trade(ack, body, True, True)
+    handle_general_trade(ack, body, True, 0)
 
 @app.action("action_buy_no")
 def handle_buy_no(ack, body):
-    handle_general_trade(ack, body, True, False)
+    handle_general_trade(ack, body, True, 1)
 
 @app.action("action_sell_yes")
 def handle_sell_yes(ack, body):
-    handle_general_trade(ack, body, False, True)
+    handle_general_trade(ack, body, False, 0)
     
 @app.action("action_sell_no")
 def handle_sell_no(ack, body):
-    handle_general_trade(ack, body, False, False)
+    handle_general_trade(ack, body, False, 1)
+
+def open_conditional_add_view(ack, body, share_index: int):
+    ack()
+    market_id = int(body['message']['metadata']['event_payload']['market_id'])
+    view = views.pmarket_add_view(
+        "",
+        channel_id=body["container"]["channel_id"],
+        thread_ts=body["container"].get("thread_ts"),
+        creator_id=body["user"]["id"],
+        parent_market_id=market_id,
+        parent_outcome=share_index
+    )
+    app.client.views_open(
+        trigger_id=body["trigger_id"],
+        view=view
+    )
+
+# the menu next to each outcome of a date market, with values like "buy_3",
+# or "if_3" to ask a question conditional on it
+@app.action(re.compile(r"action_trade_outcome_\d+"))
+def handle_trade_outcome(ack, body):
+    option, share_index = body["actions"][0]["selected_option"]["value"].split("_")
+    if option == "if":
+        open_conditional_add_view(ack, body, int(share_index))
+        return
+    handle_general_trade(ack, body, option == "buy", int(share_index))
 
-def handle_general_shares_trade(ack, body, buy_or_sell: bool, yes_or_no: bool):
+def handle_general_shares_trade(ack, body, buy_or_sell: bool):
     ack()
     private_metadata = json.loads(body["view"]["private_metadata"])
-    market_data = private_metadata["market_data"]
     shares_amount = float(body["actions"][0]["value"])
     hashh = body["view"]["hash"]
     view = views.trade_view(
-        market_data,
-        private_metadata["balance"],
-        private_metadata["user_positions"],
+        private_metadata["market_id"],
+        body["user"]["id"],
         shares_amount,
         buy_or_sell,
-        yes_or_no,
+        private_metadata["share_index"],
         private_metadata["channel_id"],
         private_metadata["ts"]
     )
@@ -161,68 +437,89 @@ def handle_general_shares_trade(ack, body, buy_or_sell: bool, yes_or_no: bool):
 
 @app.action("action_shares_buy_yes")
 def handle_shares_buy_yes(ack, body):
-    handle_general_shares_trade(ack, body, True, True)
+    handle_general_shares_trade(ack, body, True)
 
 @app.action("action_shares_buy_no")
 def handle_shares_buy_no(ack, body):
-    handle_general_shares_trade(ack, body, True, False)
+    handle_general_shares_trade(ack, body, True)
     
 @app.action("action_shares_sell_yes")
 def handle_shares_sell_yes(ack, body):
-    handle_general_shares_trade(ack, body, False, True)
+    handle_general_shares_trade(ack, body, False)
 
 @app.action("action_shares_sell_no")
 def handle_shares_sell_no(ack, body):
-    handle_general_shares_trade(ack, body, False, False)
+    handle_general_shares_trade(ack, body, False)
 
-def handle_general_trade_view(ack, body, view, buy_or_sell: bool, yes_or_no: bool):
+@app.action(re.compile(r"action_shares_(buy|sell)_outcome_\d+"))
+def handle_shares_outcome(ack, body):
+    handle_general_shares_trade(ack, body, body["actions"][0]["action_id"].startswith("action_shares_buy"))
+
+def handle_general_trade_view(ack, body, view, buy_or_sell: bool):
     values = list(view["state"]["values"].values())
     values = {k: v for d in values for k, v in d.items()}
     private_metadata = json.loads(view["private_metadata"])
-    market_id = private_metadata["market_data"]["id"]
+    market_id = private_metadata["market_id"]
+    share_index = private_metadata["share_index"]
+    outcome_key = private_metadata["outcome_key"]
     buysell = "buy" if buy_or_sell else "sell"
-    shares_amount = float(values[f"action_shares_{buysell}_" + ('yes' if yes_or_no else 'no')]["value"])
+    shares_amount = float(values[f"action_shares_{buysell}_{outcome_key}"]["value"])
     user_id = body["user"]["id"]
-    user = ps.get_user_data(user_id)
     if buy_or_sell:
-        bet_amount = utils.bet_amount(
-            ps.get_market_data(market_id),
-            shares_amount,
-            yes_or_no
-        )
-        if user["balance"] < bet_amount:
+        quote = ps.quote_trade(market_id, user_id, share_index, shares=shares_amount)
+        if not quote["affordable"]:
+            ack({
+                "response_action": "errors",
+                "errors": {
+                    f"block_shares_buy_{outcome_key}": f"Not enough funds. Balance: {quote['balance']:.0f}, Bet amount: {quote['cost']:.0f}"
+                }
+            })
+            return
+        try:
+            ps.create_trade(
+                market_id,
+                user_id,
+                shares_amount,
+                share_index,
+                idempotency_key=view["id"]
+            )
+        except Exception as e:
             ack({
                 "response_action": "errors",
                 "errors": {
-                    f"block_shares_buy_{'yes' if yes_or_no else 'no'}": f"Not enough funds. Balance: {user['balance']:.0f}, Bet amount: {bet_amount:.0f}"
+                    f"block_shares_buy_{outcome_key}": str(e)
                 }
             })
             return
         ack()
-        ps.create_trade(
-            market_id,
-            user_id,
-            shares_amount,
-            0 if yes_or_no else 1
-        )
     else:
-        user_position = ps.get_positions(market_id).get(user_id, [0, 0])
-        position = user_position[0] if yes_or_no else user_position[1]
-        if position < shares_amount:
+        quote = ps.quote_trade(market_id, user_id, share_index, shares=-shares_amount)
+        position = quote["position"] + shares_amount
+        if quote["position"] < 0:
             ack({
                 "response_action": "errors",
                 "errors": {
-                    f"block_shares_sell_{'yes' if yes_or_no else 'no'}": f"Not enough shares. Position: {position:.0f}, Shares to sell: {shares_amount:.0f}"
+                    f"block_shares_sell_{outcome_key}": f"Not enough shares. Position: {position:.0f}, Shares to sell: {shares_amount:.0f}"
+                }
+            })
+            return
+        try:
+            ps.create_trade(
+                market_id,
+                user_id,
+                -shares_amount,
+                share_index,
+                idempotency_key=view["id"]
+            )
+        except Exception as e:
+            ack({
+                "response_action": "errors",
+                "errors": {
+                    f"block_shares_sell_{outcome_key}": str(e)
                 }
             })
             return
         ack()
-        ps.create_trade(
-            market_id,
-            user_id,
-            -shares_amount,
-            0 if yes_or_no else 1
-        )
     market_data = ps.get_market_data(market_id)
     view = views.pmarket_view(market_id)
     app.client.chat_update(
@@ -240,33 +537,120 @@ def handle_general_trade_view(ack, body, view, buy_or_sell: bool, yes_or_no: boo
 
 @app.view("buy_view_yes")
 def handle_buy_view_yes(ack, body, view):
-    handle_general_trade_view(ack, body, view, True, True)
+    handle_general_trade_view(ack, body, view, True)
 
 @app.view("buy_view_no")
 def handle_buy_view_no(ack, body, view):
-    handle_general_trade_view(ack, body, view, True, False)
+    handle_general_trade_view(ack, body, view, True)
 
 @app.view("sell_view_yes")
 def handle_sell_view_yes(ack, body, view):
-    handle_general_trade_view(ack, body, view, False, True)
+    handle_general_trade_view(ack, body, view, False)
 
 @app.view("sell_view_no")
 def handle_sell_view_no(ack, body, view):
-    handle_general_trade_view(ack, body, view, False, False)
+    handle_general_trade_view(ack, body, view, False)
+
+@app.view(re.compile(r"(buy|sell)_view_outcome_\d+"))
+def handle_outcome_view(ack, body, view):
+    handle_general_trade_view(ack, body, view, view["callback_id"].startswith("buy"))
+
+@app.action("action_add_answer")
+def handle_add_answer(ack, body):
+    ack()
+    market_id = int(body['message']['metadata']['event_payload']['market_id'])
+    user_id = body["user"]["id"]
+    ps.try_create_user(user_id)
+    view = views.add_answer_view(
+        market_id,
+        user_id,
+        body["container"]["channel_id"],
+        body["container"]["message_ts"]
+    )
+    app.client.views_open(
+        trigger_id=body["trigger_id"],
+        view=view
+    )
+
+@app.view("add_answer_view")
+def handle_add_answer_view(ack, body, view):
+    values = list(view["state"]["values"].values())
+    values = {k: v for d in values for k, v in d.items()}
+    private_metadata = json.loads(view["private_metadata"])
+    market_id = private_metadata["market_id"]
+    answer = values["action_answer_add_answer"]["value"]
+    try:
+        ps.add_answer(market_id, body["user"]["id"], answer, idempotency_key=view["id"])
+    except Exception as e:
+        ack({
+            "response_action": "errors",
+            "errors": {
+                "block_answer_add_answer": str(e)
+            }
+        })
+        return
+    ack()
+    market_data = ps.get_market_data(market_id)
+    view = views.pmarket_view(market_id)
+    app.client.chat_update(
+        channel=private_metadata["channel_id"],
+        ts=private_metadata["ts"],
+        blocks=view["blocks"],
+        text=f"New answer at market: \"{market_data['title']}\"",
+        metadata={
+            "event_type": "pmarket_trade",
+            "event_payload": {
+                "market_id": market_id,
+            }
+        }
+    )
+
+# resolving a market resolves its conditional markets N/A unless their condition
+# was met, and unresolving it reopens them
+def update_conditional_markets(market_id: int):
+    for child_id in ps.get_market_data(market_id)["conditional_market_ids"]:
+        child = ps.get_market_data(child_id)
+        msm = child["main_slack_msg"]
+        if msm["exists"]:
+            app.client.chat_update(
+                channel=msm["channel_id"],
+                ts=msm["ts"],
+                blocks=views.pmarket_view(child_id)["blocks"],
+                text=f"Resolution at market \"{child['title']}\"",
+                metadata={
+                    "event_type": "pmarket_resolved",
+                    "event_payload": {
+                        "market_id": child_id,
+                    }
+                }
+            )
+        update_conditional_markets(child_id)
 
 @app.action("options_menu")
 def handle_options_menu(ack, body):
+    value = body['actions'][0]['selected_option']['value']
+    conditions = {"if_yes": 0, "if_no": 1}
+    if value in conditions:
+        open_conditional_add_view(ack, body, conditions[value])
+        return
     ack()
     market_id = int(body['message']['metadata']['event_payload']['market_id'])
-    value = body['actions'][0]['selected_option']['value']
-    if value == "resolve_yes":
-        ps.resolve_market(market_id, 0)
-    elif value == "resolve_no":
-        ps.resolve_market(market_id, 1)
-    elif value == "resolve_na":
-        ps.resolve_market(market_id, None)
-    else:
+    user_id = body["user"]["id"]
+    resolutions = {"resolve_yes": 0, "resolve_no": 1, "resolve_na": None}
+    if value not in resolutions and value != "unresolve":
         raise ValueError(f"Unknown option value: {value}")
+    try:
+        if value == "unresolve":
+            ps.unresolve_market(market_id, user_id)
+        else:
+            ps.resolve_market(market_id, resolutions[value], user_id, idempotency_key=body["trigger_id"])
+    except Exception as e:
+        app.client.chat_postEphemeral(
+            channel=body["container"]["channel_id"],
+            user=user_id,
+            text=str(e)
+        )
+        return
     market_data = ps.get_market_data(market_id)
     view = views.pmarket_view(market_id)
     app.client.chat_update(
@@ -281,6 +665,7 @@ def handle_options_menu(ack, body):
             }
         }
     )
+    update_conditional_markets(market_id)
 
 def reminder_job():
     market_ids = ps.get_reminders_and_update_time()
@@ -297,8 +682,43 @@ def reminder_job():
             text=f"Reminder for market: \"{market_data['title']}\"",
         )
 
+def dispute_job():
+    market_ids = ps.close_expired_disputes()
+    for market_id in market_ids:
+        update_conditional_markets(market_id)
+        market_data = ps.get_market_data(market_id)
+        msm = market_data["main_slack_msg"]
+        if not msm["exists"]:
+            continue
+        view = views.pmarket_view(market_id)
+        app.client.chat_update(
+            channel=msm["channel_id"],
+            ts=msm["ts"],
+            blocks=view["blocks"],
+            text=f"Dispute overturned resolution at market \"{market_data['title']}\"",
+            metadata={
+                "event_type": "pmarket_resolved",
+                "event_payload": {
+                    "market_id": market_id,
+                }
+            }
+        )
+
+# handler.start() blocks, so the jobs run on their own thread; a job that
+# fails is logged and tried again a minute later
+def run_scheduler():
+    while True:
+        try:
+            schedule.run_pending()
+        except Exception as e:
+            print(f"Scheduled job failed: {e}")
+        time.sleep(60)
+
 def main():
+    ps.load_settings()
     schedule.every().hour.do(reminder_job)
+    schedule.every().hour.do(dispute_job)
+    threading.Thread(target=run_scheduler, daemon=True).start()
     handler = SocketModeHandler(app, app_token=os.environ.get("SLACK_APP_TOKEN"))
     handler.start()
 
@@ -1,4 +1,3 @@
-from copy import deepcopy
 from datetime import datetime
 import json
 import pmarket_slack.pmarket_slack as ps
@@ -9,205 +8,36 @@ false = False
 def home_view(
     user_id: str
 ):
-    user = ps.get_user_data(user_id)
-    balance = user["balance"]
-    return {
-        "type": "home",
-        "blocks": [
-            {
-                "type": "header",
-                "text": {
-                    "type": "plain_text",
-                    "text": "Your account",
-                    "emoji": True
-                }
-            },
-            {
-                "type": "section",
-                "text": {
-                    "type": "mrkdwn",
-                    "text": f"*Balance*: {balance:.0f} :dollar:"
-                }
-            }
-        ]
-    }
+    return ps.render_home_view(user_id)
 
 def pmarket_view(
     market_id: int
 ):
-    market = ps.get_market_data(market_id)
-    is_resolved = market['is_resolved']
-    resolution = market['resolution']
-    resolution_text = ":question: N/A"
-    if resolution is not None:
-        resolution_text = ":white_check_mark: YES" if resolution == 0 else ":x: NO"
-
-    menu_options = [
-        {
-            "text": {
-                "type": "plain_text",
-                "text": "Resolve :white_check_mark: YES"
-            },
-            "value": "resolve_yes"
-        },
-        {
-            "text": {
-                "type": "plain_text",
-                "text": "Resolve :x: NO"
-            },
-            "value": "resolve_no"
-        },
-        {
-            "text": {
-                "type": "plain_text",
-                "text": "Resolve :question: N/A"
-            },
-            "value": "resolve_na"
-        }
-    ]
-    context_elements = [
-        {
-            "type": "mrkdwn",
-            "text": f"*{market['liquidity']:.0f}* :dollar: liquidity"
-        },
-        {
-            "type": "mrkdwn",
-            "text": f"Resolves on *<!date^{market['remind_at']}^{{date_num}}|{datetime.fromtimestamp(market['remind_at']).strftime('%Y-%m-%d')}>*"
-        }
-    ]
-    probability_section = [
-        {
-            "type": "section",
-            "text": {
-                "type": "mrkdwn",
-                "text": f"*{market['prob'][0]*100:.0f}%* chance"
-            },
-        },
-    ]
-    if is_resolved:
-        menu_options = [
-            {
-                "text": {
-                    "type": "plain_text",
-                    "text": "Unresolve"
-                },
-                "value": "unresolve"
-            },
-        ]
-        probability_section = [
-            {
-                "type": "section",
-                "text": {
-                    "type": "mrkdwn",
-                    "text": f"Resolved *{resolution_text}*"
-                },
-            },
-        ]
-        context_elements = [
-            {
-                "type": "mrkdwn",
-                "text": f"*{market['liquidity']:.0f}* :dollar: liquidity"
-            }
-        ]
+    return ps.render_market_view(market_id)
 
-    return {
-        "blocks": [
-            {
-                "type": "section",
-                "text": {
-                    "type": "mrkdwn",
-                    "text": f"*{market['title']}*"
-                },
-                "accessory": {
-                    "type": "overflow",
-                    "options": menu_options,
-                    "action_id": "options_menu"
-                }
-            },
-            *probability_section,
-            {
-                "type": "context",
-                "elements": context_elements
-            },
-            {
-                "type": "section",
-                "text": {
-                    "type": "mrkdwn",
-                    "text": f"{market['description']}"
-                },
-            },
-            {
-                "type": "actions",
-                "elements": [
-                    {
-                        "type": "button",
-                        "text": {
-                            "type": "plain_text",
-                            "text": "Buy :white_check_mark: YES shares",
-                            "emoji": true
-                        },
-                        "value": "yes",
-                        "action_id": "action_buy_yes"
-                    },
-                    {
-                        "type": "button",
-                        "text": {
-                            "type": "plain_text",
-                            "text": "Buy :x: NO shares",
-                            "emoji": true
-                        },
-                        "value": "no",
-                        "action_id": "action_buy_no"
-                    }
-                ]
-            },
-            {
-                "type": "actions",
-                "elements": [
-                    {
-                        "type": "button",
-                        "text": {
-                            "type": "plain_text",
-                            "text": "Sell :white_check_mark: YES shares",
-                            "emoji": true
-                        },
-                        "value": "yes",
-                        "action_id": "action_sell_yes"
-                    },
-                    {
-                        "type": "button",
-                        "text": {
-                            "type": "plain_text",
-                            "text": "Sell :x: NO shares",
-                            "emoji": true
-                        },
-                        "value": "no",
-                        "action_id": "action_sell_no"
-                    }
-                ]
-            },
-            {
-                "type": "context",
-                "elements": [
-                    {
-                        "type": "mrkdwn",
-                        "text": f"_Created by <@{market['owner_id']}> using /pmarket_"
-                    },
-                ]
-            },
-        ]
-    }
+def outcome_label(
+    market_data: dict,
+    share_index: int
+):
+    if market_data["outcomes"]:
+        return market_data["outcomes"][share_index]["label"]
+    return ["YES", "NO"][share_index]
 
+# with a parent market, the new market is a YES/NO question that only counts if
+# the parent resolves to `parent_outcome`
 def pmarket_add_view(
     title: str,
     channel_id: str,
     thread_ts: str | None,
-    creator_id: str
+    creator_id: str,
+    parent_market_id: int | None = None,
+    parent_outcome: int | None = None
 ):
     creator = ps.get_user_data(creator_id)
     balance = creator["balance"]
+    settings = ps.get_settings()
 
-    return {
+    view = {
         "type": "modal",
         "callback_id": "pmarket_add_view",
         "title": {
@@ -272,7 +102,7 @@ def pmarket_add_view(
                     "action_id": "action_liquidity_pmarket_add",
                     "placeholder": {
                         "type": "plain_text",
-                        "text": "100"
+                        "text": f"{settings['min_liquidity']:.0f}"
                     }
                 },
                 "label": {
@@ -281,6 +111,176 @@ def pmarket_add_view(
                     "emoji": true
                 }
             },
+            {
+                "type": "input",
+                "block_id": "block_fee_pmarket_add",
+                "element": {
+                    "type": "number_input",
+                    "is_decimal_allowed": true,
+                    "action_id": "action_fee_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": f"{settings['default_fee_rate']*100:g}"
+                    }
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": f"Trading fee % (at most {settings['max_fee_rate']*100:g})",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_range_min_pmarket_add",
+                "element": {
+                    "type": "number_input",
+                    "is_decimal_allowed": true,
+                    "action_id": "action_range_min_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "0"
+                    }
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Lowest answer, for questions about a number",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_range_max_pmarket_add",
+                "element": {
+                    "type": "number_input",
+                    "is_decimal_allowed": true,
+                    "action_id": "action_range_max_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "100"
+                    }
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Highest answer",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_date_start_pmarket_add",
+                "element": {
+                    "type": "datepicker",
+                    "action_id": "action_date_start_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "Select a date"
+                    },
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Earliest date, for questions about when something happens",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_date_end_pmarket_add",
+                "element": {
+                    "type": "datepicker",
+                    "action_id": "action_date_end_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "Select a date"
+                    },
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Latest date",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_bucket_pmarket_add",
+                "element": {
+                    "type": "static_select",
+                    "action_id": "action_bucket_pmarket_add",
+                    "initial_option": {
+                        "text": {"type": "plain_text", "text": "Week"},
+                        "value": "week"
+                    },
+                    "options": [
+                        {"text": {"type": "plain_text", "text": "Week"}, "value": "week"},
+                        {"text": {"type": "plain_text", "text": "Month"}, "value": "month"}
+                    ]
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Trade on dates by",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_answers_pmarket_add",
+                "element": {
+                    "type": "plain_text_input",
+                    "multiline": true,
+                    "action_id": "action_answers_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "One per line, anyone can add more later"
+                    }
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Answers, for open questions",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_category_pmarket_add",
+                "element": {
+                    "type": "plain_text_input",
+                    "action_id": "action_category_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "Politics"
+                    }
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Category",
+                    "emoji": true
+                },
+                "optional": true
+            },
+            {
+                "type": "input",
+                "block_id": "block_tags_pmarket_add",
+                "element": {
+                    "type": "plain_text_input",
+                    "action_id": "action_tags_pmarket_add",
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "#elections #2026"
+                    }
+                },
+                "label": {
+                    "type": "plain_text",
+                    "text": "Tags",
+                    "emoji": true
+                },
+                "optional": true
+            },
             {
                 "type": "input",
                 "block_id": "block_remind_pmarket_add",
@@ -311,125 +311,115 @@ def pmarket_add_view(
         ],
         "private_metadata": json.dumps({
             "channel_id": channel_id,
-            "thread_ts": thread_ts
+            "thread_ts": thread_ts,
+            "parent_market_id": parent_market_id,
+            "parent_outcome": parent_outcome
         })
     }
+    if parent_market_id is None:
+        return view
 
-def trade_view(
-    market_data,
-    balance: float,
-    user_positions: list[float],
-    shares_amount: float,
-    buy_or_sell: bool,
-    yes_or_no: bool,
-    channel_id: str,
-    ts: str
+    parent = ps.get_market_data(parent_market_id)
+    other_types = {
+        "block_range_min_pmarket_add",
+        "block_range_max_pmarket_add",
+        "block_date_start_pmarket_add",
+        "block_date_end_pmarket_add",
+        "block_bucket_pmarket_add",
+        "block_answers_pmarket_add",
+    }
+    view["blocks"] = [
+        {
+            "type": "context",
+            "elements": [
+                {
+                    "type": "mrkdwn",
+                    "text": f"Only counts if *{parent['title']}* resolves *{outcome_label(parent, parent_outcome)}*, N/A otherwise"
+                },
+            ]
+        },
+        *[block for block in view["blocks"] if block.get("block_id") not in other_types],
+    ]
+    return view
+
+def leaderboard_view(
+    scores: list
 ):
-    yesno = "yes" if yes_or_no else "no"
-    YESNO = "YES" if yes_or_no else "NO"
-    buysell = "buy" if buy_or_sell else "sell"
-    BuySell = "Buy" if buy_or_sell else "Sell"
-    yesnoem = ":white_check_mark:" if yes_or_no else ":x:"
-    position = user_positions[0] if yes_or_no else user_positions[1]
-    bet_amount_or_payoff_display = "Bet amount" if buy_or_sell else "Payoff"
-    shares_bef = market_data["bought_shares"]
-    lmsr_bef = ps.get_lmsr_info(deepcopy(shares_bef), market_data["liquidity"])
-    shares_aft = deepcopy(shares_bef)
-    shares_change = shares_amount if buy_or_sell else -shares_amount
-    if yes_or_no:
-        shares_aft[0] += shares_change
+    if not scores:
+        text = "Nobody has traded on enough resolved markets yet"
     else:
-        shares_aft[1] += shares_change
-    lmsr_aft = ps.get_lmsr_info(deepcopy(shares_aft), market_data["liquidity"])
-    balance_or_position_display = f"Position: *{position:.0f}* {yesnoem} {YESNO} shares"
-    if buy_or_sell:
-        balance_or_position_display = f"Balance: *{balance:.0f}* :dollar:"
-    bet_amount_or_payoff = lmsr_aft['cost_func'] - lmsr_bef['cost_func'] if buy_or_sell else lmsr_bef['cost_func'] - lmsr_aft['cost_func']
+        # brier skill is how much lower a trader's Brier score is than the market's
+        text = "\n".join(
+            f"{i + 1}. <@{score['user_id']}>: {score['brier_skill']:+.3f} skill, {score['brier']:.3f} Brier over {score['markets']} markets"
+            for i, score in enumerate(scores[:10])
+        )
     return {
-        "type": "modal",
-        "callback_id": f"{buysell}_view_{yesno}",
-        "title": {
-            "type": "plain_text",
-            "text": f"{BuySell} {YESNO} shares",
-            "emoji": true
-        },
-        "submit": {
-            "type": "plain_text",
-            "text": f"{BuySell}",
-            "emoji": true
-        },
-        "close": {
-            "type": "plain_text",
-            "text": "Cancel",
-            "emoji": true
-        },
         "blocks": [
             {
-                "type": "input",
-                "block_id": f"block_shares_{buysell}_{yesno}",
-                "dispatch_action": true,
-                "element": {
-                    "type": "number_input",
-                    "dispatch_action_config": {
-                        "trigger_actions_on": ["on_enter_pressed", "on_character_entered"]
-                    },
-                    "is_decimal_allowed": false,
-                    "action_id": f"action_shares_{buysell}_{yesno}",
-                    "placeholder": {
-                        "type": "plain_text",
-                        "text": "0"
-                    },
-                    "initial_value": f"{shares_amount:.0f}"
-                },
-                "label": {
+                "type": "header",
+                "text": {
                     "type": "plain_text",
-                    "text": f"Amount of {yesnoem} {YESNO} shares",
-                    "emoji": true
+                    "text": "Best forecasters",
+                    "emoji": True
                 }
             },
             {
                 "type": "section",
                 "text": {
                     "type": "mrkdwn",
-                    "text": f"""\
-{balance_or_position_display}
-{bet_amount_or_payoff_display}: *{bet_amount_or_payoff:.0f}* :dollar:
-Probability: *{lmsr_bef['probs'][0]*100:.0f}%* → *{lmsr_aft['probs'][0]*100:.0f}%*"""
+                    "text": text
                 }
-            },
-        ],
-        "private_metadata": json.dumps({
-            "balance": balance,
-            "user_positions": user_positions,
-            "market_data": market_data,
-            "channel_id": channel_id,
-            "ts": ts,
-        })
+            }
+        ]
     }
 
+def search_results_view(
+    query: str,
+    markets: list
+):
+    if not markets:
+        return {
+            "blocks": [
+                {
+                    "type": "section",
+                    "text": {
+                        "type": "mrkdwn",
+                        "text": f"No markets match \"{query}\""
+                    }
+                }
+            ]
+        }
+
+    return {"blocks": ps.render_market_list([market["id"] for market in markets])}
+
+def trade_view(
+    market_id: int,
+    user_id: str,
+    shares_amount: float,
+    buy_or_sell: bool,
+    share_index: int,
+    channel_id: str,
+    ts: str
+):
+    return ps.render_trade_view(
+        market_id,
+        user_id,
+        shares_amount,
+        buy_or_sell,
+        share_index,
+        channel_id,
+        ts
+    )
+
+def add_answer_view(
+    market_id: int,
+    user_id: str,
+    channel_id: str,
+    ts: str
+):
+    return ps.render_add_answer_view(market_id, user_id, channel_id, ts)
+
 def reminder_view(
     market_id: int
 ):
-    market_data = ps.get_market_data(market_id)
-    user_id = market_data["owner_id"]
-    title = market_data["title"]
-    remind_ts = market_data["remind_at"]
-    remind_display_fallback = datetime.fromtimestamp(remind_ts).strftime("%Y-%m-%d")
-    msm = market_data.get("main_slack_msg")
-    assert msm["exists"]
-    channel_id, ts = msm["channel_id"], msm["ts"]
-
-    return {
-        "blocks": [
-            {
-                "type": "section",
-                "text": {
-                    "type": "mrkdwn",
-                    "text": f"""Hey <@{user_id}>, you have a market:
-<https://hackclub.slack.com/archives/{channel_id}/p{ts.replace('.', '')}|{title}>
-You said it should resolve on <!date^{remind_ts}^{{date_num}}|{remind_display_fallback}>.
-Please resolve it now, or edit the question to change the resolution date."""
-                }
-            }
-        ]
-    }
\ No newline at end of file
+    return ps.render_reminder_view(market_id)
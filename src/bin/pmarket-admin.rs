@@ -0,0 +1,445 @@
+use std::fs;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Duration;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use clap::{Parser, Subcommand};
+use diesel::prelude::*;
+use serde_json::{Value, json};
+use pmarket_slack::db;
+use pmarket_slack::models::*;
+use pmarket_slack::pmarket::{api_keys, audit, browse, methods, roles, settings, utils, webhooks};
+use pmarket_slack::pmarket::errors::Error;
+use pmarket_slack::schema::*;
+
+/// Operate the prediction market database directly, outside of Slack.
+#[derive(Parser)]
+#[command(name = "pmarket-admin")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List or inspect markets
+    Markets {
+        #[command(subcommand)]
+        command: MarketsCommand,
+    },
+    /// List or inspect users
+    Users {
+        #[command(subcommand)]
+        command: UsersCommand,
+    },
+    /// Resolve a market to yes, no, na or an outcome index
+    Resolve {
+        market_id: i32,
+        resolution: String,
+    },
+    /// Reverse the payouts of a market's resolution
+    Unresolve {
+        market_id: i32,
+    },
+    /// Add coins to (or, if negative, remove coins from) a user's balance
+    AdjustBalance {
+        user_id: String,
+        #[arg(allow_negative_numbers = true)]
+        amount: String,
+        #[arg(long)]
+        reason: String,
+    },
+    /// Grant or revoke admin and moderator roles
+    Roles {
+        #[command(subcommand)]
+        command: RolesCommand,
+    },
+    /// Show or change economy settings
+    Settings {
+        #[command(subcommand)]
+        command: SettingsCommand,
+    },
+    /// Create, list or revoke keys for the HTTP API
+    ApiKeys {
+        #[command(subcommand)]
+        command: ApiKeysCommand,
+    },
+    /// Manage webhooks and run the worker that delivers them
+    Webhooks {
+        #[command(subcommand)]
+        command: WebhooksCommand,
+    },
+    /// Check that balances and market bankrolls add up
+    Audit,
+    /// Dump users, markets, trades, grants and transfers as JSON
+    Export {
+        /// File to write to instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MarketsCommand {
+    List {
+        /// open, closed or resolved
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long)]
+        owner: Option<String>,
+        #[arg(long)]
+        channel: Option<String>,
+        /// newest, most_traded, closing_soon or closest_to_half
+        #[arg(long, default_value = "newest")]
+        sort: String,
+        /// Printed after the last market when there are more
+        #[arg(long)]
+        cursor: Option<String>,
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    Show {
+        market_id: i32,
+    },
+}
+
+#[derive(Subcommand)]
+enum UsersCommand {
+    List,
+    Show {
+        user_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RolesCommand {
+    Grant {
+        user_id: String,
+        role: String,
+        #[arg(long)]
+        channel: Option<String>,
+    },
+    Revoke {
+        user_id: String,
+        role: String,
+        #[arg(long)]
+        channel: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SettingsCommand {
+    Show,
+    /// Set a value, or "none" to lift an optional limit
+    Set {
+        key: String,
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApiKeysCommand {
+    /// Prints the new key, which can't be shown again
+    Create {
+        name: String,
+        /// The user the key acts as
+        #[arg(long, required_unless_present = "service", conflicts_with = "service")]
+        user: Option<String>,
+        /// Let the key act as any user a request names, for trusted services
+        #[arg(long)]
+        service: bool,
+    },
+    List,
+    Revoke {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum WebhooksCommand {
+    /// Prints the webhook's id and signing secret
+    Add {
+        url: String,
+        /// Only deliver these events (repeatable), every event by default
+        #[arg(long = "event")]
+        events: Vec<String>,
+    },
+    List,
+    /// Stop delivering to a webhook, dropping queued deliveries
+    Disable {
+        webhook_id: i32,
+    },
+    /// Show a webhook's most recent deliveries
+    Deliveries {
+        webhook_id: i32,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Deliver queued events until stopped
+    Worker {
+        #[arg(long, default_value_t = 1000)]
+        poll_interval_ms: u64,
+    },
+}
+
+fn parse_resolution(resolution: &str) -> Result<Option<i32>, String> {
+    match resolution.to_lowercase().as_str() {
+        "yes" => Ok(Some(0)),
+        "no" => Ok(Some(1)),
+        "na" | "n/a" => Ok(None),
+        other => other.parse::<i32>()
+            .map(Some)
+            .map_err(|_| format!("Invalid resolution: {}", resolution)),
+    }
+}
+
+fn parse_amount(amount: &str) -> Result<BigDecimal, String> {
+    BigDecimal::from_str(amount)
+        .map_err(|e| format!("Invalid amount: {}", e))
+}
+
+fn list_markets(
+    filter: browse::MarketFilter,
+    sort: &str,
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let sort = browse::MarketSort::from_str(sort)?;
+    let page = browse::list_markets(&filter, sort, cursor, limit, conn)?;
+
+    for market in page.markets {
+        let status = if market.is_resolved { "resolved" } else { "open" };
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            market.id, status, market.owner_id, market.liquidity, market.title
+        );
+    }
+    if let Some(next_cursor) = page.next_cursor {
+        println!("next: --cursor '{}'", next_cursor);
+    }
+    Ok(())
+}
+
+fn list_users(conn: &mut PgConnection) -> Result<(), String> {
+    let all_users = users::table
+        .order(users::id)
+        .load::<User>(conn)
+        .map_err(|e| format!("Error fetching users: {}", e))?;
+
+    for user in all_users {
+        println!("{}\t{}", user.id, user.balance);
+    }
+    Ok(())
+}
+
+fn print_audit(conn: &mut PgConnection) -> Result<bool, String> {
+    let report = audit::audit(conn)?;
+
+    for market in report.insolvent_markets() {
+        println!(
+            "market {} is insolvent: bankroll {}, worst case payout {}",
+            market.market_id, market.bankroll, market.worst_case_payout
+        );
+        if !market.negative_positions.is_empty() {
+            println!("  negative positions: {}", market.negative_positions.join(", "));
+        }
+    }
+    println!("balances:  {}", report.total_balances);
+    println!("bankrolls: {}", report.total_bankrolls);
+    println!("fees held: {}", report.total_fees_held);
+    println!("grants:    {}", report.total_grants);
+    println!("discrepancy: {}", report.discrepancy());
+    Ok(report.is_consistent())
+}
+
+fn export(conn: &mut PgConnection) -> Result<Value, String> {
+    let user_ids = users::table
+        .order(users::id)
+        .select(users::id)
+        .load::<String>(conn)
+        .map_err(|e| format!("Error fetching users: {}", e))?;
+    let market_ids = markets::table
+        .order(markets::id)
+        .select(markets::id)
+        .load::<i32>(conn)
+        .map_err(|e| format!("Error fetching markets: {}", e))?;
+    let all_trades = trades::table
+        .order(trades::id)
+        .load::<Trade>(conn)
+        .map_err(|e| format!("Error fetching trades: {}", e))?;
+    let all_grants = grants::table
+        .order(grants::id)
+        .select((grants::user_id, grants::amount, grants::reason, grants::created_at))
+        .load::<(String, BigDecimal, String, chrono::NaiveDateTime)>(conn)
+        .map_err(|e| format!("Error fetching grants: {}", e))?;
+    let all_transfers = transfers::table
+        .order(transfers::id)
+        .load::<Transfer>(conn)
+        .map_err(|e| format!("Error fetching transfers: {}", e))?;
+
+    Ok(json!({
+        "users": user_ids.iter()
+            .map(|id| utils::get_user_data(id, conn))
+            .collect::<Result<Vec<Value>, Error>>()?,
+        "markets": market_ids.into_iter()
+            .map(|id| utils::get_market_data(id, conn))
+            .collect::<Result<Vec<Value>, Error>>()?,
+        "trades": all_trades.into_iter()
+            .map(|trade| json!({
+                "id": trade.id,
+                "market_id": trade.market_id,
+                "user_id": trade.user_id,
+                "shares_amount": trade.shares_amount.to_f64().unwrap(),
+                "share_index": trade.share_index,
+                "balance_change": trade.balance_change.to_f64().unwrap(),
+                "fee": trade.fee.to_f64().unwrap(),
+                "created_at": trade.created_at.and_utc().timestamp(),
+            }))
+            .collect::<Vec<Value>>(),
+        "grants": all_grants.into_iter()
+            .map(|(user_id, amount, reason, created_at)| json!({
+                "user_id": user_id,
+                "amount": amount.to_f64().unwrap(),
+                "reason": reason,
+                "created_at": created_at.and_utc().timestamp(),
+            }))
+            .collect::<Vec<Value>>(),
+        "transfers": all_transfers.into_iter()
+            .map(|transfer| json!({
+                "id": transfer.id,
+                "from_user_id": transfer.from_user_id,
+                "to_user_id": transfer.to_user_id,
+                "amount": transfer.amount.to_f64().unwrap(),
+                "memo": transfer.memo,
+                "created_at": transfer.created_at.and_utc().timestamp(),
+            }))
+            .collect::<Vec<Value>>(),
+    }))
+}
+
+fn run(cli: Cli) -> Result<bool, String> {
+    let mut conn = db::establish_connection();
+    let conn = &mut conn;
+
+    match cli.command {
+        Command::Markets { command: MarketsCommand::List { status, owner, channel, sort, cursor, limit } } => {
+            let filter = browse::MarketFilter {
+                status: status.as_deref().map(browse::MarketStatus::from_str).transpose()?,
+                owner_id: owner,
+                channel_id: channel,
+                ..Default::default()
+            };
+            list_markets(filter, &sort, cursor.as_deref(), limit, conn)?;
+        }
+        Command::Markets { command: MarketsCommand::Show { market_id } } => {
+            println!("{:#}", utils::get_market_data(market_id, conn)?);
+        }
+        Command::Users { command: UsersCommand::List } => {
+            list_users(conn)?;
+        }
+        Command::Users { command: UsersCommand::Show { user_id } } => {
+            println!("{:#}", utils::get_user_data(&user_id, conn)?);
+        }
+        // the operator has database access, so role checks don't apply here
+        Command::Resolve { market_id, resolution } => {
+            methods::apply_resolution(market_id, parse_resolution(&resolution)?, conn)?;
+        }
+        Command::Unresolve { market_id } => {
+            methods::reverse_resolution(market_id, conn)?;
+        }
+        Command::AdjustBalance { user_id, amount, reason } => {
+            methods::grant_balance(&user_id, &parse_amount(&amount)?, &reason, conn)?;
+        }
+        Command::Roles { command: RolesCommand::Grant { user_id, role, channel } } => {
+            let role = roles::Role::from_str(&role)?;
+            roles::add_role(&user_id, role, channel.as_deref(), None, conn)?;
+        }
+        Command::Roles { command: RolesCommand::Revoke { user_id, role, channel } } => {
+            let role = roles::Role::from_str(&role)?;
+            roles::remove_role(&user_id, role, channel.as_deref(), conn)?;
+        }
+        Command::Settings { command: SettingsCommand::Show } => {
+            println!("{:#}", utils::get_settings_data(conn)?);
+        }
+        Command::Settings { command: SettingsCommand::Set { key, value } } => {
+            settings::apply_setting(&key, &value, conn)?;
+        }
+        Command::ApiKeys { command: ApiKeysCommand::Create { name, user, .. } } => {
+            println!("{}", api_keys::create_api_key(&name, user.as_deref(), conn)?);
+        }
+        Command::ApiKeys { command: ApiKeysCommand::List } => {
+            for key in api_keys::list_api_keys(conn)? {
+                let status = if key.revoked_at.is_some() { "revoked" } else { "active" };
+                let last_used = key.last_used_at.map_or("never".to_string(), |t| t.to_string());
+                let acts_as = key.user_id.as_deref().unwrap_or("any user");
+                println!("{}\t{}\t{} ({})\t{}\tlast used {}", key.name, status, key.role, acts_as, key.created_at, last_used);
+            }
+        }
+        Command::ApiKeys { command: ApiKeysCommand::Revoke { name } } => {
+            api_keys::revoke_api_key(&name, conn)?;
+        }
+        Command::Webhooks { command: WebhooksCommand::Add { url, events } } => {
+            let (id, secret) = webhooks::add_webhook(&url, &events, conn)?;
+            println!("{}\t{}", id, secret);
+        }
+        Command::Webhooks { command: WebhooksCommand::List } => {
+            for webhook in webhooks::list_webhooks(conn)? {
+                let status = if webhook.disabled_at.is_some() { "disabled" } else { "active" };
+                let events = webhook.events.into_iter().flatten().collect::<Vec<String>>();
+                let events = if events.is_empty() { "all events".to_string() } else { events.join(",") };
+                println!("{}\t{}\t{}\t{}", webhook.id, status, webhook.url, events);
+            }
+        }
+        Command::Webhooks { command: WebhooksCommand::Disable { webhook_id } } => {
+            webhooks::disable_webhook(webhook_id, conn)?;
+        }
+        Command::Webhooks { command: WebhooksCommand::Deliveries { webhook_id, limit } } => {
+            for delivery in webhooks::list_deliveries(webhook_id, limit, conn)? {
+                let status = if delivery.delivered_at.is_some() {
+                    "delivered"
+                } else if delivery.failed_at.is_some() {
+                    "failed"
+                } else {
+                    "pending"
+                };
+                println!(
+                    "{}\t{}\t{}\t{} attempts\t{}",
+                    delivery.id,
+                    delivery.event,
+                    status,
+                    delivery.attempts,
+                    delivery.last_error.unwrap_or_default()
+                );
+            }
+        }
+        Command::Webhooks { command: WebhooksCommand::Worker { poll_interval_ms } } => {
+            webhooks::run_worker(Duration::from_millis(poll_interval_ms));
+        }
+        Command::Audit => {
+            return print_audit(conn);
+        }
+        Command::Export { output } => {
+            let data = export(conn)?;
+            let data = serde_json::to_string_pretty(&data)
+                .map_err(|e| format!("Serialization error: {}", e))?;
+            match output {
+                Some(path) => fs::write(&path, data)
+                    .map_err(|e| format!("Error writing {}: {}", path, e))?,
+                None => println!("{}", data),
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
\ No newline at end of file
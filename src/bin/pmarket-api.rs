@@ -0,0 +1,562 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, NaiveDate};
+use clap::Parser;
+use diesel::prelude::*;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Value, json};
+use tokio::sync::broadcast;
+use tokio_stream::{Stream, StreamExt};
+use tokio_stream::wrappers::BroadcastStream;
+use pmarket_slack::db;
+use pmarket_slack::models::ApiKey;
+use pmarket_slack::pmarket::dates::BucketSize;
+use pmarket_slack::pmarket::errors::{Error, ErrorKind};
+use pmarket_slack::pmarket::events::{MarketEvent, run_listener};
+use pmarket_slack::pmarket::{answers, api_keys, browse, methods, utils};
+use pmarket_slack::pmarket::quotes::TradeSize;
+
+// how far a slow subscriber can fall behind before it starts missing events
+const EVENT_BUFFER: usize = 1024;
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Serve the prediction market over HTTP as JSON.
+///
+/// Requests need an `Authorization: Bearer <key>` header with a key from
+/// `pmarket-admin api-keys create`. A user key acts as its own user, so the
+/// user ids in requests made with it can be left out. A service key acts as
+/// whichever user a request names, the same way the Slack bot does, so only
+/// hand those to trusted services.
+#[derive(Parser)]
+#[command(name = "pmarket-api")]
+struct Cli {
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+}
+
+#[derive(Clone)]
+struct AppState {
+    events: broadcast::Sender<MarketEvent>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> ApiError {
+        let status = match e.kind {
+            ErrorKind::Invalid => StatusCode::BAD_REQUEST,
+            ErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::Conflict => StatusCode::CONFLICT,
+        };
+        ApiError(status, e.message)
+    }
+}
+
+impl From<String> for ApiError {
+    fn from(e: String) -> ApiError {
+        Error::from(e).into()
+    }
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+// diesel is synchronous, so every request gets its own connection on the blocking pool
+async fn blocking<T, F>(f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> Result<T, Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db::establish_connection();
+        f(&mut conn)
+    })
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("Request failed: {}", e)))?
+        .map_err(ApiError::from)
+}
+
+// amounts can be sent as JSON numbers or, to keep every decimal, as strings
+fn decimal_from_value(value: &Value) -> Result<BigDecimal, String> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        _ => return Err("expected a number or a decimal string".to_string()),
+    };
+    BigDecimal::from_str(&text).map_err(|e| e.to_string())
+}
+
+fn deserialize_decimal<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    decimal_from_value(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_decimal<'de, D>(deserializer: D) -> Result<Option<BigDecimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_decimal(deserializer).map(Some)
+}
+
+fn deserialize_optional_decimals<'de, D>(deserializer: D) -> Result<Option<Vec<BigDecimal>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<Value>::deserialize(deserializer)?
+        .iter()
+        .map(decimal_from_value)
+        .collect::<Result<Vec<BigDecimal>, String>>()
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+// dates are YYYY-MM-DD strings
+fn deserialize_optional_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn from_timestamp(timestamp: i64) -> Result<chrono::NaiveDateTime, ApiError> {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.naive_utc())
+        .ok_or_else(|| ApiError(StatusCode::BAD_REQUEST, "Invalid timestamp".to_string()))
+}
+
+// repeating a request with the same Idempotency-Key header doesn't act twice
+fn idempotency_key(headers: &HeaderMap) -> Option<String> {
+    headers.get("Idempotency-Key")
+        .and_then(|value| value.to_str().ok())
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+// the handlers get the key from the request's extensions to know who's acting
+async fn require_api_key(mut request: Request, next: Next) -> Result<Response, ApiError> {
+    let key = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|key| key.trim().to_string())
+        .ok_or_else(|| ApiError(StatusCode::UNAUTHORIZED, "Missing API key".to_string()))?;
+    let api_key = blocking(move |conn| Ok(api_keys::authenticate_api_key(&key, conn)?))
+        .await
+        .map_err(|ApiError(_, e)| ApiError(StatusCode::UNAUTHORIZED, e))?;
+    request.extensions_mut().insert(api_key);
+    Ok(next.run(request).await)
+}
+
+#[derive(Deserialize)]
+struct CreateUserBody {
+    id: Option<String>,
+}
+
+async fn create_user(Extension(api_key): Extension<ApiKey>, Json(body): Json<CreateUserBody>) -> ApiResult {
+    let user_id = api_keys::acting_user(&api_key, body.id.as_deref())?;
+    blocking(move |conn| {
+        methods::try_create_user(&user_id, conn)?;
+        utils::get_user_data(&user_id, conn)
+    }).await.map(Json)
+}
+
+async fn get_user(Path(user_id): Path<String>) -> ApiResult {
+    blocking(move |conn| utils::get_user_data(&user_id, conn)).await.map(Json)
+}
+
+async fn get_user_score(Path(user_id): Path<String>) -> ApiResult {
+    blocking(move |conn| Ok(utils::get_user_score(&user_id, conn)?)).await.map(Json)
+}
+
+#[derive(Deserialize)]
+struct ListMarketsQuery {
+    status: Option<String>,
+    owner_id: Option<String>,
+    channel_id: Option<String>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    sort: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn list_markets(Query(query): Query<ListMarketsQuery>) -> ApiResult {
+    let filter = browse::MarketFilter {
+        status: query.status.as_deref().map(browse::MarketStatus::from_str).transpose()?,
+        owner_id: query.owner_id,
+        channel_id: query.channel_id,
+        created_after: query.created_after.map(from_timestamp).transpose()?,
+        created_before: query.created_before.map(from_timestamp).transpose()?,
+    };
+    let sort = browse::MarketSort::from_str(query.sort.as_deref().unwrap_or("newest"))?;
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    blocking(move |conn| {
+        let page = browse::list_markets(&filter, sort, query.cursor.as_deref(), limit, conn)?;
+        let markets = page.markets.iter()
+            .map(|market| utils::get_market_data(market.id, conn))
+            .collect::<Result<Vec<Value>, Error>>()?;
+        Ok(json!({
+            "markets": markets,
+            "next_cursor": page.next_cursor,
+        }))
+    }).await.map(Json)
+}
+
+#[derive(Deserialize)]
+struct CreateMarketBody {
+    title: String,
+    description: String,
+    // who the market is created for, the key's user by default
+    owner_id: Option<String>,
+    #[serde(deserialize_with = "deserialize_decimal")]
+    liquidity: BigDecimal,
+    remind_at: i64,
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    fee_rate: Option<BigDecimal>,
+    // both set for a scalar market over that range
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    scalar_min: Option<BigDecimal>,
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    scalar_max: Option<BigDecimal>,
+    // both set for a date market with an outcome per `bucket` ("week" or
+    // "month") between them
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    date_start: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    date_end: Option<NaiveDate>,
+    bucket: Option<String>,
+    // set for a free-response market starting out with these answers
+    answers: Option<Vec<String>>,
+    // both set for a YES/NO market that's resolved N/A unless the parent
+    // market resolves to the outcome at that index
+    parent_market_id: Option<i32>,
+    parent_outcome: Option<i32>,
+}
+
+async fn create_market(
+    Extension(api_key): Extension<ApiKey>,
+    headers: HeaderMap,
+    Json(body): Json<CreateMarketBody>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let owner_id = api_keys::acting_user(&api_key, body.owner_id.as_deref())?;
+    let remind_at = from_timestamp(body.remind_at)?;
+    let key = idempotency_key(&headers);
+    blocking(move |conn| {
+        let conditional = body.parent_market_id.is_some() || body.parent_outcome.is_some();
+        let other_type = body.scalar_min.is_some() || body.scalar_max.is_some() || body.answers.is_some()
+            || body.date_start.is_some() || body.date_end.is_some();
+        if conditional && other_type {
+            return Err("Conditional markets are YES/NO markets".to_string().into());
+        }
+        let market_id = match (&body.scalar_min, &body.scalar_max) {
+            (None, None) if conditional => {
+                let (Some(parent_market_id), Some(parent_outcome)) = (body.parent_market_id, body.parent_outcome) else {
+                    return Err("Conditional markets need both parent_market_id and parent_outcome".to_string().into());
+                };
+                methods::create_conditional_market(
+                    &body.title,
+                    &body.description,
+                    &owner_id,
+                    &body.liquidity,
+                    &remind_at,
+                    body.fee_rate.as_ref(),
+                    parent_market_id,
+                    parent_outcome,
+                    key.as_deref(),
+                    conn
+                )?
+            },
+            (None, None) if body.answers.is_some() => methods::create_free_response_market(
+                &body.title,
+                &body.description,
+                &owner_id,
+                &body.liquidity,
+                &remind_at,
+                body.fee_rate.as_ref(),
+                body.answers.as_deref().unwrap(),
+                key.as_deref(),
+                conn
+            )?,
+            (None, None) if body.date_start.is_some() || body.date_end.is_some() => {
+                let (Some(start), Some(end)) = (body.date_start, body.date_end) else {
+                    return Err("Date markets need both date_start and date_end".to_string().into());
+                };
+                let bucket_size = BucketSize::from_str(body.bucket.as_deref().unwrap_or("week"))?;
+                methods::create_date_market(
+                    &body.title,
+                    &body.description,
+                    &owner_id,
+                    &body.liquidity,
+                    &remind_at,
+                    body.fee_rate.as_ref(),
+                    start,
+                    end,
+                    bucket_size,
+                    key.as_deref(),
+                    conn
+                )?
+            },
+            (None, None) => methods::create_market(
+                &body.title,
+                &body.description,
+                &owner_id,
+                &body.liquidity,
+                &remind_at,
+                body.fee_rate.as_ref(),
+                key.as_deref(),
+                conn
+            )?,
+            (Some(scalar_min), Some(scalar_max)) => methods::create_scalar_market(
+                &body.title,
+                &body.description,
+                &owner_id,
+                &body.liquidity,
+                &remind_at,
+                body.fee_rate.as_ref(),
+                scalar_min,
+                scalar_max,
+                key.as_deref(),
+                conn
+            )?,
+            _ => return Err("Scalar markets need both scalar_min and scalar_max".to_string().into()),
+        };
+        utils::get_market_data(market_id, conn)
+    }).await.map(|market| (StatusCode::CREATED, Json(market)))
+}
+
+async fn get_market(Path(market_id): Path<i32>) -> ApiResult {
+    blocking(move |conn| utils::get_market_data(market_id, conn)).await.map(Json)
+}
+
+async fn get_market_stats(Path(market_id): Path<i32>) -> ApiResult {
+    blocking(move |conn| utils::get_market_stats(market_id, conn)).await.map(Json)
+}
+
+async fn get_positions(Path(market_id): Path<i32>) -> ApiResult {
+    blocking(move |conn| {
+        methods::find_market(market_id, conn)?;
+        let positions = methods::get_positions(market_id, conn)?;
+        Ok(positions.into_iter()
+            .map(|(user_id, shares)| {
+                (user_id, shares.iter().map(|s| s.to_f64().unwrap()).collect())
+            })
+            .collect::<HashMap<String, Vec<f64>>>())
+    }).await.map(|positions| Json(json!(positions)))
+}
+
+#[derive(Deserialize)]
+struct QuoteQuery {
+    user_id: Option<String>,
+    outcome: i32,
+    // exactly one of the shares to trade or the coins to spend, both negative to sell
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    shares: Option<BigDecimal>,
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    amount: Option<BigDecimal>,
+}
+
+// what a trade would cost right now, without trading
+async fn quote(
+    Extension(api_key): Extension<ApiKey>,
+    Path(market_id): Path<i32>,
+    Query(query): Query<QuoteQuery>,
+) -> ApiResult {
+    let user_id = api_keys::acting_user(&api_key, query.user_id.as_deref())?;
+    let size = match (query.shares, query.amount) {
+        (Some(shares), None) => TradeSize::Shares(shares),
+        (None, Some(amount)) => TradeSize::Amount(amount),
+        _ => return Err(ApiError(StatusCode::BAD_REQUEST, "Quote either shares or an amount".to_string())),
+    };
+    blocking(move |conn| {
+        utils::get_trade_quote(market_id, &user_id, query.outcome, &size, conn)
+    }).await.map(Json)
+}
+
+#[derive(Deserialize)]
+struct TradeBody {
+    user_id: Option<String>,
+    outcome: i32,
+    #[serde(deserialize_with = "deserialize_decimal")]
+    shares: BigDecimal,
+}
+
+async fn create_trade(
+    Extension(api_key): Extension<ApiKey>,
+    Path(market_id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<TradeBody>,
+) -> ApiResult {
+    let user_id = api_keys::acting_user(&api_key, body.user_id.as_deref())?;
+    let key = idempotency_key(&headers);
+    blocking(move |conn| {
+        methods::try_create_user(&user_id, conn)?;
+        methods::create_trade(market_id, &user_id, &body.shares, body.outcome, key.as_deref(), conn)?;
+        utils::get_market_data(market_id, conn)
+    }).await.map(Json)
+}
+
+#[derive(Deserialize)]
+struct AnswerBody {
+    user_id: Option<String>,
+    answer: String,
+}
+
+// adds an answer to a free-response market, paid for by the user adding it
+async fn add_answer(
+    Extension(api_key): Extension<ApiKey>,
+    Path(market_id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<AnswerBody>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = api_keys::acting_user(&api_key, body.user_id.as_deref())?;
+    let key = idempotency_key(&headers);
+    blocking(move |conn| {
+        methods::try_create_user(&user_id, conn)?;
+        answers::add_answer(market_id, &user_id, &body.answer, key.as_deref(), conn)?;
+        utils::get_market_data(market_id, conn)
+    }).await.map(|market| (StatusCode::CREATED, Json(market)))
+}
+
+#[derive(Deserialize)]
+struct ResolveBody {
+    actor_id: Option<String>,
+    // an outcome index, or null to resolve N/A
+    resolution: Option<i32>,
+    // what a scalar market resolves to, instead of an outcome
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    value: Option<BigDecimal>,
+    // the day a date market resolves to, instead of an outcome
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    date: Option<NaiveDate>,
+    // the answer a free-response market resolves to, instead of an outcome
+    answer: Option<String>,
+    // a probability for each outcome, which its shares pay, instead of an outcome
+    #[serde(default, deserialize_with = "deserialize_optional_decimals")]
+    probs: Option<Vec<BigDecimal>>,
+}
+
+async fn resolve_market(
+    Extension(api_key): Extension<ApiKey>,
+    Path(market_id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<ResolveBody>,
+) -> ApiResult {
+    let actor_id = api_keys::acting_user(&api_key, body.actor_id.as_deref())?;
+    let key = idempotency_key(&headers);
+    blocking(move |conn| {
+        match (&body.value, body.date, &body.answer, &body.probs) {
+            (Some(value), None, None, None) => methods::resolve_market_value(market_id, value, &actor_id, key.as_deref(), conn)?,
+            (None, Some(date), None, None) => methods::resolve_market_date(market_id, date, &actor_id, key.as_deref(), conn)?,
+            (None, None, Some(answer), None) => methods::resolve_market_answer(market_id, answer, &actor_id, key.as_deref(), conn)?,
+            (None, None, None, Some(probs)) => methods::resolve_market_prob(market_id, probs, &actor_id, key.as_deref(), conn)?,
+            (None, None, None, None) => methods::resolve_market(market_id, body.resolution, &actor_id, key.as_deref(), conn)?,
+            _ => return Err("Resolve to only one of a value, a date, an answer or probabilities".to_string().into()),
+        }
+        utils::get_market_data(market_id, conn)
+    }).await.map(Json)
+}
+
+#[derive(Deserialize)]
+struct UnresolveBody {
+    actor_id: Option<String>,
+}
+
+async fn unresolve_market(
+    Extension(api_key): Extension<ApiKey>,
+    Path(market_id): Path<i32>,
+    Json(body): Json<UnresolveBody>,
+) -> ApiResult {
+    let actor_id = api_keys::acting_user(&api_key, body.actor_id.as_deref())?;
+    blocking(move |conn| {
+        methods::unresolve_market(market_id, &actor_id, conn)?;
+        utils::get_market_data(market_id, conn)
+    }).await.map(Json)
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    market_id: Option<i32>,
+}
+
+// server-sent events for every trade, price change, new market and resolution,
+// or only those of one market
+async fn events(
+    State(state): State<AppState>,
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe())
+        .filter_map(move |event| {
+            // a subscriber that fell behind skips what it missed
+            let event = event.ok()?;
+            if query.market_id.is_some_and(|market_id| market_id != event.market_id) {
+                return None;
+            }
+            Some(Ok(Event::default()
+                .event(event.event)
+                .data(event.data.to_string())))
+        });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/users", post(create_user))
+        .route("/users/{user_id}", get(get_user))
+        .route("/users/{user_id}/score", get(get_user_score))
+        .route("/markets", get(list_markets).post(create_market))
+        .route("/markets/{market_id}", get(get_market))
+        .route("/markets/{market_id}/stats", get(get_market_stats))
+        .route("/markets/{market_id}/positions", get(get_positions))
+        .route("/markets/{market_id}/quote", get(quote))
+        .route("/markets/{market_id}/trades", post(create_trade))
+        .route("/markets/{market_id}/answers", post(add_answer))
+        .route("/markets/{market_id}/resolve", post(resolve_market))
+        .route("/markets/{market_id}/unresolve", post(unresolve_market))
+        .route("/events", get(events))
+        .layer(middleware::from_fn(require_api_key))
+        .with_state(state)
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let (sender, _) = broadcast::channel(EVENT_BUFFER);
+    let state = AppState { events: sender.clone() };
+    // diesel connections block, so LISTEN runs on its own thread
+    thread::spawn(move || run_listener(EVENT_POLL_INTERVAL, |event| {
+        // sending only fails while nobody is subscribed
+        let _ = sender.send(event);
+        true
+    }));
+
+    let listener = tokio::net::TcpListener::bind(&cli.addr)
+        .await
+        .unwrap_or_else(|e| panic!("Error binding {}: {}", cli.addr, e));
+    println!("listening on {}", cli.addr);
+    axum::serve(listener, router(state))
+        .await
+        .expect("Server error");
+}
\ No newline at end of file
@@ -1,11 +1,38 @@
 use diesel::prelude::*;
 use dotenvy::dotenv;
 use std::env;
+use std::thread;
+use std::time::Duration;
 
-pub fn establish_connection() -> PgConnection {
+// how long workers wait before reconnecting, doubling up to the maximum
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+fn database_url() -> String {
     dotenv().ok();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    env::var("DATABASE_URL").expect("DATABASE_URL must be set")
+}
+
+pub fn establish_connection() -> PgConnection {
+    let database_url = database_url();
     PgConnection::establish(&database_url)
         .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+}
+
+// for workers that run for good, which should wait out a database restart
+// instead of panicking
+pub fn establish_connection_with_retry() -> PgConnection {
+    let database_url = database_url();
+    let mut delay = RECONNECT_DELAY;
+    loop {
+        match PgConnection::establish(&database_url) {
+            Ok(conn) => return conn,
+            Err(e) => {
+                eprintln!("Error connecting to the database, retrying in {}s: {}", delay.as_secs(), e);
+                thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -1,10 +1,10 @@
-mod pmarket;
+pub mod pmarket;
 mod python;
-mod schema;
-mod models;
-mod db;
+pub mod schema;
+pub mod models;
+pub mod db;
 
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDate};
 use pyo3::prelude::*;
 use pyo3::wrap_pyfunction;
 use pyo3::exceptions::{PyException, PyValueError};
@@ -61,16 +61,92 @@ fn try_create_user(id: &str) -> PyResult<()> {
 fn change_balance<'py>(
     py: Python<'py>,
     user_id: &str, 
-    amount: Bound<'py, PyAny>
+    amount: Bound<'py, PyAny>,
+    actor_id: &str,
+    reason: &str
 ) -> PyResult<()> {
     let mut conn = db::establish_connection();
     let amount = pydecimal_to_bigdecimal(py, amount)
         .map_err(|e| PyValueError::new_err(format!("Invalid amount: {}", e)))?;
-    pmarket::methods::change_balance(user_id, &amount, &mut conn)
+    pmarket::methods::adjust_balance(actor_id, user_id, &amount, reason, &mut conn)
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+fn claim_allowance<'py>(py: Python<'py>, user_id: &str) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let amount = pmarket::allowance::claim_allowance(user_id, &mut conn)
+        .map_err(PyException::new_err)?;
+    bigdecimal_to_pydecimal(py, &amount)
+}
+
+#[pyfunction]
+#[pyo3(signature = (from_user_id, to_user_id, amount, memo=None))]
+fn transfer<'py>(
+    py: Python<'py>,
+    from_user_id: &str,
+    to_user_id: &str,
+    amount: Bound<'py, PyAny>,
+    memo: Option<&str>
+) -> PyResult<i32> {
+    let mut conn = db::establish_connection();
+    let amount = pydecimal_to_bigdecimal(py, amount)
+        .map_err(|e| PyValueError::new_err(format!("Invalid amount: {}", e)))?;
+    pmarket::transfers::transfer(from_user_id, to_user_id, &amount, memo, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+fn grant_role(
+    actor_id: &str,
+    user_id: &str,
+    role: &str,
+    channel_id: Option<&str>
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let role = pmarket::roles::Role::from_str(role)
+        .map_err(PyValueError::new_err)?;
+    pmarket::roles::grant_role(actor_id, user_id, role, channel_id, &mut conn)
         .map_err(PyException::new_err)
 }
 
 #[pyfunction]
+fn revoke_role(
+    actor_id: &str,
+    user_id: &str,
+    role: &str,
+    channel_id: Option<&str>
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let role = pmarket::roles::Role::from_str(role)
+        .map_err(PyValueError::new_err)?;
+    pmarket::roles::revoke_role(actor_id, user_id, role, channel_id, &mut conn)
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+fn load_settings() -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    pmarket::settings::load_settings(&mut conn)
+        .map(|_| ())
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+fn update_setting(
+    actor_id: &str,
+    key: &str,
+    value: &str
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    pmarket::settings::update_setting(actor_id, key, value, &mut conn)
+        .map(|_| ())
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+#[pyo3(signature = (title, description, owner_id, liquidity, remind_at, fee_rate=None, idempotency_key=None))]
+#[allow(clippy::too_many_arguments)]
 fn create_market<'py>(
     py: Python<'py>,
     title: &str,
@@ -78,10 +154,16 @@ fn create_market<'py>(
     owner_id: &str,
     liquidity: Bound<'py, PyAny>,
     remind_at: i32,
+    fee_rate: Option<Bound<'py, PyAny>>,
+    idempotency_key: Option<&str>,
 ) -> PyResult<i32> {
     let mut conn = db::establish_connection();
     let liquidity = pydecimal_to_bigdecimal(py, liquidity)
         .map_err(|e| PyValueError::new_err(format!("Invalid liquidity: {}", e)))?;
+    let fee_rate = fee_rate
+        .map(|fee_rate| pydecimal_to_bigdecimal(py, fee_rate))
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid fee_rate: {}", e)))?;
     let remind_at = DateTime::from_timestamp(remind_at as i64, 0)
         .ok_or_else(|| PyValueError::new_err("Invalid timestamp for remind_at"))?
         .naive_utc();
@@ -91,11 +173,234 @@ fn create_market<'py>(
         owner_id, 
         &liquidity, 
         &remind_at, 
+        fee_rate.as_ref(),
+        idempotency_key,
+        &mut conn
+    )
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (title, description, owner_id, liquidity, remind_at, scalar_min, scalar_max, fee_rate=None, idempotency_key=None))]
+#[allow(clippy::too_many_arguments)]
+fn create_scalar_market<'py>(
+    py: Python<'py>,
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: Bound<'py, PyAny>,
+    remind_at: i32,
+    scalar_min: Bound<'py, PyAny>,
+    scalar_max: Bound<'py, PyAny>,
+    fee_rate: Option<Bound<'py, PyAny>>,
+    idempotency_key: Option<&str>,
+) -> PyResult<i32> {
+    let mut conn = db::establish_connection();
+    let liquidity = pydecimal_to_bigdecimal(py, liquidity)
+        .map_err(|e| PyValueError::new_err(format!("Invalid liquidity: {}", e)))?;
+    let scalar_min = pydecimal_to_bigdecimal(py, scalar_min)
+        .map_err(|e| PyValueError::new_err(format!("Invalid scalar_min: {}", e)))?;
+    let scalar_max = pydecimal_to_bigdecimal(py, scalar_max)
+        .map_err(|e| PyValueError::new_err(format!("Invalid scalar_max: {}", e)))?;
+    let fee_rate = fee_rate
+        .map(|fee_rate| pydecimal_to_bigdecimal(py, fee_rate))
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid fee_rate: {}", e)))?;
+    let remind_at = DateTime::from_timestamp(remind_at as i64, 0)
+        .ok_or_else(|| PyValueError::new_err("Invalid timestamp for remind_at"))?
+        .naive_utc();
+    pmarket::methods::create_scalar_market(
+        title,
+        description,
+        owner_id,
+        &liquidity,
+        &remind_at,
+        fee_rate.as_ref(),
+        &scalar_min,
+        &scalar_max,
+        idempotency_key,
+        &mut conn
+    )
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (title, description, owner_id, liquidity, remind_at, answers, fee_rate=None, idempotency_key=None))]
+#[allow(clippy::too_many_arguments)]
+fn create_free_response_market<'py>(
+    py: Python<'py>,
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: Bound<'py, PyAny>,
+    remind_at: i32,
+    answers: Vec<String>,
+    fee_rate: Option<Bound<'py, PyAny>>,
+    idempotency_key: Option<&str>,
+) -> PyResult<i32> {
+    let mut conn = db::establish_connection();
+    let liquidity = pydecimal_to_bigdecimal(py, liquidity)
+        .map_err(|e| PyValueError::new_err(format!("Invalid liquidity: {}", e)))?;
+    let fee_rate = fee_rate
+        .map(|fee_rate| pydecimal_to_bigdecimal(py, fee_rate))
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid fee_rate: {}", e)))?;
+    let remind_at = DateTime::from_timestamp(remind_at as i64, 0)
+        .ok_or_else(|| PyValueError::new_err("Invalid timestamp for remind_at"))?
+        .naive_utc();
+    pmarket::methods::create_free_response_market(
+        title,
+        description,
+        owner_id,
+        &liquidity,
+        &remind_at,
+        fee_rate.as_ref(),
+        &answers,
+        idempotency_key,
+        &mut conn
+    )
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (title, description, owner_id, liquidity, remind_at, parent_market_id, parent_outcome, fee_rate=None, idempotency_key=None))]
+#[allow(clippy::too_many_arguments)]
+fn create_conditional_market<'py>(
+    py: Python<'py>,
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: Bound<'py, PyAny>,
+    remind_at: i32,
+    parent_market_id: i32,
+    parent_outcome: i32,
+    fee_rate: Option<Bound<'py, PyAny>>,
+    idempotency_key: Option<&str>,
+) -> PyResult<i32> {
+    let mut conn = db::establish_connection();
+    let liquidity = pydecimal_to_bigdecimal(py, liquidity)
+        .map_err(|e| PyValueError::new_err(format!("Invalid liquidity: {}", e)))?;
+    let fee_rate = fee_rate
+        .map(|fee_rate| pydecimal_to_bigdecimal(py, fee_rate))
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid fee_rate: {}", e)))?;
+    let remind_at = DateTime::from_timestamp(remind_at as i64, 0)
+        .ok_or_else(|| PyValueError::new_err("Invalid timestamp for remind_at"))?
+        .naive_utc();
+    pmarket::methods::create_conditional_market(
+        title,
+        description,
+        owner_id,
+        &liquidity,
+        &remind_at,
+        fee_rate.as_ref(),
+        parent_market_id,
+        parent_outcome,
+        idempotency_key,
+        &mut conn
+    )
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (market_id, user_id, answer, idempotency_key=None))]
+fn add_answer(
+    market_id: i32,
+    user_id: &str,
+    answer: &str,
+    idempotency_key: Option<&str>,
+) -> PyResult<i32> {
+    let mut conn = db::establish_connection();
+    pmarket::answers::add_answer(market_id, user_id, answer, idempotency_key, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+fn parse_date(name: &str, date: &str) -> PyResult<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|e| PyValueError::new_err(format!("Invalid {}: {}", name, e)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (title, description, owner_id, liquidity, remind_at, start, end, bucket_size, fee_rate=None, idempotency_key=None))]
+#[allow(clippy::too_many_arguments)]
+fn create_date_market<'py>(
+    py: Python<'py>,
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: Bound<'py, PyAny>,
+    remind_at: i32,
+    start: &str,
+    end: &str,
+    bucket_size: &str,
+    fee_rate: Option<Bound<'py, PyAny>>,
+    idempotency_key: Option<&str>,
+) -> PyResult<i32> {
+    let mut conn = db::establish_connection();
+    let liquidity = pydecimal_to_bigdecimal(py, liquidity)
+        .map_err(|e| PyValueError::new_err(format!("Invalid liquidity: {}", e)))?;
+    let start = parse_date("start", start)?;
+    let end = parse_date("end", end)?;
+    let bucket_size = pmarket::dates::BucketSize::from_str(bucket_size)
+        .map_err(PyValueError::new_err)?;
+    let fee_rate = fee_rate
+        .map(|fee_rate| pydecimal_to_bigdecimal(py, fee_rate))
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid fee_rate: {}", e)))?;
+    let remind_at = DateTime::from_timestamp(remind_at as i64, 0)
+        .ok_or_else(|| PyValueError::new_err("Invalid timestamp for remind_at"))?
+        .naive_utc();
+    pmarket::methods::create_date_market(
+        title,
+        description,
+        owner_id,
+        &liquidity,
+        &remind_at,
+        fee_rate.as_ref(),
+        start,
+        end,
+        bucket_size,
+        idempotency_key,
+        &mut conn
+    )
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+fn edit_market(
+    market_id: i32,
+    actor_id: &str,
+    title: &str,
+    description: &str,
+    remind_at: i32,
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let remind_at = DateTime::from_timestamp(remind_at as i64, 0)
+        .ok_or_else(|| PyValueError::new_err("Invalid timestamp for remind_at"))?
+        .naive_utc();
+    pmarket::methods::edit_market(
+        market_id,
+        actor_id,
+        title,
+        description,
+        &remind_at,
         &mut conn
     )
         .map_err(PyException::new_err)
 }
 
+#[pyfunction]
+fn categorize_market(
+    market_id: i32,
+    actor_id: &str,
+    category: Option<&str>,
+    tags: Vec<String>
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    pmarket::browse::categorize_market(market_id, actor_id, category, &tags, &mut conn)
+        .map_err(PyException::new_err)
+}
+
 #[pyfunction]
 fn create_market_slack_msg(
     market_id: i32,
@@ -121,29 +426,32 @@ fn check_valid_trade<'py>(
     user_id: &str,
     shares_amount: Bound<'py, PyAny>,
     share_index: i32
-) -> PyResult<(bool, Bound<'py, PyAny>)> {
+) -> PyResult<(bool, Bound<'py, PyAny>, Bound<'py, PyAny>)> {
     let mut conn = db::establish_connection();
     let shares_amount = pydecimal_to_bigdecimal(py, shares_amount)
         .map_err(|e| PyValueError::new_err(format!("Invalid shares_amount: {}", e)))?;
-    let (valid, change) = pmarket::methods::check_valid_trade(market_id, user_id, &shares_amount, share_index, &mut conn)
-        .map_err(PyException::new_err)?;
+    let (valid, change, fee) = pmarket::methods::check_valid_trade(market_id, user_id, &shares_amount, share_index, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))?;
     let py_change = bigdecimal_to_pydecimal(py, &change)?;
-    Ok((valid, py_change))
+    let py_fee = bigdecimal_to_pydecimal(py, &fee)?;
+    Ok((valid, py_change, py_fee))
 }
 
 #[pyfunction]
+#[pyo3(signature = (market_id, user_id, shares_amount, share_index, idempotency_key=None))]
 fn create_trade<'py>(
     py: Python<'py>,
     market_id: i32,
     user_id: &str,
     shares_amount: Bound<'py, PyAny>,
-    share_index: i32
+    share_index: i32,
+    idempotency_key: Option<&str>,
 ) -> PyResult<()> {
     let mut conn = db::establish_connection();
     let shares_amount = pydecimal_to_bigdecimal(py, shares_amount)
         .map_err(|e| PyValueError::new_err(format!("Invalid shares_amount: {}", e)))?;
-    pmarket::methods::create_trade(market_id, user_id, &shares_amount, share_index, &mut conn)
-        .map_err(PyException::new_err)
+    pmarket::methods::create_trade(market_id, user_id, &shares_amount, share_index, idempotency_key, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
 }
 
 #[pyfunction]
@@ -169,9 +477,163 @@ fn get_balance_changes_on_market(market_id: i32) -> PyResult<HashMap<String, f64
 }
 
 #[pyfunction]
-fn resolve_market(market_id: i32, resolution: Option<i32>) -> PyResult<()> {
+#[pyo3(signature = (market_id, resolution, actor_id, idempotency_key=None))]
+fn resolve_market(market_id: i32, resolution: Option<i32>, actor_id: &str, idempotency_key: Option<&str>) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    pmarket::methods::resolve_market(market_id, resolution, actor_id, idempotency_key, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (market_id, value, actor_id, idempotency_key=None))]
+fn resolve_market_value<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    value: Bound<'py, PyAny>,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let value = pydecimal_to_bigdecimal(py, value)
+        .map_err(|e| PyValueError::new_err(format!("Invalid value: {}", e)))?;
+    pmarket::methods::resolve_market_value(market_id, &value, actor_id, idempotency_key, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (market_id, answer, actor_id, idempotency_key=None))]
+fn resolve_market_answer(
+    market_id: i32,
+    answer: &str,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    pmarket::methods::resolve_market_answer(market_id, answer, actor_id, idempotency_key, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (market_id, date, actor_id, idempotency_key=None))]
+fn resolve_market_date(
+    market_id: i32,
+    date: &str,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let date = parse_date("date", date)?;
+    pmarket::methods::resolve_market_date(market_id, date, actor_id, idempotency_key, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+fn unresolve_market(market_id: i32, actor_id: &str) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    pmarket::methods::unresolve_market(market_id, actor_id, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+// proposes an outcome index, probabilities, a value or a date, or with none
+// of them N/A
+#[pyfunction]
+#[pyo3(signature = (market_id, user_id, proposed_resolution, reason, proposed_probs=None, proposed_value=None, proposed_date=None))]
+#[allow(clippy::too_many_arguments)]
+fn open_dispute<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    user_id: &str,
+    proposed_resolution: Option<i32>,
+    reason: &str,
+    proposed_probs: Option<Vec<Bound<'py, PyAny>>>,
+    proposed_value: Option<Bound<'py, PyAny>>,
+    proposed_date: Option<&str>,
+) -> PyResult<i32> {
+    use pmarket::disputes::Proposal;
+
+    let mut conn = db::establish_connection();
+    let proposed_probs = proposed_probs
+        .map(|probs| probs.into_iter()
+            .map(|p| pydecimal_to_bigdecimal(py, p))
+            .collect::<PyResult<Vec<BigDecimal>>>())
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid proposed_probs: {}", e)))?;
+    let proposed_value = proposed_value
+        .map(|value| pydecimal_to_bigdecimal(py, value))
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid proposed_value: {}", e)))?;
+    let proposed_date = proposed_date
+        .map(|date| parse_date("proposed_date", date))
+        .transpose()?;
+    let proposal = match (proposed_resolution, proposed_probs, proposed_value, proposed_date) {
+        (None, None, None, None) => Proposal::NotApplicable,
+        (Some(idx), None, None, None) => Proposal::Outcome(idx),
+        (None, Some(probs), None, None) => Proposal::Probabilities(probs),
+        (None, None, Some(value), None) => Proposal::Value(value),
+        (None, None, None, Some(date)) => Proposal::Date(date),
+        _ => return Err(PyValueError::new_err("Propose only one of an outcome, probabilities, a value or a date")),
+    };
+    pmarket::disputes::open_dispute(market_id, user_id, proposal, reason, &mut conn)
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+fn vote_on_dispute(dispute_id: i32, user_id: &str, in_favor: bool) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    pmarket::disputes::vote_on_dispute(dispute_id, user_id, in_favor, &mut conn)
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+fn close_expired_disputes() -> PyResult<Vec<i32>> {
     let mut conn = db::establish_connection();
-    pmarket::methods::resolve_market(market_id, resolution, &mut conn)
+    pmarket::disputes::close_expired_disputes(&mut conn)
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+#[pyo3(signature = (market_id, resolution_probs, actor_id, idempotency_key=None))]
+fn resolve_market_prob<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    resolution_probs: Vec<Bound<'py, PyAny>>,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let resolution_probs = resolution_probs.into_iter()
+        .map(|p| pydecimal_to_bigdecimal(py, p))
+        .collect::<PyResult<Vec<BigDecimal>>>()
+        .map_err(|e| PyValueError::new_err(format!("Invalid resolution_probs: {}", e)))?;
+    pmarket::methods::resolve_market_prob(market_id, &resolution_probs, actor_id, idempotency_key, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))
+}
+
+#[pyfunction]
+fn add_liquidity<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    user_id: &str,
+    amount: Bound<'py, PyAny>
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let amount = pydecimal_to_bigdecimal(py, amount)
+        .map_err(|e| PyValueError::new_err(format!("Invalid amount: {}", e)))?;
+    pmarket::methods::add_liquidity(market_id, user_id, &amount, &mut conn)
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+fn withdraw_subsidy<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    user_id: &str,
+    amount: Bound<'py, PyAny>
+) -> PyResult<()> {
+    let mut conn = db::establish_connection();
+    let amount = pydecimal_to_bigdecimal(py, amount)
+        .map_err(|e| PyValueError::new_err(format!("Invalid amount: {}", e)))?;
+    pmarket::methods::withdraw_subsidy(market_id, user_id, &amount, &mut conn)
         .map_err(PyException::new_err)
 }
 
@@ -179,7 +641,7 @@ fn resolve_market(market_id: i32, resolution: Option<i32>) -> PyResult<()> {
 fn get_user_data<'py>(py: Python<'py>, id: &str) -> PyResult<Bound<'py, PyAny>> {
     let mut conn = db::establish_connection();
     let user_data: Value = pmarket::utils::get_user_data(id, &mut conn)
-        .map_err(PyException::new_err)?;
+        .map_err(|e| PyException::new_err(e.to_string()))?;
     let user_data: String = serde_json::to_string(&user_data)
         .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
 
@@ -193,7 +655,7 @@ fn get_user_data<'py>(py: Python<'py>, id: &str) -> PyResult<Bound<'py, PyAny>>
 fn get_market_data<'py>(py: Python<'py>, market_id: i32) -> PyResult<Bound<'py, PyAny>> {
     let mut conn = db::establish_connection();
     let market_data = pmarket::utils::get_market_data(market_id, &mut conn)
-        .map_err(PyException::new_err)?;
+        .map_err(|e| PyException::new_err(e.to_string()))?;
     let market_data: String = serde_json::to_string(&market_data)
         .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
 
@@ -204,23 +666,315 @@ fn get_market_data<'py>(py: Python<'py>, market_id: i32) -> PyResult<Bound<'py,
 }
 
 #[pyfunction]
-fn get_lmsr_info<'py>(
+#[pyo3(signature = (query, tags=Vec::new(), category=None, status=None, sort="relevance", limit=20))]
+fn search_markets<'py>(
     py: Python<'py>,
-    shares: Vec<Bound<'py, PyAny>>,
-    liquidity: f64
+    query: &str,
+    tags: Vec<String>,
+    category: Option<&str>,
+    status: Option<&str>,
+    sort: &str,
+    limit: i64
 ) -> PyResult<Bound<'py, PyAny>> {
-    let shares_f64: Vec<f64> = shares.iter()
-        .map(|s| s.extract::<f64>().unwrap_or(0.0))
-        .collect();
-    
-    let info = pmarket::utils::get_lmsr_info(&shares_f64, liquidity);
-    let info_str = serde_json::to_string(&info)
+    let mut conn = db::establish_connection();
+    let status = status
+        .map(pmarket::browse::MarketStatus::from_str)
+        .transpose()
+        .map_err(PyValueError::new_err)?;
+    let sort = pmarket::browse::MarketSort::from_str(sort)
+        .map_err(PyValueError::new_err)?;
+    let markets = pmarket::browse::search_markets(query, &tags, category, status, sort, limit, &mut conn)
+        .map_err(PyException::new_err)?;
+    let markets = markets.iter()
+        .map(|market| pmarket::utils::get_market_data(market.id, &mut conn))
+        .collect::<Result<Vec<Value>, pmarket::errors::Error>>()
+        .map_err(|e| PyException::new_err(e.to_string()))?;
+    let markets: String = serde_json::to_string(&markets)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_markets = json_cls.call1((markets,))?;
+    Ok(py_markets)
+}
+
+#[pyfunction]
+#[pyo3(signature = (status=None, owner_id=None, channel_id=None, created_after=None, created_before=None, sort="newest", cursor=None, limit=20))]
+#[allow(clippy::too_many_arguments)]
+fn list_markets<'py>(
+    py: Python<'py>,
+    status: Option<&str>,
+    owner_id: Option<String>,
+    channel_id: Option<String>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    sort: &str,
+    cursor: Option<&str>,
+    limit: i64
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let to_naive = |timestamp: i64| DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.naive_utc())
+        .ok_or_else(|| PyValueError::new_err("Invalid timestamp"));
+    let filter = pmarket::browse::MarketFilter {
+        status: status
+            .map(pmarket::browse::MarketStatus::from_str)
+            .transpose()
+            .map_err(PyValueError::new_err)?,
+        owner_id,
+        channel_id,
+        created_after: created_after.map(to_naive).transpose()?,
+        created_before: created_before.map(to_naive).transpose()?,
+    };
+    let sort = pmarket::browse::MarketSort::from_str(sort)
+        .map_err(PyValueError::new_err)?;
+    let page = pmarket::browse::list_markets(&filter, sort, cursor, limit, &mut conn)
+        .map_err(PyException::new_err)?;
+    let markets = page.markets.iter()
+        .map(|market| pmarket::utils::get_market_data(market.id, &mut conn))
+        .collect::<Result<Vec<Value>, pmarket::errors::Error>>()
+        .map_err(|e| PyException::new_err(e.to_string()))?;
+    let page: String = serde_json::to_string(&serde_json::json!({
+        "markets": markets,
+        "next_cursor": page.next_cursor,
+    }))
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_page = json_cls.call1((page,))?;
+    Ok(py_page)
+}
+
+#[pyfunction]
+fn get_dispute_data<'py>(py: Python<'py>, dispute_id: i32) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let dispute_data = pmarket::utils::get_dispute_data(dispute_id, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))?;
+    let dispute_data: String = serde_json::to_string(&dispute_data)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_dispute = json_cls.call1((dispute_data,))?;
+    Ok(py_dispute)
+}
+
+#[pyfunction]
+fn get_solvency_report<'py>(py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let report = pmarket::utils::get_solvency_report(&mut conn)
+        .map_err(PyException::new_err)?;
+    let report: String = serde_json::to_string(&report)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_report = json_cls.call1((report,))?;
+    Ok(py_report)
+}
+
+#[pyfunction]
+fn get_settings<'py>(py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let settings = pmarket::utils::get_settings_data(&mut conn)
+        .map_err(PyException::new_err)?;
+    let settings: String = serde_json::to_string(&settings)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_settings = json_cls.call1((settings,))?;
+    Ok(py_settings)
+}
+
+#[pyfunction]
+#[pyo3(signature = (min_markets=3))]
+fn get_leaderboard<'py>(py: Python<'py>, min_markets: usize) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let data = pmarket::utils::get_leaderboard(min_markets, &mut conn)
+        .map_err(PyException::new_err)?;
+    let data: String = serde_json::to_string(&data)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_data = json_cls.call1((data,))?;
+    Ok(py_data)
+}
+
+#[pyfunction]
+fn get_user_score<'py>(py: Python<'py>, user_id: &str) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let data = pmarket::utils::get_user_score(user_id, &mut conn)
+        .map_err(PyException::new_err)?;
+    let data: String = serde_json::to_string(&data)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_data = json_cls.call1((data,))?;
+    Ok(py_data)
+}
+
+#[pyfunction]
+#[pyo3(signature = (bucket_count=10))]
+fn get_calibration<'py>(py: Python<'py>, bucket_count: usize) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let data = pmarket::utils::get_calibration_data(bucket_count, &mut conn)
+        .map_err(PyException::new_err)?;
+    let data: String = serde_json::to_string(&data)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_data = json_cls.call1((data,))?;
+    Ok(py_data)
+}
+
+#[pyfunction]
+fn get_market_stats<'py>(py: Python<'py>, market_id: i32) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let data = pmarket::utils::get_market_stats(market_id, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))?;
+    let data: String = serde_json::to_string(&data)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_data = json_cls.call1((data,))?;
+    Ok(py_data)
+}
+
+#[pyfunction]
+fn render_market_view<'py>(py: Python<'py>, market_id: i32) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let view = pmarket::render::market_view(market_id, &mut conn)
+        .map_err(PyException::new_err)?;
+    let view: String = serde_json::to_string(&view)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_view = json_cls.call1((view,))?;
+    Ok(py_view)
+}
+
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+fn render_trade_view<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    user_id: &str,
+    shares_amount: Bound<'py, PyAny>,
+    buy: bool,
+    share_index: i32,
+    channel_id: &str,
+    ts: &str
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let shares_amount = pydecimal_to_bigdecimal(py, shares_amount)
+        .map_err(|e| PyValueError::new_err(format!("Invalid shares_amount: {}", e)))?;
+    let view = pmarket::render::trade_view(market_id, user_id, &shares_amount, buy, share_index, channel_id, ts, &mut conn)
+        .map_err(PyException::new_err)?;
+    let view: String = serde_json::to_string(&view)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_view = json_cls.call1((view,))?;
+    Ok(py_view)
+}
+
+#[pyfunction]
+fn render_reminder_view<'py>(py: Python<'py>, market_id: i32) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let view = pmarket::render::reminder_view(market_id, &mut conn)
+        .map_err(PyException::new_err)?;
+    let view: String = serde_json::to_string(&view)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_view = json_cls.call1((view,))?;
+    Ok(py_view)
+}
+
+#[pyfunction]
+fn render_add_answer_view<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    user_id: &str,
+    channel_id: &str,
+    ts: &str
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let view = pmarket::render::add_answer_view(market_id, user_id, channel_id, ts, &mut conn)
+        .map_err(PyException::new_err)?;
+    let view: String = serde_json::to_string(&view)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_view = json_cls.call1((view,))?;
+    Ok(py_view)
+}
+
+#[pyfunction]
+fn render_home_view<'py>(py: Python<'py>, user_id: &str) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let view = pmarket::render::home_view(user_id, &mut conn)
+        .map_err(PyException::new_err)?;
+    let view: String = serde_json::to_string(&view)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_view = json_cls.call1((view,))?;
+    Ok(py_view)
+}
+
+#[pyfunction]
+fn render_market_list<'py>(py: Python<'py>, market_ids: Vec<i32>) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let blocks = pmarket::render::market_list(&market_ids, &mut conn)
+        .map_err(PyException::new_err)?;
+    let blocks: String = serde_json::to_string(&blocks)
+        .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
+
+    let json = py.import("json")?;
+    let json_cls = json.getattr("loads")?;
+    let py_blocks = json_cls.call1((blocks,))?;
+    Ok(py_blocks)
+}
+
+#[pyfunction]
+#[pyo3(signature = (market_id, user_id, share_index, shares=None, amount=None))]
+fn quote_trade<'py>(
+    py: Python<'py>,
+    market_id: i32,
+    user_id: &str,
+    share_index: i32,
+    shares: Option<Bound<'py, PyAny>>,
+    amount: Option<Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut conn = db::establish_connection();
+    let size = match (shares, amount) {
+        (Some(shares), None) => pmarket::quotes::TradeSize::Shares(pydecimal_to_bigdecimal(py, shares)
+            .map_err(|e| PyValueError::new_err(format!("Invalid shares: {}", e)))?),
+        (None, Some(amount)) => pmarket::quotes::TradeSize::Amount(pydecimal_to_bigdecimal(py, amount)
+            .map_err(|e| PyValueError::new_err(format!("Invalid amount: {}", e)))?),
+        _ => return Err(PyValueError::new_err("Quote either shares or an amount")),
+    };
+    let quote = pmarket::utils::get_trade_quote(market_id, user_id, share_index, &size, &mut conn)
+        .map_err(|e| PyException::new_err(e.to_string()))?;
+    let quote: String = serde_json::to_string(&quote)
         .map_err(|e| PyException::new_err(format!("Serialization error: {}", e)))?;
 
     let json = py.import("json")?;
     let json_cls = json.getattr("loads")?;
-    let py_info = json_cls.call1((info_str,))?;
-    Ok(py_info)
+    let py_quote = json_cls.call1((quote,))?;
+    Ok(py_quote)
 }
 
 #[pymodule]
@@ -229,15 +983,53 @@ fn pmarket_slack(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(create_user, py)?)?;
     m.add_function(wrap_pyfunction!(try_create_user, py)?)?;
     m.add_function(wrap_pyfunction!(change_balance, py)?)?;
+    m.add_function(wrap_pyfunction!(claim_allowance, py)?)?;
+    m.add_function(wrap_pyfunction!(transfer, py)?)?;
+    m.add_function(wrap_pyfunction!(grant_role, py)?)?;
+    m.add_function(wrap_pyfunction!(revoke_role, py)?)?;
+    m.add_function(wrap_pyfunction!(load_settings, py)?)?;
+    m.add_function(wrap_pyfunction!(update_setting, py)?)?;
     m.add_function(wrap_pyfunction!(create_market, py)?)?;
+    m.add_function(wrap_pyfunction!(create_scalar_market, py)?)?;
+    m.add_function(wrap_pyfunction!(create_date_market, py)?)?;
+    m.add_function(wrap_pyfunction!(create_free_response_market, py)?)?;
+    m.add_function(wrap_pyfunction!(create_conditional_market, py)?)?;
+    m.add_function(wrap_pyfunction!(add_answer, py)?)?;
+    m.add_function(wrap_pyfunction!(edit_market, py)?)?;
+    m.add_function(wrap_pyfunction!(categorize_market, py)?)?;
     m.add_function(wrap_pyfunction!(create_market_slack_msg, py)?)?;
     m.add_function(wrap_pyfunction!(check_valid_trade, py)?)?;
     m.add_function(wrap_pyfunction!(create_trade, py)?)?;
     m.add_function(wrap_pyfunction!(get_positions, py)?)?;
     m.add_function(wrap_pyfunction!(get_balance_changes_on_market, py)?)?;
     m.add_function(wrap_pyfunction!(resolve_market, py)?)?;
+    m.add_function(wrap_pyfunction!(resolve_market_value, py)?)?;
+    m.add_function(wrap_pyfunction!(resolve_market_date, py)?)?;
+    m.add_function(wrap_pyfunction!(resolve_market_answer, py)?)?;
+    m.add_function(wrap_pyfunction!(resolve_market_prob, py)?)?;
+    m.add_function(wrap_pyfunction!(unresolve_market, py)?)?;
+    m.add_function(wrap_pyfunction!(open_dispute, py)?)?;
+    m.add_function(wrap_pyfunction!(vote_on_dispute, py)?)?;
+    m.add_function(wrap_pyfunction!(close_expired_disputes, py)?)?;
+    m.add_function(wrap_pyfunction!(add_liquidity, py)?)?;
+    m.add_function(wrap_pyfunction!(withdraw_subsidy, py)?)?;
     m.add_function(wrap_pyfunction!(get_user_data, py)?)?;
     m.add_function(wrap_pyfunction!(get_market_data, py)?)?;
-    m.add_function(wrap_pyfunction!(get_lmsr_info, py)?)?;
+    m.add_function(wrap_pyfunction!(search_markets, py)?)?;
+    m.add_function(wrap_pyfunction!(list_markets, py)?)?;
+    m.add_function(wrap_pyfunction!(get_dispute_data, py)?)?;
+    m.add_function(wrap_pyfunction!(quote_trade, py)?)?;
+    m.add_function(wrap_pyfunction!(get_solvency_report, py)?)?;
+    m.add_function(wrap_pyfunction!(get_settings, py)?)?;
+    m.add_function(wrap_pyfunction!(get_leaderboard, py)?)?;
+    m.add_function(wrap_pyfunction!(get_user_score, py)?)?;
+    m.add_function(wrap_pyfunction!(get_calibration, py)?)?;
+    m.add_function(wrap_pyfunction!(get_market_stats, py)?)?;
+    m.add_function(wrap_pyfunction!(render_market_view, py)?)?;
+    m.add_function(wrap_pyfunction!(render_trade_view, py)?)?;
+    m.add_function(wrap_pyfunction!(render_reminder_view, py)?)?;
+    m.add_function(wrap_pyfunction!(render_add_answer_view, py)?)?;
+    m.add_function(wrap_pyfunction!(render_home_view, py)?)?;
+    m.add_function(wrap_pyfunction!(render_market_list, py)?)?;
     Ok(())
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use diesel::prelude::*;
 use bigdecimal::BigDecimal;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = crate::schema::users)]
@@ -10,7 +10,7 @@ pub struct User {
     pub balance: BigDecimal,
 }
 
-#[derive(Queryable, Selectable)]
+#[derive(Queryable, Selectable, Clone)]
 #[diesel(table_name = crate::schema::markets)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Market {
@@ -24,6 +24,18 @@ pub struct Market {
     pub is_resolved: bool,
     pub resolution: Option<i32>,
     pub created_at: NaiveDateTime,
+    pub subsidy: BigDecimal,
+    pub resolution_probs: Option<Vec<Option<BigDecimal>>>,
+    pub resolution_kind: Option<String>,
+    pub fee_rate: BigDecimal,
+    pub fee_recipient: String,
+    pub category: Option<String>,
+    pub scalar_min: Option<BigDecimal>,
+    pub scalar_max: Option<BigDecimal>,
+    pub resolution_value: Option<BigDecimal>,
+    pub resolution_date: Option<NaiveDate>,
+    pub parent_market_id: Option<i32>,
+    pub parent_outcome: Option<i32>,
 }
 
 #[derive(Queryable, Selectable, Insertable)]
@@ -36,6 +48,17 @@ pub struct MarketSlackMsg {
     pub main: bool,
 }
 
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::market_outcomes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MarketOutcome {
+    pub market_id: i32,
+    pub share_index: i32,
+    pub label: String,
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<NaiveDate>,
+}
+
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = crate::schema::trades)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -47,6 +70,9 @@ pub struct Trade {
     pub share_index: i32,
     pub balance_change: BigDecimal,
     pub created_at: NaiveDateTime,
+    pub fee: BigDecimal,
+    pub probs_after: Vec<Option<BigDecimal>>,
+    pub from_answer: bool,
 }
 
 #[derive(Insertable)]
@@ -66,6 +92,12 @@ pub struct NewMarket {
     pub owner_id: String,
     pub liquidity: BigDecimal,
     pub remind_at: NaiveDateTime,
+    pub subsidy: BigDecimal,
+    pub fee_rate: BigDecimal,
+    pub fee_recipient: String,
+    pub scalar_min: Option<BigDecimal>,
+    pub scalar_max: Option<BigDecimal>,
+    pub bought_shares: Vec<BigDecimal>,
 }
 
 #[derive(Insertable)]
@@ -77,4 +109,269 @@ pub struct NewTrade {
     pub shares_amount: BigDecimal,
     pub share_index: i32,
     pub balance_change: BigDecimal,
+    pub fee: BigDecimal,
+    pub probs_after: Vec<BigDecimal>,
+    pub from_answer: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::liquidity_changes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewLiquidityChange {
+    pub market_id: i32,
+    pub user_id: String,
+    pub amount: BigDecimal,
+    pub liquidity_before: BigDecimal,
+    pub liquidity_after: BigDecimal,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::grants)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewGrant {
+    pub user_id: String,
+    pub amount: BigDecimal,
+    pub reason: String,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::resolutions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Resolution {
+    pub id: i32,
+    pub market_id: i32,
+    pub resolution: Option<i32>,
+    pub resolution_probs: Option<Vec<Option<BigDecimal>>>,
+    pub resolution_kind: String,
+    pub created_at: NaiveDateTime,
+    pub unresolved_at: Option<NaiveDateTime>,
+    pub parent_resolution_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::resolutions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewResolution {
+    pub market_id: i32,
+    pub resolution: Option<i32>,
+    pub resolution_probs: Option<Vec<BigDecimal>>,
+    pub resolution_kind: String,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::payouts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Payout {
+    pub id: i32,
+    pub resolution_id: i32,
+    pub user_id: String,
+    pub amount: BigDecimal,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::payouts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewPayout {
+    pub resolution_id: i32,
+    pub user_id: String,
+    pub amount: BigDecimal,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::disputes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Dispute {
+    pub id: i32,
+    pub market_id: i32,
+    pub resolution_id: i32,
+    pub opened_by: String,
+    pub proposed_resolution: Option<i32>,
+    pub proposed_probs: Option<Vec<Option<BigDecimal>>>,
+    pub reason: String,
+    pub created_at: NaiveDateTime,
+    pub closes_at: NaiveDateTime,
+    pub closed_at: Option<NaiveDateTime>,
+    pub overturned: Option<bool>,
+    pub proposed_value: Option<BigDecimal>,
+    pub proposed_date: Option<NaiveDate>,
+    pub close_error: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::disputes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewDispute {
+    pub market_id: i32,
+    pub resolution_id: i32,
+    pub opened_by: String,
+    pub proposed_resolution: Option<i32>,
+    pub proposed_probs: Option<Vec<BigDecimal>>,
+    pub proposed_value: Option<BigDecimal>,
+    pub proposed_date: Option<NaiveDate>,
+    pub reason: String,
+    pub closes_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::dispute_votes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewDisputeVote {
+    pub dispute_id: i32,
+    pub user_id: String,
+    pub in_favor: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::roles)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewRole {
+    pub user_id: String,
+    pub role: String,
+    pub channel_id: Option<String>,
+    pub granted_by: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::allowance_claims)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewAllowanceClaim {
+    pub user_id: String,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub amount: BigDecimal,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::transfers)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Transfer {
+    pub id: i32,
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub amount: BigDecimal,
+    pub memo: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::transfers)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTransfer {
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub amount: BigDecimal,
+    pub memo: Option<String>,
+}
+
+#[derive(Queryable, Selectable, AsChangeset, Clone)]
+#[diesel(table_name = crate::schema::settings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct Settings {
+    pub id: i32,
+    pub starting_balance: BigDecimal,
+    pub min_liquidity: BigDecimal,
+    pub max_liquidity: Option<BigDecimal>,
+    pub min_trade_size: BigDecimal,
+    pub default_fee_rate: BigDecimal,
+    pub max_fee_rate: BigDecimal,
+    pub max_open_markets_per_user: Option<i32>,
+    pub allowance_amount: BigDecimal,
+    pub allowance_period: String,
+    pub allowance_net_worth_cap: Option<BigDecimal>,
+    pub updated_at: NaiveDateTime,
+    pub house_account: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::market_tags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewMarketTag {
+    pub market_id: i32,
+    pub tag: String,
+}
+
+#[derive(Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub key_hash: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub user_id: Option<String>,
+    pub role: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewApiKey {
+    pub name: String,
+    pub key_hash: String,
+    pub user_id: Option<String>,
+    pub role: String,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhooks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<Option<String>>,
+    pub created_at: NaiveDateTime,
+    pub disabled_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::webhooks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: String,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub failed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::idempotency_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IdempotencyKey {
+    pub user_id: String,
+    pub key: String,
+    pub action: String,
+    pub request_hash: String,
+    pub result: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::idempotency_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewIdempotencyKey {
+    pub user_id: String,
+    pub key: String,
+    pub action: String,
+    pub request_hash: String,
+    pub result: String,
 }
\ No newline at end of file
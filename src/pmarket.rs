@@ -1,3 +1,23 @@
 pub mod methods;
 pub mod lmsr;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod audit;
+pub mod disputes;
+pub mod roles;
+pub mod allowance;
+pub mod transfers;
+pub mod settings;
+pub mod browse;
+pub mod scoring;
+pub mod stats;
+pub mod api_keys;
+pub mod events;
+pub mod webhooks;
+pub mod render;
+pub mod quotes;
+pub mod idempotency;
+pub mod scalar;
+pub mod dates;
+pub mod answers;
+pub mod conditional;
+pub mod errors;
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::str::FromStr;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::methods::{get_net_worth, grant_balance};
+use crate::pmarket::settings::get_settings;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AllowancePeriod {
+    Daily,
+    Weekly,
+}
+
+impl AllowancePeriod {
+    pub fn start_of(&self, time: &NaiveDateTime) -> NaiveDate {
+        let date = time.date();
+        match self {
+            AllowancePeriod::Daily => date,
+            AllowancePeriod::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    pub fn length(&self) -> Duration {
+        match self {
+            AllowancePeriod::Daily => Duration::days(1),
+            AllowancePeriod::Weekly => Duration::weeks(1),
+        }
+    }
+
+    // the start of the first period that doesn't begin before `date`
+    pub fn first_start_from(&self, date: NaiveDate) -> NaiveDate {
+        let start = self.start_of(&date.and_time(NaiveTime::MIN));
+        if start < date { start + self.length() } else { start }
+    }
+}
+
+impl FromStr for AllowancePeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(AllowancePeriod::Daily),
+            "weekly" => Ok(AllowancePeriod::Weekly),
+            _ => Err(format!("Unknown allowance period: {}", s)),
+        }
+    }
+}
+
+pub fn claim_allowance(
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<BigDecimal, String> {
+    let settings = get_settings(conn)?;
+    if settings.allowance_amount.is_zero() {
+        return Err("Allowances are disabled".to_string());
+    }
+    let period = settings.allowance_period()?;
+    let now = chrono::Utc::now().naive_utc();
+    let period_start = period.start_of(&now);
+    let next_period = period_start + period.length();
+
+    // users worth at least the cap get nothing, others get topped up to it at most
+    let mut amount = settings.allowance_amount.clone();
+    if let Some(cap) = &settings.allowance_net_worth_cap {
+        let net_worth = get_net_worth(user_id, conn)?;
+        amount = amount.min(cap - net_worth);
+    }
+    if amount <= BigDecimal::zero() {
+        return Err("Your net worth is too high to claim an allowance".to_string());
+    }
+
+    let new_claim = NewAllowanceClaim {
+        user_id: user_id.to_string(),
+        period_start,
+        period_end: next_period,
+        amount: amount.clone(),
+    };
+
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        // a claim made before the period setting changed still covers the days
+        // it was for, so a claim can't start until every earlier one has ended
+        let claimed_until = allowance_claims::table
+            .filter(allowance_claims::user_id.eq(user_id))
+            .filter(allowance_claims::period_start.lt(next_period))
+            .filter(allowance_claims::period_end.gt(period_start))
+            .select(diesel::dsl::max(allowance_claims::period_end))
+            .first::<Option<NaiveDate>>(conn)
+            .map_err(|e| {
+                err = Some(format!("Error fetching allowance claims: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        if let Some(claimed_until) = claimed_until {
+            err = Some(format!(
+                "Allowance already claimed, the next one is available on {}",
+                period.first_start_from(claimed_until)
+            ));
+            return Err(DieselError::RollbackTransaction);
+        }
+        // the primary key makes a second claim in the same period fail
+        diesel::insert_into(allowance_claims::table)
+            .values(&new_claim)
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(match e {
+                    DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => format!(
+                        "Allowance already claimed, the next one is available on {}",
+                        next_period
+                    ),
+                    e => format!("Error recording allowance claim: {}", e),
+                });
+                DieselError::RollbackTransaction
+            })?;
+        grant_balance(user_id, &amount, &format!("allowance {}", period_start), conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(amount)
+}
\ No newline at end of file
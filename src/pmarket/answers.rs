@@ -0,0 +1,202 @@
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde_json::json;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::dates::get_market_outcomes;
+use crate::pmarket::errors::Error;
+use crate::pmarket::idempotency::with_idempotency_key;
+use crate::pmarket::lmsr::{prob, split_outcome};
+use crate::pmarket::methods::{change_balance, find_market, get_positions};
+
+// free-response markets start out with Other and the answers they're created
+// with, and new answers split off from Other
+pub const OTHER: i32 = 0;
+pub const OTHER_LABEL: &str = "Other";
+// the most answers a market takes, Other not included, so it fits in a Slack message
+pub const MAX_ANSWERS: usize = 30;
+const MAX_ANSWER_LENGTH: usize = 100;
+
+pub fn is_free_response(
+    outcomes: &[MarketOutcome],
+) -> bool {
+    outcomes.first().is_some_and(|outcome| outcome.starts_on.is_none())
+}
+
+fn check_answer(
+    answer: &str,
+    labels: &[String],
+) -> Result<String, String> {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Err("Answers can't be empty".to_string());
+    }
+    if answer.chars().count() > MAX_ANSWER_LENGTH {
+        return Err(format!("Answers can be at most {} characters", MAX_ANSWER_LENGTH));
+    }
+    if labels.iter().any(|label| label.to_lowercase() == answer.to_lowercase()) {
+        return Err(format!("{} is already an answer", answer));
+    }
+    Ok(answer.to_string())
+}
+
+// the outcome labels of a new free-response market, Other first
+pub fn answer_labels(
+    answers: &[String],
+) -> Result<Vec<String>, String> {
+    if answers.is_empty() {
+        return Err("Free-response markets need at least one answer".to_string());
+    }
+    if answers.len() > MAX_ANSWERS {
+        return Err(format!("A market can have at most {} answers", MAX_ANSWERS));
+    }
+    answers.iter()
+        .try_fold(vec![OTHER_LABEL.to_string()], |mut labels, answer| {
+            labels.push(check_answer(answer, &labels)?);
+            Ok(labels)
+        })
+}
+
+// what adding an answer to `market` costs right now
+pub fn answer_cost(
+    market: &Market,
+) -> BigDecimal {
+    split_outcome(market, OTHER).1
+}
+
+// the outcome an answer names, matched regardless of case, Other included
+pub fn find_answer(
+    outcomes: &[MarketOutcome],
+    answer: &str,
+) -> Option<i32> {
+    let answer = answer.trim().to_lowercase();
+    outcomes.iter()
+        .find(|outcome| outcome.label.to_lowercase() == answer)
+        .map(|outcome| outcome.share_index)
+}
+
+fn insert_answer(
+    market_id: i32,
+    user_id: &str,
+    answer: &str,
+    conn: &mut PgConnection,
+) -> Result<i32, String> {
+    let market = markets::table
+        .filter(markets::id.eq(market_id))
+        .for_update()
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?;
+    if market.is_resolved {
+        return Err("Market is already resolved".to_string());
+    }
+    let outcomes = get_market_outcomes(market_id, conn)?;
+    if !is_free_response(&outcomes) {
+        return Err("Only free-response markets take new answers".to_string());
+    }
+    if outcomes.len() > MAX_ANSWERS {
+        return Err(format!("A market can have at most {} answers", MAX_ANSWERS));
+    }
+    let labels = outcomes.iter()
+        .map(|outcome| outcome.label.clone())
+        .collect::<Vec<String>>();
+    let label = check_answer(answer, &labels)?;
+
+    // whoever adds the answer pays for keeping the market solvent with it
+    let (split, cost) = split_outcome(&market, OTHER);
+    let balance = users::table
+        .filter(users::id.eq(user_id))
+        .select(users::balance)
+        .first::<BigDecimal>(conn)
+        .map_err(|e| format!("Error fetching user balance: {}", e))?;
+    if balance < cost {
+        return Err(format!(
+            "Adding an answer costs {:.0}, your balance is {:.0}",
+            cost, balance
+        ));
+    }
+    let share_index = market.bought_shares.len() as i32;
+    let probs_after = prob(&split).into_iter()
+        .map(|p| p.with_scale_round(4, RoundingMode::HalfEven))
+        .collect::<Vec<BigDecimal>>();
+    let positions = get_positions(market_id, conn)?;
+
+    change_balance(user_id, &-&cost, conn)
+        .map_err(|e| format!("Error deducting the cost of the answer: {}", e))?;
+    diesel::update(markets::table.filter(markets::id.eq(market_id)))
+        .set(markets::bought_shares.eq(&split.bought_shares))
+        .execute(conn)
+        .map_err(|e| format!("Error updating market shares: {}", e))?;
+    diesel::insert_into(market_outcomes::table)
+        .values(&MarketOutcome {
+            market_id,
+            share_index,
+            label,
+            starts_on: None,
+            ends_on: None,
+        })
+        .execute(conn)
+        .map_err(|e| format!("Error adding answer: {}", e))?;
+
+    // the cost is paid in as a trade so an N/A resolution refunds it, and
+    // whoever held Other had bet on the new answer too, so they get as many
+    // of its shares for free, or owe as many when they sold Other short;
+    // neither is a forecast, so statistics and scores leave them out
+    let seed = NewTrade {
+        market_id,
+        user_id: user_id.to_string(),
+        shares_amount: BigDecimal::zero(),
+        share_index,
+        balance_change: -cost,
+        fee: BigDecimal::zero(),
+        probs_after: probs_after.clone(),
+        from_answer: true,
+    };
+    let copies = positions.into_iter()
+        .filter(|(_, shares)| !shares[OTHER as usize].is_zero())
+        .map(|(holder_id, shares)| NewTrade {
+            market_id,
+            user_id: holder_id,
+            shares_amount: shares[OTHER as usize].clone(),
+            share_index,
+            balance_change: BigDecimal::zero(),
+            fee: BigDecimal::zero(),
+            probs_after: probs_after.clone(),
+            from_answer: true,
+        });
+    diesel::insert_into(trades::table)
+        .values(&std::iter::once(seed).chain(copies).collect::<Vec<NewTrade>>())
+        .execute(conn)
+        .map_err(|e| format!("Error recording answer: {}", e))?;
+
+    Ok(share_index)
+}
+
+// adds an answer to a free-response market, returning its outcome index
+pub fn add_answer(
+    market_id: i32,
+    user_id: &str,
+    answer: &str,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<i32, Error> {
+    find_market(market_id, conn)?;
+    let request = json!({ "market_id": market_id, "answer": answer });
+    with_idempotency_key(idempotency_key, user_id, "add_answer", &request, conn, |conn| {
+        let mut err = None;
+        let mut share_index = None;
+        let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+            let added = insert_answer(market_id, user_id, answer, conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })?;
+            share_index = Some(added);
+            Ok(())
+        });
+        if let Err(e) = transaction {
+            return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+        }
+        Ok(share_index.unwrap())
+    })
+}
\ No newline at end of file
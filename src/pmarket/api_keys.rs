@@ -0,0 +1,137 @@
+use std::str::FromStr;
+use diesel::prelude::*;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::errors::Error;
+use crate::pmarket::methods::try_create_user;
+
+const KEY_PREFIX: &str = "pm_";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyRole {
+    // acts as the user the key belongs to
+    User,
+    // acts as whichever user a request names, for trusted services
+    Service,
+}
+
+impl KeyRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyRole::User => "user",
+            KeyRole::Service => "service",
+        }
+    }
+}
+
+impl FromStr for KeyRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(KeyRole::User),
+            "service" => Ok(KeyRole::Service),
+            _ => Err(format!("Unknown API key role: {}", s)),
+        }
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+// returns the key itself, which isn't stored and can't be shown again; the
+// key acts as `user_id`, or without one is a service key that can act as anyone
+pub fn create_api_key(
+    name: &str,
+    user_id: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("API key names can't be empty".to_string());
+    }
+    let role = match user_id {
+        Some(user_id) => {
+            try_create_user(user_id, conn)?;
+            KeyRole::User
+        },
+        None => KeyRole::Service,
+    };
+    let mut secret = [0u8; 32];
+    rand::rng().fill_bytes(&mut secret);
+    let key = format!("{}{}", KEY_PREFIX, hex::encode(secret));
+
+    diesel::insert_into(api_keys::table)
+        .values(&NewApiKey {
+            name: name.to_string(),
+            key_hash: hash_key(&key),
+            user_id: user_id.map(str::to_string),
+            role: role.as_str().to_string(),
+        })
+        .execute(conn)
+        .map_err(|e| format!("Error creating API key: {}", e))?;
+    Ok(key)
+}
+
+pub fn revoke_api_key(
+    name: &str,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let revoked = diesel::update(api_keys::table
+        .filter(api_keys::name.eq(name))
+        .filter(api_keys::revoked_at.is_null()))
+        .set(api_keys::revoked_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+        .map_err(|e| format!("Error revoking API key: {}", e))?;
+    if revoked == 0 {
+        return Err(format!("No active API key named {}", name));
+    }
+    Ok(())
+}
+
+pub fn list_api_keys(
+    conn: &mut PgConnection,
+) -> Result<Vec<ApiKey>, String> {
+    api_keys::table
+        .order(api_keys::id)
+        .load::<ApiKey>(conn)
+        .map_err(|e| format!("Error fetching API keys: {}", e))
+}
+
+pub fn authenticate_api_key(
+    key: &str,
+    conn: &mut PgConnection,
+) -> Result<ApiKey, String> {
+    let api_key = api_keys::table
+        .filter(api_keys::key_hash.eq(hash_key(key)))
+        .filter(api_keys::revoked_at.is_null())
+        .first::<ApiKey>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching API key: {}", e))?
+        .ok_or_else(|| "Invalid API key".to_string())?;
+    diesel::update(api_keys::table.filter(api_keys::id.eq(api_key.id)))
+        .set(api_keys::last_used_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+        .map_err(|e| format!("Error updating API key: {}", e))?;
+    Ok(api_key)
+}
+
+// the user a request made with `api_key` acts as, given the one the request
+// names if any; a user key can only act as its own user
+pub fn acting_user(
+    api_key: &ApiKey,
+    requested: Option<&str>,
+) -> Result<String, Error> {
+    match (KeyRole::from_str(&api_key.role)?, &api_key.user_id, requested) {
+        (KeyRole::User, Some(user_id), Some(requested)) if requested != user_id => {
+            Err(Error::forbidden(format!("Only {} can be acted as with this API key", user_id)))
+        },
+        (KeyRole::User, Some(user_id), _) => Ok(user_id.clone()),
+        (KeyRole::User, None, _) => Err("API key has no user".to_string().into()),
+        (KeyRole::Service, _, Some(requested)) => Ok(requested.to_string()),
+        (KeyRole::Service, _, None) => Err("Service API keys have to say which user they act as".to_string().into()),
+    }
+}
\ No newline at end of file
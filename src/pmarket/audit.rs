@@ -0,0 +1,117 @@
+use bigdecimal::{BigDecimal, Zero};
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::lmsr::cost_function;
+use crate::pmarket::methods::{get_positions, market_bankroll, market_fees};
+
+pub struct MarketSolvency {
+    pub market_id: i32,
+    pub bankroll: BigDecimal,
+    // trading fees held until the market resolves, apart from the bankroll
+    pub fees_held: BigDecimal,
+    // LMSR guarantees the bankroll covers any outcome while it stays above this
+    pub cost_bound: BigDecimal,
+    pub worst_case_payout: BigDecimal,
+    pub negative_positions: Vec<String>,
+}
+
+impl MarketSolvency {
+    pub fn is_solvent(&self) -> bool {
+        self.bankroll >= self.worst_case_payout && self.negative_positions.is_empty()
+    }
+}
+
+pub struct SolvencyReport {
+    pub markets: Vec<MarketSolvency>,
+    pub total_balances: BigDecimal,
+    pub total_bankrolls: BigDecimal,
+    pub total_fees_held: BigDecimal,
+    pub total_grants: BigDecimal,
+}
+
+impl SolvencyReport {
+    pub fn total_coins(&self) -> BigDecimal {
+        &self.total_balances + &self.total_bankrolls + &self.total_fees_held
+    }
+
+    pub fn discrepancy(&self) -> BigDecimal {
+        self.total_coins() - &self.total_grants
+    }
+
+    pub fn insolvent_markets(&self) -> Vec<&MarketSolvency> {
+        self.markets.iter()
+            .filter(|m| !m.is_solvent())
+            .collect()
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.insolvent_markets().is_empty() && self.discrepancy().is_zero()
+    }
+}
+
+pub fn check_market_solvency(
+    market: &Market,
+    conn: &mut PgConnection,
+) -> Result<MarketSolvency, String> {
+    let bankroll = market_bankroll(market, conn)?;
+    let positions = get_positions(market.id, conn)?;
+
+    let worst_case_payout = (0..market.bought_shares.len())
+        .map(|idx| positions.values()
+            .map(|shares| shares[idx].clone())
+            .sum::<BigDecimal>())
+        .max()
+        .unwrap_or_else(BigDecimal::zero);
+    let mut negative_positions = positions.into_iter()
+        .filter(|(_, shares)| shares.iter().any(|s| *s < BigDecimal::zero()))
+        .map(|(user_id, _)| user_id)
+        .collect::<Vec<String>>();
+    negative_positions.sort();
+
+    Ok(MarketSolvency {
+        market_id: market.id,
+        bankroll,
+        fees_held: market_fees(market.id, conn)?,
+        cost_bound: cost_function(market),
+        worst_case_payout,
+        negative_positions,
+    })
+}
+
+pub fn audit(
+    conn: &mut PgConnection,
+) -> Result<SolvencyReport, String> {
+    let open_markets = markets::table
+        .filter(markets::is_resolved.eq(false))
+        .order(markets::id)
+        .load::<Market>(conn)
+        .map_err(|e| format!("Error fetching markets: {}", e))?;
+
+    let markets_solvency = open_markets.iter()
+        .map(|market| check_market_solvency(market, conn))
+        .collect::<Result<Vec<MarketSolvency>, String>>()?;
+
+    let total_balances = users::table
+        .select(diesel::dsl::sum(users::balance))
+        .first::<Option<BigDecimal>>(conn)
+        .map_err(|e| format!("Error fetching balances: {}", e))?
+        .unwrap_or_else(BigDecimal::zero);
+    let total_grants = grants::table
+        .select(diesel::dsl::sum(grants::amount))
+        .first::<Option<BigDecimal>>(conn)
+        .map_err(|e| format!("Error fetching grants: {}", e))?
+        .unwrap_or_else(BigDecimal::zero);
+
+    Ok(SolvencyReport {
+        total_bankrolls: markets_solvency.iter()
+            .map(|m| m.bankroll.clone())
+            .sum(),
+        total_fees_held: markets_solvency.iter()
+            .map(|m| m.fees_held.clone())
+            .sum(),
+        markets: markets_solvency,
+        total_balances,
+        total_grants,
+    })
+}
\ No newline at end of file
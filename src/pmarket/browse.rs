@@ -0,0 +1,342 @@
+use std::str::FromStr;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::dsl::sql;
+use diesel::result::Error as DieselError;
+use diesel::pg::Pg;
+use diesel::sql_types::{Bool, Float, Integer, Text, Untyped};
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::roles::require_market_manager;
+
+const MAX_TAGS: usize = 10;
+const MAX_TAG_LENGTH: usize = 32;
+
+// the same expression markets_search_idx is built on, so the index gets used
+const SEARCH_VECTOR: &str = "to_tsvector('english', markets.title || ' ' || markets.description)";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarketStatus {
+    // still trading
+    Open,
+    // past its resolution date, waiting for the owner to resolve it
+    Closed,
+    Resolved,
+}
+
+impl FromStr for MarketStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(MarketStatus::Open),
+            "closed" => Ok(MarketStatus::Closed),
+            "resolved" => Ok(MarketStatus::Resolved),
+            _ => Err(format!("Unknown market status: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarketSort {
+    // best text match first, newest when there is no query
+    Relevance,
+    Newest,
+    MostTraded,
+    ClosingSoon,
+    ClosestToHalf,
+}
+
+impl MarketSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarketSort::Relevance => "relevance",
+            MarketSort::Newest => "newest",
+            MarketSort::MostTraded => "most_traded",
+            MarketSort::ClosingSoon => "closing_soon",
+            MarketSort::ClosestToHalf => "closest_to_half",
+        }
+    }
+
+    // the SQL expression markets are ordered by, its type and whether it's descending;
+    // ties are broken by id in the same direction
+    fn key(&self) -> (&'static str, &'static str, bool) {
+        match self {
+            MarketSort::Relevance | MarketSort::Newest => ("markets.id", "integer", true),
+            MarketSort::MostTraded => (
+                "(SELECT count(*) FROM trades WHERE trades.market_id = markets.id AND NOT trades.from_answer)",
+                "bigint",
+                true,
+            ),
+            MarketSort::ClosingSoon => ("markets.remind_at", "timestamp", false),
+            // the YES probability is 1 / (1 + e^((no - yes) / b)), so it gets
+            // closer to 50% as the share difference shrinks relative to b; for
+            // markets with more outcomes, the spread between the most and least
+            // bought one stands in for it
+            MarketSort::ClosestToHalf => (
+                "(SELECT max(s) - min(s) FROM unnest(markets.bought_shares) s) / GREATEST(markets.liquidity, 0.0001)",
+                "numeric",
+                false,
+            ),
+        }
+    }
+
+    fn order_clause(&self) -> String {
+        let (expr, _, descending) = self.key();
+        let direction = if descending { "DESC" } else { "ASC" };
+        format!("{} {}, markets.id {}", expr, direction, direction)
+    }
+}
+
+impl FromStr for MarketSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relevance" => Ok(MarketSort::Relevance),
+            "newest" => Ok(MarketSort::Newest),
+            "most_traded" => Ok(MarketSort::MostTraded),
+            "closing_soon" => Ok(MarketSort::ClosingSoon),
+            "closest_to_half" => Ok(MarketSort::ClosestToHalf),
+            _ => Err(format!("Unknown market sort: {}", s)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MarketFilter {
+    pub status: Option<MarketStatus>,
+    pub owner_id: Option<String>,
+    // any channel the market was posted in, not only the one it was announced in
+    pub channel_id: Option<String>,
+    pub created_after: Option<NaiveDateTime>,
+    pub created_before: Option<NaiveDateTime>,
+}
+
+pub struct MarketPage {
+    pub markets: Vec<Market>,
+    // pass back to list_markets to get the page after this one
+    pub next_cursor: Option<String>,
+}
+
+// cursors are "<sort>~<id>~<sort key>", the position of the last market on a page
+fn parse_cursor(
+    cursor: &str,
+    sort: MarketSort,
+) -> Result<(i32, String), String> {
+    let invalid = || "Invalid cursor".to_string();
+    let (cursor_sort, rest) = cursor.split_once('~').ok_or_else(invalid)?;
+    let (id, key) = rest.split_once('~').ok_or_else(invalid)?;
+    if cursor_sort != sort.as_str() {
+        return Err("Cursor belongs to a different sort order".to_string());
+    }
+    Ok((id.parse::<i32>().map_err(|_| invalid())?, key.to_string()))
+}
+
+fn filter_status<'a, ST>(
+    query: markets::BoxedQuery<'a, Pg, ST>,
+    status: Option<MarketStatus>,
+) -> markets::BoxedQuery<'a, Pg, ST> {
+    let now = chrono::Utc::now().naive_utc();
+    match status {
+        Some(MarketStatus::Open) => query
+            .filter(markets::is_resolved.eq(false))
+            .filter(markets::remind_at.gt(now)),
+        Some(MarketStatus::Closed) => query
+            .filter(markets::is_resolved.eq(false))
+            .filter(markets::remind_at.le(now)),
+        Some(MarketStatus::Resolved) => query
+            .filter(markets::is_resolved.eq(true)),
+        None => query,
+    }
+}
+
+pub fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    if tag.is_empty() {
+        return Err("Tags can't be empty".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!("Tags can be at most {} characters", MAX_TAG_LENGTH));
+    }
+    Ok(tag)
+}
+
+pub fn get_market_tags(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Vec<String>, String> {
+    market_tags::table
+        .filter(market_tags::market_id.eq(market_id))
+        .order(market_tags::tag)
+        .select(market_tags::tag)
+        .load::<String>(conn)
+        .map_err(|e| format!("Error fetching market tags: {}", e))
+}
+
+pub fn categorize_market(
+    market_id: i32,
+    actor_id: &str,
+    category: Option<&str>,
+    tags: &[String],
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    require_market_manager(actor_id, market_id, conn)?;
+
+    let category = category
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty());
+    let mut tags = tags.iter()
+        .map(|tag| normalize_tag(tag))
+        .collect::<Result<Vec<String>, String>>()?;
+    tags.sort();
+    tags.dedup();
+    if tags.len() > MAX_TAGS {
+        return Err(format!("Markets can have at most {} tags", MAX_TAGS));
+    }
+
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        diesel::update(markets::table.filter(markets::id.eq(market_id)))
+            .set(markets::category.eq(&category))
+            .execute(conn)
+            .map_err(|e| {
+                err = Some(format!("Error updating market category: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        diesel::delete(market_tags::table.filter(market_tags::market_id.eq(market_id)))
+            .execute(conn)
+            .map_err(|e| {
+                err = Some(format!("Error removing market tags: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        diesel::insert_into(market_tags::table)
+            .values(tags.iter()
+                .map(|tag| NewMarketTag {
+                    market_id,
+                    tag: tag.clone(),
+                })
+                .collect::<Vec<NewMarketTag>>())
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error adding market tags: {}", e));
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(())
+}
+
+pub fn search_markets(
+    query: &str,
+    tags: &[String],
+    category: Option<&str>,
+    status: Option<MarketStatus>,
+    sort: MarketSort,
+    limit: i64,
+    conn: &mut PgConnection,
+) -> Result<Vec<Market>, String> {
+    let query = query.trim();
+    let mut markets_query = markets::table
+        .into_boxed();
+
+    if !query.is_empty() {
+        markets_query = markets_query.filter(
+            sql::<Bool>(&format!("{} @@ websearch_to_tsquery('english', ", SEARCH_VECTOR))
+                .bind::<Text, _>(query.to_string())
+                .sql(")")
+        );
+    }
+    for tag in tags {
+        let tag = normalize_tag(tag)?;
+        markets_query = markets_query.filter(markets::id.eq_any(
+            market_tags::table
+                .filter(market_tags::tag.eq(tag))
+                .select(market_tags::market_id)
+        ));
+    }
+    if let Some(category) = category {
+        markets_query = markets_query.filter(markets::category.eq(category.trim().to_lowercase()));
+    }
+    markets_query = filter_status(markets_query, status);
+    markets_query = match sort {
+        MarketSort::Relevance if !query.is_empty() => markets_query
+            .order(
+                sql::<Float>(&format!("ts_rank({}, websearch_to_tsquery('english', ", SEARCH_VECTOR))
+                    .bind::<Text, _>(query.to_string())
+                    .sql(")) DESC")
+            )
+            .then_order_by(markets::id.desc()),
+        _ => markets_query
+            .order(sql::<Untyped>(&sort.order_clause())),
+    };
+
+    markets_query
+        .limit(limit)
+        .load::<Market>(conn)
+        .map_err(|e| format!("Error searching markets: {}", e))
+
+}
+
+pub fn list_markets(
+    filter: &MarketFilter,
+    sort: MarketSort,
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut PgConnection,
+) -> Result<MarketPage, String> {
+    let (key_expr, key_type, descending) = sort.key();
+    let mut markets_query = markets::table
+        .select((Market::as_select(), sql::<Text>(&format!("CAST({} AS TEXT)", key_expr))))
+        .into_boxed();
+
+    markets_query = filter_status(markets_query, filter.status);
+    if let Some(owner_id) = &filter.owner_id {
+        markets_query = markets_query.filter(markets::owner_id.eq(owner_id.clone()));
+    }
+    if let Some(channel_id) = &filter.channel_id {
+        markets_query = markets_query.filter(markets::id.eq_any(
+            market_slack_msg::table
+                .filter(market_slack_msg::channel_id.eq(channel_id.clone()))
+                .select(market_slack_msg::market_id)
+        ));
+    }
+    if let Some(created_after) = filter.created_after {
+        markets_query = markets_query.filter(markets::created_at.ge(created_after));
+    }
+    if let Some(created_before) = filter.created_before {
+        markets_query = markets_query.filter(markets::created_at.lt(created_before));
+    }
+    if let Some(cursor) = cursor {
+        let (cursor_id, cursor_key) = parse_cursor(cursor, sort)?;
+        // row comparison, so markets sharing the cursor's key continue by id
+        let comparison = if descending { "<" } else { ">" };
+        markets_query = markets_query.filter(
+            sql::<Bool>(&format!("({}, markets.id) {} (CAST(", key_expr, comparison))
+                .bind::<Text, _>(cursor_key)
+                .sql(&format!(" AS {}), ", key_type))
+                .bind::<Integer, _>(cursor_id)
+                .sql(")")
+        );
+    }
+
+    // one extra row tells whether there is a next page
+    let mut rows = markets_query
+        .order(sql::<Untyped>(&sort.order_clause()))
+        .limit(limit + 1)
+        .load::<(Market, String)>(conn)
+        .map_err(|e| format!("Error listing markets: {}", e))?;
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit.max(0) as usize);
+
+    let next_cursor = rows.last()
+        .filter(|_| has_more)
+        .map(|(market, key)| format!("{}~{}~{}", sort.as_str(), market.id, key));
+    Ok(MarketPage {
+        markets: rows.into_iter().map(|(market, _)| market).collect(),
+        next_cursor,
+    })
+}
\ No newline at end of file
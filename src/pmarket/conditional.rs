@@ -0,0 +1,134 @@
+use bigdecimal::{BigDecimal, One};
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::methods::{ResolutionKind, apply_resolution, resolution_kind, reverse_resolution};
+use crate::pmarket::scalar::scalar_range;
+
+// a conditional market asks its question assuming its parent market resolves
+// to `parent_outcome`; when the parent resolves any other way, including N/A
+// or partly to the outcome, the question is moot and every trade is refunded
+pub fn condition(
+    market: &Market,
+) -> Option<(i32, i32)> {
+    market.parent_market_id.zip(market.parent_outcome)
+}
+
+// whether a resolution paid out `outcome` in full
+fn resolved_to(
+    resolution_probs: Option<&[BigDecimal]>,
+    outcome: i32,
+) -> bool {
+    usize::try_from(outcome).ok()
+        .and_then(|idx| resolution_probs?.get(idx))
+        .is_some_and(|p| p.is_one())
+}
+
+// checks a market can have a conditional market on `parent_outcome`, locking
+// it so it can't resolve before the conditional market exists
+pub fn check_parent(
+    parent_market_id: i32,
+    parent_outcome: i32,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let parent = markets::table
+        .filter(markets::id.eq(parent_market_id))
+        .for_update()
+        .first::<Market>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching parent market: {}", e))?
+        .ok_or_else(|| format!("Market {} doesn't exist", parent_market_id))?;
+    if parent.is_resolved {
+        return Err("Markets can't be conditional on a resolved market".to_string());
+    }
+    if scalar_range(&parent).is_some() {
+        return Err("Markets can't be conditional on a scalar market".to_string());
+    }
+    if usize::try_from(parent_outcome).is_err() || parent_outcome as usize >= parent.bought_shares.len() {
+        return Err(format!("Market {} has no outcome {}", parent_market_id, parent_outcome));
+    }
+    Ok(())
+}
+
+// a conditional market only resolves to an outcome once its condition is met
+pub fn check_condition_met(
+    market: &Market,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let Some((parent_market_id, parent_outcome)) = condition(market) else {
+        return Ok(());
+    };
+    let parent = markets::table
+        .filter(markets::id.eq(parent_market_id))
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching parent market: {}", e))?;
+    let resolution_probs = parent.resolution_probs
+        .map(|probs| probs.into_iter().map(Option::unwrap_or_default).collect::<Vec<BigDecimal>>());
+    if !parent.is_resolved || !resolved_to(resolution_probs.as_deref(), parent_outcome) {
+        return Err(format!(
+            "This market can only resolve once market {} resolves to its condition, resolve it N/A to cancel it",
+            parent_market_id,
+        ));
+    }
+    Ok(())
+}
+
+// resolves N/A the conditional markets on `market_id` whose condition its
+// resolution didn't meet, recording which resolution did it so unresolving
+// the parent reopens them
+pub fn resolve_conditional_markets(
+    market_id: i32,
+    resolution_id: i32,
+    resolution_probs: Option<&[BigDecimal]>,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let children = markets::table
+        .filter(markets::parent_market_id.eq(market_id))
+        .order(markets::id.asc())
+        .load::<Market>(conn)
+        .map_err(|e| format!("Error fetching conditional markets: {}", e))?;
+    for child in children {
+        let Some((_, parent_outcome)) = condition(&child) else {
+            continue;
+        };
+        if resolved_to(resolution_probs, parent_outcome) {
+            continue;
+        }
+        if child.is_resolved {
+            if resolution_kind(&child) == Some(ResolutionKind::NotApplicable) {
+                continue;
+            }
+            // resolved while an earlier resolution of the parent met the condition
+            reverse_resolution(child.id, conn)?;
+        }
+        apply_resolution(child.id, None, conn)
+            .map_err(|e| format!("Error resolving conditional market {}: {}", child.id, e))?;
+        diesel::update(
+            resolutions::table
+                .filter(resolutions::market_id.eq(child.id))
+                .filter(resolutions::unresolved_at.is_null())
+        )
+            .set(resolutions::parent_resolution_id.eq(resolution_id))
+            .execute(conn)
+            .map_err(|e| format!("Error recording conditional resolution: {}", e))?;
+    }
+    Ok(())
+}
+
+// unresolves the conditional markets a resolution resolved N/A
+pub fn reopen_conditional_markets(
+    resolution_id: i32,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let children = resolutions::table
+        .filter(resolutions::parent_resolution_id.eq(resolution_id))
+        .filter(resolutions::unresolved_at.is_null())
+        .select(resolutions::market_id)
+        .load::<i32>(conn)
+        .map_err(|e| format!("Error fetching conditional resolutions: {}", e))?;
+    for child_id in children {
+        reverse_resolution(child_id, conn)
+            .map_err(|e| format!("Error reopening conditional market {}: {}", child_id, e))?;
+    }
+    Ok(())
+}
\ No newline at end of file
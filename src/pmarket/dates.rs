@@ -0,0 +1,146 @@
+use std::str::FromStr;
+use bigdecimal::ToPrimitive;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::lmsr::prob;
+
+// the most buckets a date market is split into, not counting the last,
+// open-ended one
+pub const MAX_BUCKETS: usize = 24;
+
+// the percentiles reported for date markets, next to the median
+pub const DATE_PERCENTILES: [f64; 3] = [0.1, 0.5, 0.9];
+
+#[derive(Clone, Copy)]
+pub enum BucketSize {
+    Week,
+    Month,
+}
+
+impl BucketSize {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BucketSize::Week => "week",
+            BucketSize::Month => "month",
+        }
+    }
+
+    fn next(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            BucketSize::Week => date + Days::new(7),
+            BucketSize::Month => date + Months::new(1),
+        }
+    }
+}
+
+impl FromStr for BucketSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "week" => Ok(BucketSize::Week),
+            "month" => Ok(BucketSize::Month),
+            _ => Err(format!("Unknown bucket size: {}", s)),
+        }
+    }
+}
+
+// splits the days from `start` up to `end` into buckets of `size`, the last
+// one cut short at `end`, and adds an open-ended bucket for `end` and later
+pub fn date_buckets(
+    start: NaiveDate,
+    end: NaiveDate,
+    size: BucketSize,
+) -> Result<Vec<(NaiveDate, Option<NaiveDate>)>, String> {
+    if start >= end {
+        return Err("The first date must be before the last one".to_string());
+    }
+    let mut buckets = Vec::new();
+    let mut starts_on = start;
+    while starts_on < end {
+        if buckets.len() == MAX_BUCKETS {
+            return Err(format!(
+                "A date market can have at most {} buckets, use a shorter range or bigger buckets",
+                MAX_BUCKETS,
+            ));
+        }
+        let ends_on = size.next(starts_on).min(end);
+        buckets.push((starts_on, Some(ends_on)));
+        starts_on = ends_on;
+    }
+    buckets.push((end, None));
+    Ok(buckets)
+}
+
+pub fn bucket_label(
+    starts_on: NaiveDate,
+    ends_on: Option<NaiveDate>,
+) -> String {
+    let Some(ends_on) = ends_on else {
+        return format!("{} or later", starts_on.format("%b %-d, %Y"));
+    };
+    let last = ends_on - Days::new(1);
+    if starts_on.day() == 1 && ends_on.day() == 1 && starts_on + Months::new(1) == ends_on {
+        starts_on.format("%B %Y").to_string()
+    } else if starts_on == last {
+        starts_on.format("%b %-d, %Y").to_string()
+    } else if starts_on.year() == last.year() {
+        format!("{} – {}", starts_on.format("%b %-d"), last.format("%b %-d, %Y"))
+    } else {
+        format!("{} – {}", starts_on.format("%b %-d, %Y"), last.format("%b %-d, %Y"))
+    }
+}
+
+pub fn get_market_outcomes(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Vec<MarketOutcome>, String> {
+    market_outcomes::table
+        .filter(market_outcomes::market_id.eq(market_id))
+        .order(market_outcomes::share_index.asc())
+        .load::<MarketOutcome>(conn)
+        .map_err(|e| format!("Error fetching market outcomes: {}", e))
+}
+
+pub fn is_date_market(
+    outcomes: &[MarketOutcome],
+) -> bool {
+    outcomes.iter().any(|outcome| outcome.starts_on.is_some())
+}
+
+// the date by which the market thinks it's `q` likely to have happened,
+// spreading each bucket's probability evenly over its days; None if that's
+// in the open-ended last bucket
+pub fn date_percentile(
+    market: &Market,
+    outcomes: &[MarketOutcome],
+    q: f64,
+) -> Option<NaiveDate> {
+    let probs = prob(market);
+    let mut below = 0.0;
+    for outcome in outcomes {
+        let p = probs.get(usize::try_from(outcome.share_index).ok()?)?.to_f64()?;
+        let starts_on = outcome.starts_on?;
+        let ends_on = outcome.ends_on?;
+        if below + p >= q && p > 0.0 {
+            let days = (ends_on - starts_on).num_days() as f64;
+            let offset = ((q - below) / p * days).floor() as u64;
+            return Some(starts_on + Days::new(offset.min(days as u64 - 1)));
+        }
+        below += p;
+    }
+    None
+}
+
+// the outcome a date falls in; dates before the first bucket count towards it
+pub fn date_resolution(
+    outcomes: &[MarketOutcome],
+    date: NaiveDate,
+) -> Option<i32> {
+    outcomes.iter()
+        .filter(|outcome| outcome.starts_on.is_some())
+        .find(|outcome| outcome.ends_on.is_none_or(|ends_on| date < ends_on))
+        .map(|outcome| outcome.share_index)
+}
\ No newline at end of file
@@ -0,0 +1,378 @@
+use bigdecimal::BigDecimal;
+use chrono::{Duration, NaiveDate};
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::dates::{date_resolution, get_market_outcomes};
+use crate::pmarket::methods::{
+    ResolutionKind, apply_date_resolution, apply_prob_resolution, apply_resolution,
+    apply_value_resolution, probability_resolution, resolution_kind, reverse_resolution,
+};
+use crate::pmarket::roles::is_market_moderator;
+use crate::pmarket::scalar::value_resolution;
+
+// how long after a resolution traders can still dispute it
+const DISPUTE_WINDOW_HOURS: i64 = 72;
+// how long a dispute stays open for votes
+const VOTING_PERIOD_HOURS: i64 = 48;
+// the votes in favor, the opener's included, a dispute needs to overturn a
+// resolution, so nobody overturns one alone when nobody else votes
+const MIN_VOTES_IN_FAVOR: usize = 2;
+
+fn has_traded(
+    market_id: i32,
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<bool, String> {
+    diesel::select(diesel::dsl::exists(
+        trades::table
+            .filter(trades::market_id.eq(market_id))
+            .filter(trades::user_id.eq(user_id))
+    ))
+        .get_result::<bool>(conn)
+        .map_err(|e| format!("Error fetching trades: {}", e))
+}
+
+// what a dispute proposes its market resolves to instead, any way a market
+// can be resolved
+#[derive(Clone, PartialEq, Debug)]
+pub enum Proposal {
+    NotApplicable,
+    Outcome(i32),
+    Probabilities(Vec<BigDecimal>),
+    Value(BigDecimal),
+    Date(NaiveDate),
+}
+
+impl Proposal {
+    fn of(dispute: &Dispute) -> Proposal {
+        if let Some(probs) = &dispute.proposed_probs {
+            Proposal::Probabilities(probs.iter().map(|p| p.clone().unwrap()).collect())
+        } else if let Some(value) = &dispute.proposed_value {
+            Proposal::Value(value.clone())
+        } else if let Some(date) = dispute.proposed_date {
+            Proposal::Date(date)
+        } else if let Some(idx) = dispute.proposed_resolution {
+            Proposal::Outcome(idx)
+        } else {
+            Proposal::NotApplicable
+        }
+    }
+}
+
+// checks `proposal` could resolve `market` and puts it the way resolving
+// would store it, so it compares with the current resolution
+fn normalize_proposal(
+    market: &Market,
+    proposal: Proposal,
+    conn: &mut PgConnection,
+) -> Result<Proposal, String> {
+    match proposal {
+        Proposal::Outcome(idx) if idx < 0 || idx as usize >= market.bought_shares.len() => {
+            Err("Invalid proposed resolution".to_string())
+        }
+        Proposal::Probabilities(probs) => probability_resolution(market, &probs)
+            .map(Proposal::Probabilities),
+        Proposal::Value(value) => value_resolution(market, &value)
+            .map(|(value, _)| Proposal::Value(value))
+            .ok_or_else(|| "Only scalar markets resolve to a value".to_string()),
+        Proposal::Date(date) => {
+            let outcomes = get_market_outcomes(market.id, conn)?;
+            date_resolution(&outcomes, date)
+                .map(|_| Proposal::Date(date))
+                .ok_or_else(|| "Only date markets resolve to a date".to_string())
+        }
+        proposal => Ok(proposal),
+    }
+}
+
+fn is_current_resolution(
+    market: &Market,
+    proposal: &Proposal,
+) -> bool {
+    let kind = resolution_kind(market);
+    match proposal {
+        Proposal::NotApplicable => kind == Some(ResolutionKind::NotApplicable),
+        Proposal::Outcome(idx) => {
+            kind == Some(ResolutionKind::Outcome) && market.resolution == Some(*idx)
+        }
+        Proposal::Probabilities(probs) => {
+            kind == Some(ResolutionKind::Probabilities)
+                && market.resolution_value.is_none()
+                && market.resolution_probs.as_ref().is_some_and(|current| current.iter()
+                    .map(|p| p.clone().unwrap())
+                    .eq(probs.iter().cloned()))
+        }
+        Proposal::Value(value) => market.resolution_value.as_ref() == Some(value),
+        Proposal::Date(date) => market.resolution_date == Some(*date),
+    }
+}
+
+// resolves a market the way the dispute proposes, down the same path as
+// resolving it by hand
+fn apply_proposal(
+    market_id: i32,
+    proposal: &Proposal,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    match proposal {
+        Proposal::NotApplicable => apply_resolution(market_id, None, conn),
+        Proposal::Outcome(idx) => apply_resolution(market_id, Some(*idx), conn),
+        Proposal::Probabilities(probs) => apply_prob_resolution(market_id, probs, conn),
+        Proposal::Value(value) => apply_value_resolution(market_id, value, conn),
+        Proposal::Date(date) => apply_date_resolution(market_id, *date, conn),
+    }
+}
+
+pub fn open_dispute(
+    market_id: i32,
+    user_id: &str,
+    proposal: Proposal,
+    reason: &str,
+    conn: &mut PgConnection,
+) -> Result<i32, String> {
+    let market = markets::table
+        .filter(markets::id.eq(market_id))
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?;
+    if !market.is_resolved {
+        return Err("Market is not resolved".to_string());
+    }
+    let proposal = normalize_proposal(&market, proposal, conn)?;
+    if !has_traded(market_id, user_id, conn)? {
+        return Err("Only traders on this market can dispute its resolution".to_string());
+    }
+
+    let resolution = resolutions::table
+        .filter(resolutions::market_id.eq(market_id))
+        .filter(resolutions::unresolved_at.is_null())
+        .order(resolutions::id.desc())
+        .first::<Resolution>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching resolution: {}", e))?
+        .ok_or_else(|| "Market was resolved without recorded payouts".to_string())?;
+    let now = chrono::Utc::now().naive_utc();
+    if now > resolution.created_at + Duration::hours(DISPUTE_WINDOW_HOURS) {
+        return Err("The dispute window for this resolution has closed".to_string());
+    }
+    if is_current_resolution(&market, &proposal) {
+        return Err("Proposed resolution is the current resolution".to_string());
+    }
+
+    let open_dispute = disputes::table
+        .filter(disputes::market_id.eq(market_id))
+        .filter(disputes::closed_at.is_null())
+        .select(disputes::id)
+        .first::<i32>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching disputes: {}", e))?;
+    if open_dispute.is_some() {
+        return Err("Market already has an open dispute".to_string());
+    }
+
+    let new_dispute = NewDispute {
+        market_id,
+        resolution_id: resolution.id,
+        opened_by: user_id.to_string(),
+        proposed_resolution: match proposal {
+            Proposal::Outcome(idx) => Some(idx),
+            _ => None,
+        },
+        proposed_probs: match &proposal {
+            Proposal::Probabilities(probs) => Some(probs.clone()),
+            _ => None,
+        },
+        proposed_value: match &proposal {
+            Proposal::Value(value) => Some(value.clone()),
+            _ => None,
+        },
+        proposed_date: match proposal {
+            Proposal::Date(date) => Some(date),
+            _ => None,
+        },
+        reason: reason.to_string(),
+        closes_at: now + Duration::hours(VOTING_PERIOD_HOURS),
+    };
+
+    let mut err = None;
+    let mut id = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        let dispute_id = diesel::insert_into(disputes::table)
+            .values(&new_dispute)
+            .returning(disputes::id)
+            .get_result::<i32>(conn)
+            .map_err(|e| {
+                err = Some(format!("Error creating dispute: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        id = Some(dispute_id);
+
+        // whoever opens a dispute votes for it
+        diesel::insert_into(dispute_votes::table)
+            .values(NewDisputeVote {
+                dispute_id,
+                user_id: user_id.to_string(),
+                in_favor: true,
+            })
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error recording dispute vote: {}", e));
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(id.unwrap())
+}
+
+pub fn vote_on_dispute(
+    dispute_id: i32,
+    user_id: &str,
+    in_favor: bool,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let dispute = disputes::table
+        .filter(disputes::id.eq(dispute_id))
+        .first::<Dispute>(conn)
+        .map_err(|e| format!("Error fetching dispute: {}", e))?;
+    if dispute.closed_at.is_some() || chrono::Utc::now().naive_utc() > dispute.closes_at {
+        return Err("Dispute is closed for voting".to_string());
+    }
+    let owner_id = markets::table
+        .filter(markets::id.eq(dispute.market_id))
+        .select(markets::owner_id)
+        .first::<String>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?;
+    if owner_id == user_id {
+        return Err("The market owner can't vote on disputes of their resolution".to_string());
+    }
+    if !has_traded(dispute.market_id, user_id, conn)?
+        && !is_market_moderator(user_id, dispute.market_id, conn)?
+    {
+        return Err("Only traders and moderators can vote on this dispute".to_string());
+    }
+
+    diesel::insert_into(dispute_votes::table)
+        .values(NewDisputeVote {
+            dispute_id,
+            user_id: user_id.to_string(),
+            in_favor,
+        })
+        .on_conflict((dispute_votes::dispute_id, dispute_votes::user_id))
+        .do_update()
+        .set(dispute_votes::in_favor.eq(in_favor))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| format!("Error recording dispute vote: {}", e))
+}
+
+pub fn close_dispute(
+    dispute_id: i32,
+    conn: &mut PgConnection,
+) -> Result<bool, String> {
+    let dispute = disputes::table
+        .filter(disputes::id.eq(dispute_id))
+        .first::<Dispute>(conn)
+        .map_err(|e| format!("Error fetching dispute: {}", e))?;
+    if dispute.closed_at.is_some() {
+        return Err("Dispute is already closed".to_string());
+    }
+    let now = chrono::Utc::now().naive_utc();
+    if now < dispute.closes_at {
+        return Err("Dispute is still open for voting".to_string());
+    }
+
+    let votes = dispute_votes::table
+        .filter(dispute_votes::dispute_id.eq(dispute_id))
+        .select(dispute_votes::in_favor)
+        .load::<bool>(conn)
+        .map_err(|e| format!("Error fetching dispute votes: {}", e))?;
+    let votes_for = votes.iter().filter(|v| **v).count();
+    let votes_against = votes.len() - votes_for;
+
+    // a resolution that was already reversed by hand leaves nothing to overturn
+    let unresolved_at = resolutions::table
+        .filter(resolutions::id.eq(dispute.resolution_id))
+        .select(resolutions::unresolved_at)
+        .first::<Option<chrono::NaiveDateTime>>(conn)
+        .map_err(|e| format!("Error fetching resolution: {}", e))?;
+    let overturned = unresolved_at.is_none()
+        && votes_for >= MIN_VOTES_IN_FAVOR
+        && votes_for > votes_against;
+
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        if overturned {
+            reverse_resolution(dispute.market_id, conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })?;
+            apply_proposal(dispute.market_id, &Proposal::of(&dispute), conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })?;
+        }
+
+        diesel::update(disputes::table.filter(disputes::id.eq(dispute_id)))
+            .set((
+                disputes::closed_at.eq(now),
+                disputes::overturned.eq(overturned),
+            ))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error closing dispute: {}", e));
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(overturned)
+}
+
+pub fn close_expired_disputes(
+    conn: &mut PgConnection,
+) -> Result<Vec<i32>, String> {
+    let now = chrono::Utc::now().naive_utc();
+    let expired = disputes::table
+        .filter(disputes::closed_at.is_null())
+        .filter(disputes::closes_at.le(now))
+        .select((disputes::id, disputes::market_id))
+        .load::<(i32, i32)>(conn)
+        .map_err(|e| format!("Error fetching disputes: {}", e))?;
+
+    let mut overturned_markets = Vec::new();
+    for (dispute_id, market_id) in expired {
+        match close_dispute(dispute_id, conn) {
+            Ok(true) => overturned_markets.push(market_id),
+            Ok(false) => {}
+            // e.g. a winner already spent the payout reversing would take back;
+            // left open, the dispute would fail every later run before the
+            // disputes after it, so it's closed with the error instead
+            Err(e) => close_with_error(dispute_id, &e, conn)?,
+        }
+    }
+    Ok(overturned_markets)
+}
+
+fn close_with_error(
+    dispute_id: i32,
+    error: &str,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    diesel::update(disputes::table.filter(disputes::id.eq(dispute_id)))
+        .set((
+            disputes::closed_at.eq(chrono::Utc::now().naive_utc()),
+            disputes::overturned.eq(false),
+            disputes::close_error.eq(error),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| format!("Error closing dispute: {}", e))
+}
\ No newline at end of file
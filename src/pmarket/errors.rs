@@ -0,0 +1,52 @@
+use std::fmt;
+
+// what kind of failure an error is, for callers like the API that answer each
+// kind differently; most are the request's own fault
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    Invalid,
+    Forbidden,
+    NotFound,
+    // the request clashes with an earlier one
+    Conflict,
+}
+
+// the failures of calls that can be forbidden, miss what they act on or clash
+// with an earlier request; anything else comes as a message and is Invalid
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl Error {
+    pub fn forbidden(message: impl Into<String>) -> Error {
+        Error { kind: ErrorKind::Forbidden, message: message.into() }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Error {
+        Error { kind: ErrorKind::NotFound, message: message.into() }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Error {
+        Error { kind: ErrorKind::Conflict, message: message.into() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error { kind: ErrorKind::Invalid, message }
+    }
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> String {
+        e.message
+    }
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+use std::thread;
+use std::time::Duration;
+use diesel::prelude::*;
+use serde_json::{Value, json};
+
+// the channel the triggers on trades and markets notify
+pub const EVENTS_CHANNEL: &str = "market_events";
+
+#[derive(Clone, Debug)]
+pub struct MarketEvent {
+    // trade, prob_change, market_created, market_resolved or market_unresolved
+    pub event: String,
+    pub market_id: i32,
+    pub data: Value,
+}
+
+pub fn listen(
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    diesel::sql_query(format!("LISTEN {}", EVENTS_CHANNEL))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| format!("Error listening for market events: {}", e))
+}
+
+fn parse_event(
+    payload: &str,
+) -> Result<Vec<MarketEvent>, String> {
+    let data = serde_json::from_str::<Value>(payload)
+        .map_err(|e| format!("Invalid market event {}: {}", payload, e))?;
+    let event = data["event"].as_str()
+        .ok_or_else(|| format!("Market event without a type: {}", payload))?
+        .to_string();
+    let market_id = data["market_id"].as_i64()
+        .ok_or_else(|| format!("Market event without a market: {}", payload))? as i32;
+
+    // every trade moves the price, so subscribers that only chart
+    // probabilities don't need to follow trades
+    let mut events = Vec::new();
+    if event == "trade" {
+        events.push(MarketEvent {
+            event: "prob_change".to_string(),
+            market_id,
+            data: json!({
+                "event": "prob_change",
+                "market_id": market_id,
+                "prob": data["probs_after"],
+                "created_at": data["created_at"],
+            }),
+        });
+    }
+    events.insert(0, MarketEvent { event, market_id, data });
+    Ok(events)
+}
+
+// events that arrived since the last poll, without waiting for more
+pub fn poll_events(
+    conn: &mut PgConnection,
+) -> Result<Vec<MarketEvent>, String> {
+    let mut events = Vec::new();
+    for notification in conn.notifications_iter() {
+        let notification = notification
+            .map_err(|e| format!("Error receiving market events: {}", e))?;
+        if notification.channel != EVENTS_CHANNEL {
+            continue;
+        }
+        match parse_event(&notification.payload) {
+            Ok(parsed) => events.extend(parsed),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    Ok(events)
+}
+
+// hands every event to `on_event` until it returns false, reconnecting
+// whenever the connection drops; events sent while reconnecting are lost
+pub fn run_listener(
+    poll_interval: Duration,
+    mut on_event: impl FnMut(MarketEvent) -> bool,
+) {
+    loop {
+        let mut conn = crate::db::establish_connection_with_retry();
+        if let Err(e) = listen(&mut conn) {
+            eprintln!("{}", e);
+            thread::sleep(poll_interval);
+            continue;
+        }
+        loop {
+            match poll_events(&mut conn) {
+                Ok(events) => {
+                    for event in events {
+                        if !on_event(event) {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}, reconnecting", e.trim_end());
+                    break;
+                }
+            }
+            thread::sleep(poll_interval);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::errors::Error;
+
+fn hash_request(request: &Value) -> String {
+    hex::encode(Sha256::digest(request.to_string().as_bytes()))
+}
+
+fn stored_result<T: DeserializeOwned>(
+    user_id: &str,
+    key: &str,
+    action: &str,
+    request_hash: &str,
+    conn: &mut PgConnection,
+) -> Result<Option<T>, Error> {
+    let stored = idempotency_keys::table
+        .filter(idempotency_keys::user_id.eq(user_id))
+        .filter(idempotency_keys::key.eq(key))
+        .first::<IdempotencyKey>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching idempotency key: {}", e))?;
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    if stored.action != action {
+        return Err(Error::conflict(format!("Idempotency key {} was already used to {}", key, stored.action)));
+    }
+    if stored.request_hash != request_hash {
+        return Err(Error::conflict(format!("Idempotency key {} was already used for a different request", key)));
+    }
+    serde_json::from_str(&stored.result)
+        .map(Some)
+        .map_err(|e| format!("Invalid result stored for idempotency key {}: {}", key, e).into())
+}
+
+// runs `f` once per key of `user_id`: the key is stored with the result in the
+// same transaction as whatever `f` does, so a repeated call, even one running
+// at the same time, gets the original result back instead of acting again.
+// `request` is what the call was asked to do, which a repeat has to match
+pub fn with_idempotency_key<T, E>(
+    key: Option<&str>,
+    user_id: &str,
+    action: &str,
+    request: &Value,
+    conn: &mut PgConnection,
+    f: impl FnOnce(&mut PgConnection) -> Result<T, E>,
+) -> Result<T, Error>
+where
+    T: Serialize + DeserializeOwned,
+    E: Into<Error>,
+{
+    let Some(key) = key else {
+        return f(conn).map_err(Into::into);
+    };
+    let request_hash = hash_request(request);
+    if let Some(result) = stored_result(user_id, key, action, &request_hash, conn)? {
+        return Ok(result);
+    }
+
+    let mut err = None;
+    let mut result = None;
+    let mut duplicate = false;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        let value = f(conn)
+            .map_err(|e| {
+                err = Some(e.into());
+                DieselError::RollbackTransaction
+            })?;
+        let serialized = serde_json::to_string(&value)
+            .map_err(|e| {
+                err = Some(format!("Error serializing result: {}", e).into());
+                DieselError::RollbackTransaction
+            })?;
+        // waits on the primary key for a call with the same key that is still
+        // running, and fails once that one commits
+        diesel::insert_into(idempotency_keys::table)
+            .values(&NewIdempotencyKey {
+                user_id: user_id.to_string(),
+                key: key.to_string(),
+                action: action.to_string(),
+                request_hash: request_hash.clone(),
+                result: serialized,
+            })
+            .execute(conn)
+            .map_err(|e| {
+                duplicate = matches!(e, DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _));
+                err = Some(format!("Error storing idempotency key: {}", e).into());
+                DieselError::RollbackTransaction
+            })?;
+        result = Some(value);
+        Ok(())
+    });
+    if let Err(e) = transaction {
+        if duplicate && let Some(result) = stored_result(user_id, key, action, &request_hash, conn)? {
+            return Ok(result);
+        }
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e).into()));
+    }
+    Ok(result.unwrap())
+}
\ No newline at end of file
@@ -1,8 +1,8 @@
-use bigdecimal::{BigDecimal, ToPrimitive, FromPrimitive};
+use bigdecimal::{BigDecimal, ToPrimitive, FromPrimitive, RoundingMode};
 use crate::models::*;
 
 fn logsumexp(
-    inp: &Vec<f64>
+    inp: &[f64]
 ) -> f64 {
     let max = inp.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
     let sum = inp.iter().map(|&x| (x - max).exp()).sum::<f64>();
@@ -11,7 +11,7 @@ fn logsumexp(
 
 pub fn cost_function_algo(
     liquidity: f64,
-    shares: &Vec<f64>
+    shares: &[f64]
 ) -> f64 {
     let shares_scaled = shares.iter().map(|s| s / liquidity).collect::<Vec<f64>>();
     let lse = logsumexp(&shares_scaled);
@@ -20,7 +20,7 @@ pub fn cost_function_algo(
 
 pub fn prob_algo(
     liquidity: f64,
-    shares: &Vec<f64>,
+    shares: &[f64],
 ) -> Vec<f64> {
     let shares_scaled = shares.iter().map(|s| s / liquidity).collect::<Vec<f64>>();
     let lse = logsumexp(&shares_scaled);
@@ -29,7 +29,7 @@ pub fn prob_algo(
 
 fn schange_to_bchange_algo(
     liquidity: f64,
-    shares: &Vec<f64>,
+    shares: &[f64],
     share_change: f64,
     share_index: usize
 ) -> f64 {
@@ -85,4 +85,129 @@ pub fn schange_to_bchange(
         .collect::<Vec<f64>>();
     let share_change_f64 = share_change.to_f64().unwrap();
     BigDecimal::from_f64(schange_to_bchange_algo(liquidity, &shares, share_change_f64, share_index.try_into().unwrap())).unwrap()
+}
+
+// the liquidity a market with `outcome_count` outcomes can have on `subsidy`:
+// before any trade the cost function is b * ln(n), which the subsidy has to
+// cover, so past two outcomes b shrinks below the subsidy
+pub fn liquidity_for_subsidy(
+    subsidy: &BigDecimal,
+    outcome_count: usize,
+) -> BigDecimal {
+    if outcome_count <= 2 {
+        return subsidy.clone();
+    }
+    let liquidity = subsidy.to_f64().unwrap() / (outcome_count as f64).ln();
+    BigDecimal::from_f64(liquidity).unwrap()
+        .with_scale_round(4, RoundingMode::Down)
+}
+
+// adds an outcome that takes half of outcome `share_index`'s probability and
+// leaves every other outcome where it was: the new outcome gets the same shares
+// as the one it splits, and all the others b * ln(2) more. Returns the market
+// with the outcome added and how much that raised the cost function, which has
+// to be paid into the market to keep it solvent
+pub fn split_outcome(
+    market: &Market,
+    share_index: i32,
+) -> (Market, BigDecimal) {
+    let idx: usize = share_index.try_into().unwrap();
+    let shift = BigDecimal::from_f64(market.liquidity.to_f64().unwrap() * 2f64.ln()).unwrap()
+        .with_scale_round(4, RoundingMode::Up);
+    let mut split = market.clone();
+    split.bought_shares = market.bought_shares.iter()
+        .enumerate()
+        .map(|(i, s)| if i == idx {
+            s.clone()
+        } else {
+            s.as_ref().map(|v| v + &shift)
+        })
+        .collect();
+    split.bought_shares.push(market.bought_shares[idx].clone());
+    let cost = (cost_function(&split) - cost_function(market))
+        .with_scale_round(4, RoundingMode::Up);
+    (split, cost)
+}
+
+pub fn rescale_liquidity(
+    market: &Market,
+    factor: &BigDecimal
+) -> Market {
+    // scaling liquidity and shares together leaves prob() unchanged;
+    // shares round up so they never fall below the shares traders hold
+    let mut rescaled = market.clone();
+    rescaled.liquidity = (&market.liquidity * factor)
+        .with_scale_round(4, RoundingMode::Down);
+    rescaled.bought_shares = market.bought_shares.iter()
+        .map(|s| s.as_ref().map(
+            |v| (v * factor).with_scale_round(4, RoundingMode::Up)
+        ))
+        .collect();
+    rescaled
+}
+
+// with p the current probability of an outcome, buying it up to `target` costs
+// b * ln((1 - p) / (1 - target)), and buying the same amount of every other
+// outcome to bring it down to `target` costs b * ln(p / target)
+fn cost_to_move_algo(
+    liquidity: f64,
+    shares: &[f64],
+    share_index: usize,
+    target: f64,
+) -> f64 {
+    let p = prob_algo(liquidity, shares)[share_index];
+    if target > p {
+        liquidity * ((1.0 - p) / (1.0 - target)).ln()
+    } else {
+        liquidity * (p / target).ln()
+    }
+}
+
+pub fn cost_to_move(
+    market: &Market,
+    share_index: i32,
+    target: f64,
+) -> BigDecimal {
+    let liquidity = market.liquidity.to_f64().unwrap();
+    let shares = market.bought_shares.iter()
+        .map(|s| s.clone().map_or(
+            0.0, 
+            |v| v.to_f64().unwrap()
+        ))
+        .collect::<Vec<f64>>();
+    let cost = cost_to_move_algo(liquidity, &shares, share_index.try_into().unwrap(), target);
+    BigDecimal::from_f64(cost).unwrap()
+        .with_scale_round(4, RoundingMode::Up)
+}
+
+// solving b * ln(1 - p + p * e^(s / b)), what buying s shares of an outcome
+// with probability p costs, for s; None when asking for more than selling
+// any number of shares would ever pay
+fn bchange_to_schange_algo(
+    liquidity: f64,
+    shares: &[f64],
+    balance_change: f64,
+    share_index: usize,
+) -> Option<f64> {
+    let p = prob_algo(liquidity, shares)[share_index];
+    let scaled = ((-balance_change / liquidity).exp() - 1.0 + p) / p;
+    (scaled > 0.0).then(|| liquidity * scaled.ln())
+}
+
+pub fn bchange_to_schange(
+    market: &Market,
+    balance_change: &BigDecimal,
+    share_index: i32,
+) -> Option<BigDecimal> {
+    let liquidity = market.liquidity.to_f64().unwrap();
+    let shares = market.bought_shares.iter()
+        .map(|s| s.clone().map_or(
+            0.0, 
+            |v| v.to_f64().unwrap()
+        ))
+        .collect::<Vec<f64>>();
+    let share_change = bchange_to_schange_algo(liquidity, &shares, balance_change.to_f64().unwrap(), share_index.try_into().unwrap())?;
+    // rounding towards zero never costs more, or pays out more, than asked for
+    BigDecimal::from_f64(share_change)
+        .map(|s| s.with_scale_round(4, RoundingMode::Down))
 }
\ No newline at end of file
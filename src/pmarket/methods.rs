@@ -1,16 +1,36 @@
 use std::collections::HashMap;
-use std::sync::LazyLock;
-use bigdecimal::FromPrimitive;
-use bigdecimal::Zero;
-use chrono::NaiveDateTime;
+use std::collections::hash_map::Entry;
+use std::str::FromStr;
+use bigdecimal::{One, Zero};
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
-use bigdecimal::BigDecimal;
+use serde_json::{Value, json};
+use bigdecimal::{BigDecimal, RoundingMode};
 use crate::models::*;
 use crate::schema::*;
-use crate::pmarket::lmsr::schange_to_bchange;
+use crate::pmarket::answers::{OTHER_LABEL, answer_labels, find_answer, is_free_response};
+use crate::pmarket::conditional::{check_condition_met, check_parent, reopen_conditional_markets, resolve_conditional_markets};
+use crate::pmarket::errors::Error;
+use crate::pmarket::dates::{BucketSize, bucket_label, date_buckets, date_resolution, get_market_outcomes};
+use crate::pmarket::idempotency::with_idempotency_key;
+use crate::pmarket::lmsr::{cost_function, liquidity_for_subsidy, prob, rescale_liquidity};
+use crate::pmarket::quotes::{TradeQuote, TradeSize, quote_trade};
+use crate::pmarket::roles::{require_admin, require_market_manager};
+use crate::pmarket::scalar::value_resolution;
+use crate::pmarket::settings::get_settings;
 
-static DEFAULT_BALANCE: LazyLock<BigDecimal> = LazyLock::new(|| BigDecimal::from_f64(1000.0).unwrap());
+pub fn find_market(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Market, Error> {
+    markets::table
+        .filter(markets::id.eq(market_id))
+        .first::<Market>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching market: {}", e))?
+        .ok_or_else(|| Error::not_found("Market not found"))
+}
 
 fn update_time(
     conn: &mut PgConnection,
@@ -67,7 +87,8 @@ pub fn create_user(
         .map(|_| ())
         .map_err(|e| format!("Error creating new user: {}", e))?;
 
-    change_balance(id, &DEFAULT_BALANCE, conn)
+    let starting_balance = get_settings(conn)?.starting_balance;
+    grant_balance(id, &starting_balance, "signup", conn)
 }
 
 pub fn try_create_user(
@@ -100,21 +121,319 @@ pub fn change_balance(
         .map_err(|e| format!("Error updating user balance: {}", e))
 }
 
+pub fn grant_balance(
+    user_id: &str,
+    amount: &BigDecimal,
+    reason: &str,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let new_grant = NewGrant {
+        user_id: user_id.to_string(),
+        amount: amount.clone(),
+        reason: reason.to_string(),
+    };
+
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        change_balance(user_id, amount, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        diesel::insert_into(grants::table)
+            .values(&new_grant)
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error recording grant: {}", e));
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(())
+}
+
+pub fn adjust_balance(
+    actor_id: &str,
+    user_id: &str,
+    amount: &BigDecimal,
+    reason: &str,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    require_admin(actor_id, conn)?;
+    grant_balance(user_id, amount, reason, conn)
+}
+
+// what creating a market asks for, which a retry with the same idempotency key
+// has to ask for again; `details` are what the kind of market adds
+fn market_request(
+    title: &str,
+    description: &str,
+    liquidity: &BigDecimal,
+    remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    details: Value,
+) -> Value {
+    json!({
+        "title": title,
+        "description": description,
+        "liquidity": liquidity.normalized().to_string(),
+        "remind_at": remind_at.to_string(),
+        "fee_rate": fee_rate.map(|rate| rate.normalized().to_string()),
+        "details": details,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_market(
     title: &str,
     description: &str,
     owner_id: &str,
     liquidity: &BigDecimal,
     remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<i32, Error> {
+    let request = market_request(title, description, liquidity, remind_at, fee_rate, Value::Null);
+    with_idempotency_key(idempotency_key, owner_id, "create_market", &request, conn, |conn| {
+        insert_market(title, description, owner_id, liquidity, remind_at, fee_rate, None, 2, conn)
+    })
+}
+
+// a market on a number between `scalar_min` and `scalar_max`, see scalar.rs
+#[allow(clippy::too_many_arguments)]
+pub fn create_scalar_market(
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: &BigDecimal,
+    remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    scalar_min: &BigDecimal,
+    scalar_max: &BigDecimal,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<i32, Error> {
+    if scalar_min >= scalar_max {
+        return Err("The range's minimum must be below its maximum".to_string().into());
+    }
+    let request = market_request(title, description, liquidity, remind_at, fee_rate, json!({
+        "scalar_min": scalar_min.normalized().to_string(),
+        "scalar_max": scalar_max.normalized().to_string(),
+    }));
+    with_idempotency_key(idempotency_key, owner_id, "create_market", &request, conn, |conn| {
+        insert_market(title, description, owner_id, liquidity, remind_at, fee_rate, Some((scalar_min, scalar_max)), 2, conn)
+    })
+}
+
+// a market on when something happens, with an outcome for each week or month
+// from `start` up to `end` and one for later, see dates.rs
+#[allow(clippy::too_many_arguments)]
+pub fn create_date_market(
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: &BigDecimal,
+    remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    start: NaiveDate,
+    end: NaiveDate,
+    bucket_size: BucketSize,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<i32, Error> {
+    let outcomes = date_buckets(start, end, bucket_size)?
+        .into_iter()
+        .map(|(starts_on, ends_on)| (bucket_label(starts_on, ends_on), Some(starts_on), ends_on))
+        .collect::<Vec<(String, Option<NaiveDate>, Option<NaiveDate>)>>();
+    let request = market_request(title, description, liquidity, remind_at, fee_rate, json!({
+        "start": start.to_string(),
+        "end": end.to_string(),
+        "bucket_size": bucket_size.as_str(),
+    }));
+    with_idempotency_key(idempotency_key, owner_id, "create_market", &request, conn, |conn| {
+        insert_market_with_outcomes(title, description, owner_id, liquidity, remind_at, fee_rate, &outcomes, conn)
+    })
+}
+
+// a market on an open question, with Other and `answers` as its outcomes, that
+// anyone can add answers to, see answers.rs
+#[allow(clippy::too_many_arguments)]
+pub fn create_free_response_market(
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: &BigDecimal,
+    remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    answers: &[String],
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<i32, Error> {
+    let outcomes = answer_labels(answers)?
+        .into_iter()
+        .map(|label| (label, None, None))
+        .collect::<Vec<(String, Option<NaiveDate>, Option<NaiveDate>)>>();
+    let request = market_request(title, description, liquidity, remind_at, fee_rate, json!({
+        "answers": answers,
+    }));
+    with_idempotency_key(idempotency_key, owner_id, "create_market", &request, conn, |conn| {
+        insert_market_with_outcomes(title, description, owner_id, liquidity, remind_at, fee_rate, &outcomes, conn)
+    })
+}
+
+// a YES/NO market that only counts if `parent_market_id` resolves to
+// `parent_outcome`, and is resolved N/A otherwise, see conditional.rs
+#[allow(clippy::too_many_arguments)]
+pub fn create_conditional_market(
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: &BigDecimal,
+    remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    parent_market_id: i32,
+    parent_outcome: i32,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<i32, Error> {
+    let request = market_request(title, description, liquidity, remind_at, fee_rate, json!({
+        "parent_market_id": parent_market_id,
+        "parent_outcome": parent_outcome,
+    }));
+    with_idempotency_key(idempotency_key, owner_id, "create_market", &request, conn, |conn| {
+        let mut err = None;
+        let mut market_id = None;
+        let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+            check_parent(parent_market_id, parent_outcome, conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })?;
+            let id = insert_market(title, description, owner_id, liquidity, remind_at, fee_rate, None, 2, conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })?;
+            diesel::update(markets::table.filter(markets::id.eq(id)))
+                .set((
+                    markets::parent_market_id.eq(parent_market_id),
+                    markets::parent_outcome.eq(parent_outcome),
+                ))
+                .execute(conn)
+                .map_err(|e| {
+                    err = Some(format!("Error linking market to its parent: {}", e));
+                    DieselError::RollbackTransaction
+                })?;
+            market_id = Some(id);
+            Ok(())
+        });
+        if let Err(e) = transaction {
+            return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+        }
+        Ok(market_id.unwrap())
+    })
+}
+
+// inserts a market together with the outcomes it names, each a label and the
+// days it covers, if any
+#[allow(clippy::too_many_arguments)]
+fn insert_market_with_outcomes(
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: &BigDecimal,
+    remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    outcomes: &[(String, Option<NaiveDate>, Option<NaiveDate>)],
     conn: &mut PgConnection,
 ) -> Result<i32, String> {
-    
+    let mut err = None;
+    let mut market_id = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        let id = insert_market(title, description, owner_id, liquidity, remind_at, fee_rate, None, outcomes.len(), conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        let outcomes = outcomes.iter()
+            .enumerate()
+            .map(|(idx, (label, starts_on, ends_on))| MarketOutcome {
+                market_id: id,
+                share_index: idx as i32,
+                label: label.clone(),
+                starts_on: *starts_on,
+                ends_on: *ends_on,
+            })
+            .collect::<Vec<MarketOutcome>>();
+        diesel::insert_into(market_outcomes::table)
+            .values(&outcomes)
+            .execute(conn)
+            .map_err(|e| {
+                err = Some(format!("Error creating market outcomes: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        market_id = Some(id);
+        Ok(())
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(market_id.unwrap())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn insert_market(
+    title: &str,
+    description: &str,
+    owner_id: &str,
+    liquidity: &BigDecimal,
+    remind_at: &NaiveDateTime,
+    fee_rate: Option<&BigDecimal>,
+    scalar_range: Option<(&BigDecimal, &BigDecimal)>,
+    outcome_count: usize,
+    conn: &mut PgConnection,
+) -> Result<i32, String> {
+    let settings = get_settings(conn)?;
+    let fee_rate = fee_rate.unwrap_or(&settings.default_fee_rate);
+    if *fee_rate < BigDecimal::zero() || *fee_rate > settings.max_fee_rate {
+        return Err(format!("Fee rate must be between 0 and {}", settings.max_fee_rate));
+    }
+    if *liquidity < settings.min_liquidity {
+        return Err(format!("Liquidity must be at least {}", settings.min_liquidity));
+    }
+    if let Some(max_liquidity) = &settings.max_liquidity
+        && liquidity > max_liquidity
+    {
+        return Err(format!("Liquidity can be at most {}", max_liquidity));
+    }
+    if let Some(max_open_markets) = settings.max_open_markets_per_user {
+        let open_markets = markets::table
+            .filter(markets::owner_id.eq(owner_id))
+            .filter(markets::is_resolved.eq(false))
+            .count()
+            .get_result::<i64>(conn)
+            .map_err(|e| format!("Error fetching markets: {}", e))?;
+        if open_markets >= max_open_markets as i64 {
+            return Err(format!("You already have the maximum of {} open markets", max_open_markets));
+        }
+    }
+
     let new_market = NewMarket {
         title: title.to_string(),
         description: description.to_string(),
         owner_id: owner_id.to_string(),
-        liquidity: liquidity.clone(),
-        remind_at: remind_at.clone(),
+        liquidity: liquidity_for_subsidy(liquidity, outcome_count),
+        remind_at: *remind_at,
+        subsidy: liquidity.clone(),
+        fee_rate: fee_rate.clone(),
+        fee_recipient: settings.house_account.clone().unwrap_or_else(|| owner_id.to_string()),
+        scalar_min: scalar_range.map(|(min, _)| min.clone()),
+        scalar_max: scalar_range.map(|(_, max)| max.clone()),
+        bought_shares: vec![BigDecimal::zero(); outcome_count],
     };
     
     let mut err = None;
@@ -146,6 +465,27 @@ pub fn create_market(
     Ok(id.unwrap())
 }
 
+pub fn edit_market(
+    market_id: i32,
+    actor_id: &str,
+    title: &str,
+    description: &str,
+    remind_at: &NaiveDateTime,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    require_market_manager(actor_id, market_id, conn)?;
+
+    diesel::update(markets::table.filter(markets::id.eq(market_id)))
+        .set((
+            markets::title.eq(title),
+            markets::description.eq(description),
+            markets::remind_at.eq(remind_at),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| format!("Error editing market: {}", e))
+}
+
 pub fn create_market_slack_msg(
     market_id: i32,
     channel_id: &str,
@@ -167,29 +507,44 @@ pub fn create_market_slack_msg(
         .map_err(|e| format!("Error creating market slack message: {}", e))
 }
 
+pub fn trade_fee(
+    market: &Market,
+    balance_change: &BigDecimal,
+) -> BigDecimal {
+    // charged on what the trade costs or pays out, in the market's favor
+    (balance_change.abs() * &market.fee_rate).with_scale_round(4, RoundingMode::Up)
+}
+
+// None for trades on markets that are already resolved
+fn quote_valid_trade(
+    market_id: i32,
+    user_id: &str,
+    shares_amount: &BigDecimal,
+    share_index: i32,
+    conn: &mut PgConnection,
+) -> Result<Option<TradeQuote>, Error> {
+    let min_trade_size = get_settings(conn)?.min_trade_size;
+    if shares_amount.abs() < min_trade_size {
+        return Err(format!("Trades must be at least {} shares", min_trade_size).into());
+    }
+    if find_market(market_id, conn)?.is_resolved {
+        return Ok(None);
+    }
+    quote_trade(market_id, user_id, share_index, &TradeSize::Shares(shares_amount.clone()), conn)
+        .map(Some)
+}
+
 pub fn check_valid_trade(
     market_id: i32,
     user_id: &str,
     shares_amount: &BigDecimal,
     share_index: i32,
     conn: &mut PgConnection,
-) -> Result<(bool, BigDecimal), String> {
-    let balance = users::table
-        .filter(users::id.eq(user_id))
-        .select(users::balance)
-        .first::<BigDecimal>(conn)
-        .map_err(|e| format!("Error fetching user balance: {}", e))?;
-    let market = markets::table
-        .filter(markets::id.eq(market_id))
-        .first::<Market>(conn)
-        .map_err(|e| format!("Error fetching market: {}", e))?;
-    let balance_change = schange_to_bchange(&market, shares_amount.clone(), share_index);
-    let new_balance = balance + &balance_change;
-    let is_resolved = market.is_resolved;
-    if is_resolved {
-        return Ok((false, BigDecimal::zero()));
+) -> Result<(bool, BigDecimal, BigDecimal), Error> {
+    match quote_valid_trade(market_id, user_id, shares_amount, share_index, conn)? {
+        Some(quote) => Ok((quote.affordable, quote.balance_change, quote.fee)),
+        None => Ok((false, BigDecimal::zero(), BigDecimal::zero())),
     }
-    Ok((new_balance >= BigDecimal::zero(), balance_change))
 }
 
 pub fn create_trade(
@@ -197,12 +552,30 @@ pub fn create_trade(
     user_id: &str,
     shares_amount: &BigDecimal,
     share_index: i32,
+    idempotency_key: Option<&str>,
     conn: &mut PgConnection,
-) -> Result<(), String> {
-    let (is_valid, balance_change) = check_valid_trade(market_id, user_id, shares_amount, share_index, conn)?;
-    if !is_valid {
-        return Err("Invalid trade".to_string());
-    }
+) -> Result<(), Error> {
+    let request = json!({
+        "market_id": market_id,
+        "shares_amount": shares_amount.normalized().to_string(),
+        "share_index": share_index,
+    });
+    with_idempotency_key(idempotency_key, user_id, "create_trade", &request, conn, |conn| {
+        execute_trade(market_id, user_id, shares_amount, share_index, conn)
+    })
+}
+
+fn execute_trade(
+    market_id: i32,
+    user_id: &str,
+    shares_amount: &BigDecimal,
+    share_index: i32,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    let quote = quote_valid_trade(market_id, user_id, shares_amount, share_index, conn)?
+        .filter(|quote| quote.affordable)
+        .ok_or_else(|| "Invalid trade".to_string())?;
+    let TradeQuote { balance_change, fee, prob_after, .. } = quote;
 
     let new_trade = NewTrade {
         market_id,
@@ -210,11 +583,16 @@ pub fn create_trade(
         shares_amount: shares_amount.clone(),
         share_index,
         balance_change: balance_change.clone(),
+        fee: fee.clone(),
+        probs_after: prob_after,
+        from_answer: false,
     };
 
     let mut err = None;
     let transaction = conn.transaction::<(), DieselError, _>(|conn| {
-        change_balance(user_id, &balance_change, conn)
+        // the market holds on to the fee until it resolves, so resolving N/A
+        // can give it back without taking it from whoever spent it
+        change_balance(user_id, &(&balance_change - &fee), conn)
             .map_err(|e| {
                 err = Some(format!("Error updating user balance for trade: {}", e));
                 DieselError::RollbackTransaction
@@ -254,7 +632,7 @@ pub fn create_trade(
             })
     });
     if let Err(e) = transaction {
-        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)).into());
     }
     Ok(())
 }
@@ -266,6 +644,13 @@ pub fn get_positions(
     use crate::schema::trades::dsl as trades_dsl;
     use crate::schema::users::dsl as users_dsl;
 
+    let outcome_count = markets::table
+        .filter(markets::id.eq(market_id))
+        .select(markets::bought_shares)
+        .first::<Vec<Option<BigDecimal>>>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?
+        .len();
+
     let trades_simple = trades_dsl::trades
         .inner_join(users_dsl::users.on(trades_dsl::user_id.eq(users_dsl::id)))
         .filter(trades_dsl::market_id.eq(market_id))
@@ -283,8 +668,8 @@ pub fn get_positions(
             |mut acc, (user_id, shares_amount, share_index)| {
                 let idx: usize = share_index.try_into().unwrap();
                 acc.entry(user_id)
-                    .or_insert(
-                        vec![BigDecimal::zero(), BigDecimal::zero()]
+                    .or_insert_with(
+                        || vec![BigDecimal::zero(); outcome_count]
                     )[idx] += shares_amount;
                 acc
             }
@@ -293,6 +678,49 @@ pub fn get_positions(
     Ok(positions)
 }
 
+pub fn get_net_worth(
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<BigDecimal, String> {
+    let balance = users::table
+        .filter(users::id.eq(user_id))
+        .select(users::balance)
+        .first::<BigDecimal>(conn)
+        .map_err(|e| format!("Error fetching user balance: {}", e))?;
+    let open_trades = trades::table
+        .inner_join(markets::table)
+        .filter(trades::user_id.eq(user_id))
+        .filter(markets::is_resolved.eq(false))
+        .select((trades::market_id, trades::shares_amount, trades::share_index))
+        .load::<(i32, BigDecimal, i32)>(conn)
+        .map_err(|e| format!("Error fetching trades: {}", e))?;
+
+    let holdings = open_trades.into_iter()
+        .fold(HashMap::new(), |mut acc, (market_id, shares_amount, share_index)| {
+            *acc.entry((market_id, share_index)).or_insert(BigDecimal::zero()) += shares_amount;
+            acc
+        });
+
+    // value open positions at the current market probabilities
+    let mut net_worth = balance;
+    let mut market_probs: HashMap<i32, Vec<BigDecimal>> = HashMap::new();
+    for ((market_id, share_index), shares) in holdings {
+        let probs = match market_probs.entry(market_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let market = markets::table
+                    .filter(markets::id.eq(market_id))
+                    .first::<Market>(conn)
+                    .map_err(|e| format!("Error fetching market: {}", e))?;
+                entry.insert(prob(&market))
+            }
+        };
+        let idx: usize = share_index.try_into().unwrap();
+        net_worth += shares * &probs[idx];
+    }
+    Ok(net_worth.with_scale_round(4, RoundingMode::Down))
+}
+
 pub fn get_balance_changes_on_market(
     market_id: i32,
     conn: &mut PgConnection,
@@ -314,7 +742,117 @@ pub fn get_balance_changes_on_market(
     )
 }
 
+// the trading fees each user paid on a market
+pub fn get_fees_on_market(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<HashMap<String, BigDecimal>, String> {
+    use crate::schema::trades::dsl as trades_dsl;
+
+    let trades_simple = trades_dsl::trades
+        .filter(trades_dsl::market_id.eq(market_id))
+        .select((trades_dsl::user_id, trades_dsl::fee))
+        .load::<(String, BigDecimal)>(conn)
+        .map_err(|e| format!("Error fetching trades: {}", e))?;
+
+    Ok(
+        trades_simple.into_iter()
+        .fold(HashMap::new(), |mut acc, (user_id, fee)| {
+            *acc.entry(user_id).or_insert(BigDecimal::zero()) += fee;
+            acc
+        })
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResolutionKind {
+    // every share of one outcome pays a coin
+    Outcome,
+    // each outcome's shares pay its probability, which also covers scalar
+    // markets resolved to a value
+    Probabilities,
+    // trades are undone
+    NotApplicable,
+}
+
+impl ResolutionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResolutionKind::Outcome => "outcome",
+            ResolutionKind::Probabilities => "probabilities",
+            ResolutionKind::NotApplicable => "na",
+        }
+    }
+}
+
+impl FromStr for ResolutionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "outcome" => Ok(ResolutionKind::Outcome),
+            "probabilities" => Ok(ResolutionKind::Probabilities),
+            "na" => Ok(ResolutionKind::NotApplicable),
+            _ => Err(format!("Unknown resolution kind: {}", s)),
+        }
+    }
+}
+
+// how a market resolved, None while it's unresolved
+pub fn resolution_kind(market: &Market) -> Option<ResolutionKind> {
+    market.resolution_kind.as_deref()
+        .and_then(|kind| kind.parse().ok())
+}
+
+// the trading fees a market holds until it resolves
+pub fn market_fees(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<BigDecimal, String> {
+    trades::table
+        .filter(trades::market_id.eq(market_id))
+        .select(diesel::dsl::sum(trades::fee))
+        .first::<Option<BigDecimal>>(conn)
+        .map(Option::unwrap_or_default)
+        .map_err(|e| format!("Error fetching trades: {}", e))
+}
+
 pub fn resolve_market(
+    market_id: i32,
+    resolution: Option<i32>,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection
+) -> Result<(), Error> {
+    let request = json!({ "market_id": market_id, "resolution": resolution });
+    with_idempotency_key(idempotency_key, actor_id, "resolve_market", &request, conn, |conn| {
+        // resolving N/A is how a market gets cancelled
+        require_market_manager(actor_id, market_id, conn)?;
+        apply_resolution(market_id, resolution, conn).map_err(Error::from)
+    })
+}
+
+// locks the market row until the transaction ends and checks it's still in
+// the state the caller expects, so of two concurrent resolutions or
+// unresolutions the second waits for the first and then fails here
+fn lock_market(
+    market_id: i32,
+    is_resolved: bool,
+    conn: &mut PgConnection
+) -> Result<Market, String> {
+    let market = markets::table
+        .filter(markets::id.eq(market_id))
+        .for_update()
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?;
+    match (market.is_resolved, is_resolved) {
+        (true, false) => Err("Market is already resolved".to_string()),
+        (false, true) => Err("Market is not resolved".to_string()),
+        _ => Ok(market),
+    }
+}
+
+pub fn apply_resolution(
     market_id: i32,
     resolution: Option<i32>,
     conn: &mut PgConnection
@@ -325,24 +863,47 @@ pub fn resolve_market(
         .filter(markets_dsl::id.eq(market_id))
         .first::<Market>(conn)
         .map_err(|e| format!("Error fetching market: {}", e))?;
-    let bchanges = get_balance_changes_on_market(market_id, conn)
-        .map_err(|e| format!("Error fetching balance changes: {}", e))?;
+    if market.is_resolved {
+        return Err("Market is already resolved".to_string());
+    }
 
     if resolution.is_none() {
         let mut err = None;
         let transaction = conn.transaction::<(), DieselError, _>(|conn| {
-            // undo all balance changes
+            let market = lock_market(market_id, false, conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })?;
+            let bchanges = get_balance_changes_on_market(market_id, conn)
+                .map_err(|e| {
+                    err = Some(format!("Error fetching balance changes: {}", e));
+                    DieselError::RollbackTransaction
+                })?;
+            let mut fees = get_fees_on_market(market_id, conn)
+                .map_err(|e| {
+                    err = Some(format!("Error fetching trading fees: {}", e));
+                    DieselError::RollbackTransaction
+                })?;
+            let resolution_id = record_resolution(market_id, resolution, None, ResolutionKind::NotApplicable, conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })?;
+
+            // undo all balance changes and give back the fees the market held
             for (user_id, balance_change) in bchanges {
-                change_balance(&user_id, &-balance_change, conn)
+                let fee = fees.remove(&user_id).unwrap_or_default();
+                pay_out(resolution_id, &user_id, &(fee - balance_change), conn)
                     .map_err(|e| {
                         err = Some(format!("Error updating user balance for resolution: {}", e));
                         DieselError::RollbackTransaction
                     })?;
             }
 
-            // give all liquidity back to owner
+            // give all subsidy back to owner
             let owner_id = market.owner_id.clone();
-            change_balance(&owner_id, &market.liquidity, conn)
+            pay_out(resolution_id, &owner_id, &market.subsidy, conn)
                 .map_err(|e| {
                     err = Some(format!("Error giving liquidity back to owner: {}", e));
                     DieselError::RollbackTransaction
@@ -352,36 +913,261 @@ pub fn resolve_market(
                 .set((
                     markets_dsl::is_resolved.eq(true),
                     markets_dsl::resolution.eq(resolution),
+                    markets_dsl::resolution_kind.eq(ResolutionKind::NotApplicable.as_str()),
                 ))
                 .execute(conn)
-                .map(|_| ())
                 .map_err(|e| {
                     err = Some(format!("Error resolving market: {}", e));
                     DieselError::RollbackTransaction
-                })
-        });
+                })?;
+
+            resolve_conditional_markets(market_id, resolution_id, None, conn)
+                .map_err(|e| {
+                    err = Some(e);
+                    DieselError::RollbackTransaction
+                })
+        });
         if let Err(e) = transaction {
             return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
         }
         return Ok(());
     }
 
-    let share_index = resolution.unwrap();
-    let share_index: usize = share_index.try_into().unwrap();
-    let positions = get_positions(market_id, conn)
-        .map_err(|e| format!("Error fetching positions: {}", e))?;
+    let share_index: usize = resolution.unwrap().try_into()
+        .map_err(|_| "Invalid resolution".to_string())?;
+    if share_index >= market.bought_shares.len() {
+        return Err("Invalid resolution".to_string());
+    }
+    let resolution_probs = (0..market.bought_shares.len())
+        .map(|idx| if idx == share_index { BigDecimal::one() } else { BigDecimal::zero() })
+        .collect::<Vec<BigDecimal>>();
+
+    settle_market(&market, resolution, &resolution_probs, None, conn)
+}
+
+// resolves a market to a probability for each outcome, which its shares pay
+pub fn resolve_market_prob(
+    market_id: i32,
+    resolution_probs: &[BigDecimal],
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection
+) -> Result<(), Error> {
+    let request = json!({
+        "market_id": market_id,
+        "probs": resolution_probs.iter()
+            .map(|p| p.normalized().to_string())
+            .collect::<Vec<String>>(),
+    });
+    with_idempotency_key(idempotency_key, actor_id, "resolve_market", &request, conn, |conn| {
+        require_market_manager(actor_id, market_id, conn)?;
+        apply_prob_resolution(market_id, resolution_probs, conn).map_err(Error::from)
+    })
+}
+
+pub fn apply_prob_resolution(
+    market_id: i32,
+    resolution_probs: &[BigDecimal],
+    conn: &mut PgConnection
+) -> Result<(), String> {
+    let market = markets::table
+        .filter(markets::id.eq(market_id))
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?;
+    if market.is_resolved {
+        return Err("Market is already resolved".to_string());
+    }
+    let resolution_probs = probability_resolution(&market, resolution_probs)?;
+
+    settle_market(&market, None, &resolution_probs, None, conn)
+}
+
+// checks probabilities to resolve `market` to and rounds them the way they're
+// stored
+pub fn probability_resolution(
+    market: &Market,
+    resolution_probs: &[BigDecimal],
+) -> Result<Vec<BigDecimal>, String> {
+    if resolution_probs.len() != market.bought_shares.len() {
+        return Err("Resolution needs a probability for every outcome".to_string());
+    }
+    if resolution_probs.iter().any(|p| *p < BigDecimal::zero() || *p > BigDecimal::one()) {
+        return Err("Resolution probabilities must be between 0 and 1".to_string());
+    }
+
+    // probabilities are stored to 4 decimals, so after rounding whatever is
+    // left over goes to the last outcome and the stored ones sum to exactly 1;
+    // that can only make up for rounding, at most 0.0001 per outcome
+    let mut resolution_probs = resolution_probs.iter()
+        .map(|p| p.with_scale_round(4, RoundingMode::HalfEven))
+        .collect::<Vec<BigDecimal>>();
+    let remainder = BigDecimal::one() - resolution_probs.iter().sum::<BigDecimal>();
+    if remainder.abs() > BigDecimal::new(1.into(), 4) * BigDecimal::from(resolution_probs.len() as i64) {
+        return Err("Resolution probabilities must sum to 1".to_string());
+    }
+    let last = resolution_probs.last_mut().unwrap();
+    *last += remainder;
+    if *last < BigDecimal::zero() || *last > BigDecimal::one() {
+        return Err("Resolution probabilities must be between 0 and 1".to_string());
+    }
+    Ok(resolution_probs)
+}
+
+// resolves a scalar market to a number, paying LONG and SHORT in proportion
+pub fn resolve_market_value(
+    market_id: i32,
+    value: &BigDecimal,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection
+) -> Result<(), Error> {
+    let request = json!({ "market_id": market_id, "value": value.normalized().to_string() });
+    with_idempotency_key(idempotency_key, actor_id, "resolve_market", &request, conn, |conn| {
+        require_market_manager(actor_id, market_id, conn)?;
+        apply_value_resolution(market_id, value, conn).map_err(Error::from)
+    })
+}
+
+pub fn apply_value_resolution(
+    market_id: i32,
+    value: &BigDecimal,
+    conn: &mut PgConnection
+) -> Result<(), String> {
+    let market = markets::table
+        .filter(markets::id.eq(market_id))
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?;
+    if market.is_resolved {
+        return Err("Market is already resolved".to_string());
+    }
+    let (value, resolution_probs) = value_resolution(&market, value)
+        .ok_or_else(|| "Only scalar markets resolve to a value".to_string())?;
+
+    settle_market(&market, None, &resolution_probs, Some(&value), conn)
+}
+
+// resolves a date market to the outcome the date falls in
+pub fn resolve_market_date(
+    market_id: i32,
+    date: NaiveDate,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection
+) -> Result<(), Error> {
+    let request = json!({ "market_id": market_id, "date": date.to_string() });
+    with_idempotency_key(idempotency_key, actor_id, "resolve_market", &request, conn, |conn| {
+        require_market_manager(actor_id, market_id, conn)?;
+        apply_date_resolution(market_id, date, conn).map_err(Error::from)
+    })
+}
+
+pub fn apply_date_resolution(
+    market_id: i32,
+    date: NaiveDate,
+    conn: &mut PgConnection
+) -> Result<(), String> {
+    let outcomes = get_market_outcomes(market_id, conn)?;
+    let resolution = date_resolution(&outcomes, date)
+        .ok_or_else(|| "Only date markets resolve to a date".to_string())?;
+
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        apply_resolution(market_id, Some(resolution), conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        diesel::update(markets::table.filter(markets::id.eq(market_id)))
+            .set(markets::resolution_date.eq(date))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error resolving market: {}", e));
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(())
+}
+
+// resolves a free-response market to one of its answers by name; when none of
+// them is right, that's Other
+pub fn resolve_market_answer(
+    market_id: i32,
+    answer: &str,
+    actor_id: &str,
+    idempotency_key: Option<&str>,
+    conn: &mut PgConnection
+) -> Result<(), Error> {
+    let request = json!({ "market_id": market_id, "answer": answer });
+    with_idempotency_key(idempotency_key, actor_id, "resolve_market", &request, conn, |conn| {
+        require_market_manager(actor_id, market_id, conn)?;
 
-    let mut bankroll_left = market.liquidity.clone() - 
-        bchanges.values()
-            .sum::<BigDecimal>();
+        let outcomes = get_market_outcomes(market_id, conn)?;
+        if !is_free_response(&outcomes) {
+            return Err("Only free-response markets resolve to an answer".to_string().into());
+        }
+        let resolution = find_answer(&outcomes, answer)
+            .ok_or_else(|| format!(
+                "{} isn't an answer, resolve to {} if none of the answers is right",
+                answer.trim(), OTHER_LABEL
+            ))?;
+        apply_resolution(market_id, Some(resolution), conn).map_err(Error::from)
+    })
+}
 
+fn settle_market(
+    market: &Market,
+    resolution: Option<i32>,
+    resolution_probs: &[BigDecimal],
+    resolution_value: Option<&BigDecimal>,
+    conn: &mut PgConnection
+) -> Result<(), String> {
+    use crate::schema::markets::dsl as markets_dsl;
+
+    check_condition_met(market, conn)?;
+
+    let market_id = market.id;
     let mut err = None;
     let transaction = conn.transaction::<(), DieselError, _>(|conn| {
-        // reward traders by how many correct shares they bought
+        let market = lock_market(market_id, false, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        let bchanges = get_balance_changes_on_market(market_id, conn)
+            .map_err(|e| {
+                err = Some(format!("Error fetching balance changes: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        let positions = get_positions(market_id, conn)
+            .map_err(|e| {
+                err = Some(format!("Error fetching positions: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+
+        let mut bankroll_left = market.subsidy.clone() - 
+            bchanges.values()
+                .sum::<BigDecimal>();
+
+        let kind = if resolution.is_some() { ResolutionKind::Outcome } else { ResolutionKind::Probabilities };
+        let resolution_id = record_resolution(market_id, resolution, Some(resolution_probs), kind, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+
+        // each share pays out the probability its outcome resolved to
         for (users_id, shares) in positions {
-            let reward = shares[share_index].clone();
+            let reward = shares.iter()
+                .zip(resolution_probs)
+                .map(|(s, p)| s * p)
+                .sum::<BigDecimal>()
+                .with_scale_round(4, RoundingMode::Down);
             bankroll_left -= &reward;
-            change_balance(&users_id, &reward, conn)
+            pay_out(resolution_id, &users_id, &reward, conn)
                 .map_err(|e| {
                     err = Some(format!("Error updating user balance for resolution: {}", e));
                     DieselError::RollbackTransaction
@@ -390,22 +1176,389 @@ pub fn resolve_market(
 
         // give remaining bankroll to owner
         let owner_id = market.owner_id.clone();
-        change_balance(&owner_id, &bankroll_left, conn)
+        pay_out(resolution_id, &owner_id, &bankroll_left, conn)
             .map_err(|e| {
                 err = Some(format!("Error giving remaining bankroll to owner: {}", e));
                 DieselError::RollbackTransaction
             })?;
 
+        // the fees the market held go to their recipient now it won't be N/A
+        let fees = market_fees(market_id, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        if !fees.is_zero() {
+            pay_out(resolution_id, &market.fee_recipient, &fees, conn)
+                .map_err(|e| {
+                    err = Some(format!("Error paying trading fees: {}", e));
+                    DieselError::RollbackTransaction
+                })?;
+        }
+
         diesel::update(markets_dsl::markets.filter(markets_dsl::id.eq(market_id)))
             .set((
                 markets_dsl::is_resolved.eq(true),
                 markets_dsl::resolution.eq(resolution),
+                markets_dsl::resolution_probs.eq(Some(resolution_probs.to_vec())),
+                markets_dsl::resolution_kind.eq(kind.as_str()),
+                markets_dsl::resolution_value.eq(resolution_value),
             ))
             .execute(conn)
-            .map(|_| ())
             .map_err(|e| {
                 err = Some(format!("Error resolving market: {}", e));
                 DieselError::RollbackTransaction
+            })?;
+
+        resolve_conditional_markets(market_id, resolution_id, Some(resolution_probs), conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(())
+}
+
+fn record_resolution(
+    market_id: i32,
+    resolution: Option<i32>,
+    resolution_probs: Option<&[BigDecimal]>,
+    kind: ResolutionKind,
+    conn: &mut PgConnection
+) -> Result<i32, String> {
+    let new_resolution = NewResolution {
+        market_id,
+        resolution,
+        resolution_probs: resolution_probs.map(|probs| probs.to_vec()),
+        resolution_kind: kind.as_str().to_string(),
+    };
+
+    diesel::insert_into(resolutions::table)
+        .values(&new_resolution)
+        .returning(resolutions::id)
+        .get_result::<i32>(conn)
+        .map_err(|e| format!("Error recording resolution: {}", e))
+}
+
+fn pay_out(
+    resolution_id: i32,
+    user_id: &str,
+    amount: &BigDecimal,
+    conn: &mut PgConnection
+) -> Result<(), String> {
+    let new_payout = NewPayout {
+        resolution_id,
+        user_id: user_id.to_string(),
+        amount: amount.clone(),
+    };
+
+    change_balance(user_id, amount, conn)?;
+    diesel::insert_into(payouts::table)
+        .values(&new_payout)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| format!("Error recording payout: {}", e))
+}
+
+pub fn unresolve_market(
+    market_id: i32,
+    actor_id: &str,
+    conn: &mut PgConnection
+) -> Result<(), Error> {
+    require_market_manager(actor_id, market_id, conn)?;
+    Ok(reverse_resolution(market_id, conn)?)
+}
+
+pub fn reverse_resolution(
+    market_id: i32,
+    conn: &mut PgConnection
+) -> Result<(), String> {
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        lock_market(market_id, true, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        let resolution = resolutions::table
+            .filter(resolutions::market_id.eq(market_id))
+            .filter(resolutions::unresolved_at.is_null())
+            .order(resolutions::id.desc())
+            .first::<Resolution>(conn)
+            .optional()
+            .map_err(|e| {
+                err = Some(format!("Error fetching resolution: {}", e));
+                DieselError::RollbackTransaction
+            })?
+            .ok_or_else(|| {
+                err = Some("Market was resolved without recorded payouts".to_string());
+                DieselError::RollbackTransaction
+            })?;
+        let resolution_payouts = payouts::table
+            .filter(payouts::resolution_id.eq(resolution.id))
+            .load::<Payout>(conn)
+            .map_err(|e| {
+                err = Some(format!("Error fetching payouts: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+
+        // take back exactly what the resolution paid out
+        for payout in resolution_payouts {
+            change_balance(&payout.user_id, &-payout.amount, conn)
+                .map_err(|e| {
+                    err = Some(format!("Error reversing payout to {}: {}", payout.user_id, e));
+                    DieselError::RollbackTransaction
+                })?;
+        }
+
+        let reversed = diesel::update(
+            resolutions::table
+                .filter(resolutions::id.eq(resolution.id))
+                .filter(resolutions::unresolved_at.is_null())
+        )
+            .set(resolutions::unresolved_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(conn)
+            .map_err(|e| {
+                err = Some(format!("Error recording unresolution: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        if reversed == 0 {
+            err = Some("Resolution was already reversed".to_string());
+            return Err(DieselError::RollbackTransaction);
+        }
+
+        diesel::update(markets::table.filter(markets::id.eq(market_id)))
+            .set((
+                markets::is_resolved.eq(false),
+                markets::resolution.eq(None::<i32>),
+                markets::resolution_probs.eq(None::<Vec<BigDecimal>>),
+                markets::resolution_kind.eq(None::<String>),
+                markets::resolution_value.eq(None::<BigDecimal>),
+                markets::resolution_date.eq(None::<NaiveDate>),
+            ))
+            .execute(conn)
+            .map_err(|e| {
+                err = Some(format!("Error unresolving market: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+
+        reopen_conditional_markets(resolution.id, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(())
+}
+
+pub fn market_bankroll(
+    market: &Market,
+    conn: &mut PgConnection,
+) -> Result<BigDecimal, String> {
+    let bchange_sum = trades::table
+        .filter(trades::market_id.eq(market.id))
+        .select(diesel::dsl::sum(trades::balance_change))
+        .first::<Option<BigDecimal>>(conn)
+        .map_err(|e| format!("Error fetching trades: {}", e))?
+        .unwrap_or_else(BigDecimal::zero);
+
+    // balance changes are negative when traders pay into the market
+    Ok(&market.subsidy - bchange_sum)
+}
+
+pub fn get_withdrawable_subsidy(
+    market: &Market,
+    conn: &mut PgConnection,
+) -> Result<BigDecimal, String> {
+    // the bankroll must keep covering the cost function, which bounds
+    // the payout of every outcome
+    let bankroll = market_bankroll(market, conn)?;
+    let slack = bankroll - cost_function(market);
+    if slack <= BigDecimal::zero() {
+        return Ok(BigDecimal::zero());
+    }
+    Ok(slack.min(market.subsidy.clone()).with_scale_round(4, RoundingMode::Down))
+}
+
+fn fetch_owned_open_market(
+    market_id: i32,
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<Market, String> {
+    let market = markets::table
+        .filter(markets::id.eq(market_id))
+        .for_update()
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))?;
+    if market.owner_id != user_id {
+        return Err("Only the market owner can change its liquidity".to_string());
+    }
+    if market.is_resolved {
+        return Err("Market is already resolved".to_string());
+    }
+    Ok(market)
+}
+
+pub fn add_liquidity(
+    market_id: i32,
+    user_id: &str,
+    amount: &BigDecimal,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    if *amount <= BigDecimal::zero() {
+        return Err("Amount must be positive".to_string());
+    }
+    let settings = get_settings(conn)?;
+
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        let market = fetch_owned_open_market(market_id, user_id, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        let bankroll = market_bankroll(&market, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+
+        if bankroll <= BigDecimal::zero() {
+            err = Some("The market has no bankroll to add liquidity to".to_string());
+            return Err(DieselError::RollbackTransaction);
+        }
+
+        // scale liquidity, shares and bankroll by the same factor, as if
+        // the market had been created with the extra subsidy
+        let factor = (&bankroll + amount) / &bankroll;
+        let rescaled = rescale_liquidity(&market, &factor);
+
+        // positions aren't scaled, so bought_shares ends up above what traders
+        // hold rather than equal to it; trades move both by the same amount
+        // from then on, so each outcome still pays out at most its bought
+        // shares, which the cost function and so the bankroll cover
+        let positions = get_positions(market_id, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        let held = positions.values()
+            .fold(vec![BigDecimal::zero(); rescaled.bought_shares.len()], |mut held, shares| {
+                held.iter_mut().zip(shares).for_each(|(h, s)| *h += s);
+                held
+            });
+        if rescaled.bought_shares.iter().zip(&held).any(|(s, h)| s.clone().unwrap_or_default() < *h) {
+            err = Some("Adding liquidity would leave outcomes with fewer shares than traders hold".to_string());
+            return Err(DieselError::RollbackTransaction);
+        }
+        if let Some(max_liquidity) = &settings.max_liquidity
+            && rescaled.liquidity > *max_liquidity
+        {
+            err = Some(format!("Liquidity can be at most {}", max_liquidity));
+            return Err(DieselError::RollbackTransaction);
+        }
+        if &bankroll + amount < cost_function(&rescaled) {
+            err = Some("Adding liquidity would leave the market insolvent".to_string());
+            return Err(DieselError::RollbackTransaction);
+        }
+
+        change_balance(user_id, &-amount, conn)
+            .map_err(|e| {
+                err = Some(format!("Error deducting owner's balance for liquidity: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        diesel::update(markets::table.filter(markets::id.eq(market_id)))
+            .set((
+                markets::liquidity.eq(&rescaled.liquidity),
+                markets::bought_shares.eq(&rescaled.bought_shares),
+                markets::subsidy.eq(&market.subsidy + amount),
+            ))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error updating market liquidity: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        diesel::insert_into(liquidity_changes::table)
+            .values(NewLiquidityChange {
+                market_id,
+                user_id: user_id.to_string(),
+                amount: amount.clone(),
+                liquidity_before: market.liquidity.clone(),
+                liquidity_after: rescaled.liquidity.clone(),
+            })
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error recording liquidity change: {}", e));
+                DieselError::RollbackTransaction
+            })
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| format!("Transaction failed: {}", e)));
+    }
+    Ok(())
+}
+
+pub fn withdraw_subsidy(
+    market_id: i32,
+    user_id: &str,
+    amount: &BigDecimal,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    if *amount <= BigDecimal::zero() {
+        return Err("Amount must be positive".to_string());
+    }
+
+    let mut err = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        let market = fetch_owned_open_market(market_id, user_id, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        let withdrawable = get_withdrawable_subsidy(&market, conn)
+            .map_err(|e| {
+                err = Some(e);
+                DieselError::RollbackTransaction
+            })?;
+        if *amount > withdrawable {
+            err = Some(format!("Only {} of the subsidy can be withdrawn", withdrawable));
+            return Err(DieselError::RollbackTransaction);
+        }
+
+        change_balance(user_id, amount, conn)
+            .map_err(|e| {
+                err = Some(format!("Error returning subsidy to owner: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        diesel::update(markets::table.filter(markets::id.eq(market_id)))
+            .set(markets::subsidy.eq(&market.subsidy - amount))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error updating market subsidy: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        diesel::insert_into(liquidity_changes::table)
+            .values(NewLiquidityChange {
+                market_id,
+                user_id: user_id.to_string(),
+                amount: -amount,
+                liquidity_before: market.liquidity.clone(),
+                liquidity_after: market.liquidity.clone(),
+            })
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| {
+                err = Some(format!("Error recording liquidity change: {}", e));
+                DieselError::RollbackTransaction
             })
     });
     if let Err(e) = transaction {
@@ -0,0 +1,122 @@
+use bigdecimal::{BigDecimal, One, RoundingMode, Zero};
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::lmsr::{bchange_to_schange, prob, schange_to_bchange};
+use crate::pmarket::errors::Error;
+use crate::pmarket::methods::{find_market, trade_fee};
+
+pub enum TradeSize {
+    // shares to buy, or if negative, to sell
+    Shares(BigDecimal),
+    // coins to spend, or if negative, to receive, fees included
+    Amount(BigDecimal),
+}
+
+pub struct TradeQuote {
+    pub share_index: i32,
+    pub shares_amount: BigDecimal,
+    // what the trade does to the trader's balance before the fee, as stored on trades
+    pub balance_change: BigDecimal,
+    pub fee: BigDecimal,
+    // coins the trader pays, fee included, or if negative, receives
+    pub cost: BigDecimal,
+    // cost per share, the same for buys and sells
+    pub average_price: BigDecimal,
+    pub prob_before: Vec<BigDecimal>,
+    pub prob_after: Vec<BigDecimal>,
+    pub balance: BigDecimal,
+    // shares of the outcome the trader holds after the trade
+    pub position: BigDecimal,
+    // what those shares pay if the outcome wins
+    pub max_payout: BigDecimal,
+    pub affordable: bool,
+}
+
+fn size_to_shares(
+    market: &Market,
+    size: &TradeSize,
+    share_index: i32,
+) -> Result<BigDecimal, String> {
+    let amount = match size {
+        TradeSize::Shares(shares_amount) => return Ok(shares_amount.clone()),
+        TradeSize::Amount(amount) => amount,
+    };
+    // buyers pay the fee on top of the price and sellers have it taken off
+    let fee_factor = if *amount >= BigDecimal::zero() {
+        BigDecimal::one() + &market.fee_rate
+    } else {
+        BigDecimal::one() - &market.fee_rate
+    };
+    let balance_change = -(amount / fee_factor);
+    bchange_to_schange(market, &balance_change, share_index)
+        .ok_or_else(|| format!("No trade on outcome {} pays out {}", share_index, -amount))
+}
+
+pub fn quote_trade(
+    market_id: i32,
+    user_id: &str,
+    share_index: i32,
+    size: &TradeSize,
+    conn: &mut PgConnection,
+) -> Result<TradeQuote, Error> {
+    let market = find_market(market_id, conn)?;
+    let idx = usize::try_from(share_index).ok()
+        .filter(|&idx| idx < market.bought_shares.len())
+        .ok_or_else(|| format!("Invalid outcome: {}", share_index))?;
+    let balance = users::table
+        .filter(users::id.eq(user_id))
+        .select(users::balance)
+        .first::<BigDecimal>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching user balance: {}", e))?
+        .ok_or_else(|| Error::not_found("User not found"))?;
+    let held = trades::table
+        .filter(trades::market_id.eq(market_id))
+        .filter(trades::user_id.eq(user_id))
+        .filter(trades::share_index.eq(share_index))
+        .select(diesel::dsl::sum(trades::shares_amount))
+        .first::<Option<BigDecimal>>(conn)
+        .map_err(|e| format!("Error fetching position: {}", e))?
+        .unwrap_or_default();
+
+    let shares_amount = size_to_shares(&market, size, share_index)?;
+    let balance_change = schange_to_bchange(&market, shares_amount.clone(), share_index);
+    let fee = trade_fee(&market, &balance_change);
+    let cost = &fee - &balance_change;
+    let average_price = if shares_amount.is_zero() {
+        BigDecimal::zero()
+    } else {
+        (&cost / &shares_amount).with_scale_round(4, RoundingMode::HalfEven)
+    };
+
+    let round_probs = |market: &Market| -> Vec<BigDecimal> {
+        prob(market).into_iter()
+            .map(|p| p.with_scale_round(4, RoundingMode::HalfEven))
+            .collect()
+    };
+    let prob_before = round_probs(&market);
+    let mut after = market.clone();
+    after.bought_shares[idx] = after.bought_shares[idx].take()
+        .map(|shares| shares + &shares_amount);
+    let prob_after = round_probs(&after);
+
+    let position = held + &shares_amount;
+    let max_payout = position.clone().max(BigDecimal::zero());
+    let affordable = &balance - &cost >= BigDecimal::zero();
+
+    Ok(TradeQuote {
+        share_index,
+        shares_amount,
+        balance_change,
+        fee,
+        cost,
+        average_price,
+        prob_before,
+        prob_after,
+        balance,
+        position,
+        max_payout,
+        affordable,
+    })
+}
\ No newline at end of file
@@ -0,0 +1,680 @@
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use serde_json::{Value, json};
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::answers::{MAX_ANSWERS, OTHER, answer_cost, is_free_response};
+use crate::pmarket::browse::{MarketFilter, MarketSort, MarketStatus, get_market_tags, list_markets};
+use crate::pmarket::conditional::condition;
+use crate::pmarket::dates::{date_percentile, get_market_outcomes, is_date_market};
+use crate::pmarket::lmsr::prob;
+use crate::pmarket::methods::{ResolutionKind, resolution_kind};
+use crate::pmarket::quotes::{TradeSize, quote_trade};
+use crate::pmarket::scalar::{expected_value, scalar_range};
+use crate::pmarket::scoring::score_user;
+use crate::pmarket::stats::{DEPTH_TARGETS, market_stats};
+
+const CLOSING_SOON_COUNT: i64 = 5;
+
+fn mrkdwn(text: impl Into<String>) -> Value {
+    json!({
+        "type": "mrkdwn",
+        "text": text.into(),
+    })
+}
+
+fn plain_text(text: impl Into<String>) -> Value {
+    json!({
+        "type": "plain_text",
+        "text": text.into(),
+        "emoji": true,
+    })
+}
+
+fn section(text: impl Into<String>) -> Value {
+    json!({
+        "type": "section",
+        "text": mrkdwn(text),
+    })
+}
+
+fn context(texts: Vec<String>) -> Value {
+    json!({
+        "type": "context",
+        "elements": texts.into_iter().map(mrkdwn).collect::<Vec<Value>>(),
+    })
+}
+
+fn header(text: &str) -> Value {
+    json!({
+        "type": "header",
+        "text": plain_text(text),
+    })
+}
+
+fn button(text: &str, action_id: &str, value: Option<&str>) -> Value {
+    let mut button = json!({
+        "type": "button",
+        "text": plain_text(text),
+        "action_id": action_id,
+    });
+    if let Some(value) = value {
+        button["value"] = json!(value);
+    }
+    button
+}
+
+fn overflow_option(text: &str, value: &str) -> Value {
+    json!({
+        "text": {
+            "type": "plain_text",
+            "text": text,
+        },
+        "value": value,
+    })
+}
+
+// Slack shows the date in the reader's timezone, the fallback is for clients that can't
+fn slack_date(time: &NaiveDateTime) -> String {
+    format!(
+        "<!date^{}^{{date_num}}|{}>",
+        time.and_utc().timestamp(),
+        time.format("%Y-%m-%d")
+    )
+}
+
+fn load_market(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Market, String> {
+    markets::table
+        .filter(markets::id.eq(market_id))
+        .first::<Market>(conn)
+        .map_err(|e| format!("Error fetching market: {}", e))
+}
+
+fn main_slack_msg(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Option<MarketSlackMsg>, String> {
+    market_slack_msg::table
+        .filter(market_slack_msg::market_id.eq(market_id))
+        .filter(market_slack_msg::main.eq(true))
+        .first::<MarketSlackMsg>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching market slack message: {}", e))
+}
+
+fn probs(market: &Market) -> Vec<f64> {
+    prob(market).iter()
+        .map(|p| p.to_f64().unwrap())
+        .collect()
+}
+
+// the emoji and name of each outcome, in share index order
+fn outcome_labels(market: &Market, outcomes: &[MarketOutcome]) -> Vec<(&'static str, String)> {
+    if !outcomes.is_empty() {
+        outcomes.iter()
+            .map(|outcome| {
+                let emoji = if outcome.starts_on.is_some() {
+                    ":spiral_calendar_pad:"
+                } else if outcome.share_index == OTHER {
+                    ":grey_question:"
+                } else {
+                    ":speech_balloon:"
+                };
+                (emoji, outcome.label.clone())
+            })
+            .collect()
+    } else if scalar_range(market).is_some() {
+        vec![(":chart_with_upwards_trend:", "LONG".to_string()), (":chart_with_downwards_trend:", "SHORT".to_string())]
+    } else {
+        vec![(":white_check_mark:", "YES".to_string()), (":x:", "NO".to_string())]
+    }
+}
+
+// what LONG's probability `long` says a scalar market will resolve to
+fn scalar_value(market: &Market, long: f64) -> f64 {
+    let (min, max) = scalar_range(market)
+        .map(|(min, max)| (min.to_f64().unwrap(), max.to_f64().unwrap()))
+        .unwrap_or((0.0, 1.0));
+    min + (max - min) * long
+}
+
+fn format_value(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    rounded.to_string()
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%b %-d, %Y").to_string()
+}
+
+// the date a date market puts `q` of its probability before, or the last,
+// open-ended outcome if that's where it falls
+fn percentile_text(market: &Market, outcomes: &[MarketOutcome], q: f64) -> String {
+    date_percentile(market, outcomes, q)
+        .map(format_date)
+        .or_else(|| outcomes.last().map(|outcome| outcome.label.clone()))
+        .unwrap_or_default()
+}
+
+fn forecast_text(market: &Market, outcomes: &[MarketOutcome]) -> String {
+    if is_date_market(outcomes) {
+        return format!("Median *{}*", percentile_text(market, outcomes, 0.5));
+    }
+    if is_free_response(outcomes) {
+        let (p, outcome) = probs(market).into_iter()
+            .zip(outcomes)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .unwrap();
+        return format!("*{}* leads at *{:.0}%*", outcome.label, p * 100.0);
+    }
+    match expected_value(market) {
+        Some(value) => format!("Expected *{}*", format_value(value.to_f64().unwrap())),
+        None => format!("*{:.0}%* chance", probs(market)[0] * 100.0),
+    }
+}
+
+fn resolution_text(market: &Market, outcomes: &[MarketOutcome]) -> String {
+    let labels = outcome_labels(market, outcomes);
+    let (first_emoji, first) = &labels[0];
+    if let Some(date) = market.resolution_date {
+        return format!("to {}", format_date(date));
+    }
+    if let Some(value) = &market.resolution_value {
+        return format!("to {}", format_value(value.to_f64().unwrap()));
+    }
+    match (resolution_kind(market), market.resolution, &market.resolution_probs) {
+        (Some(ResolutionKind::Outcome), Some(resolution), _) => {
+            let (emoji, label) = &labels[resolution as usize];
+            format!("{} {}", emoji, label)
+        }
+        (Some(ResolutionKind::Probabilities), _, Some(resolution_probs)) => {
+            let first_prob = resolution_probs[0].as_ref().map_or(0.0, |p| p.to_f64().unwrap());
+            format!("{:.0}% {} {}", first_prob * 100.0, first_emoji, first)
+        }
+        _ => ":question: N/A".to_string(),
+    }
+}
+
+// what a conditional market's question assumes
+fn condition_text(market: &Market, conn: &mut PgConnection) -> Result<Option<String>, String> {
+    let Some((parent_market_id, parent_outcome)) = condition(market) else {
+        return Ok(None);
+    };
+    let parent = load_market(parent_market_id, conn)?;
+    let parent_outcomes = get_market_outcomes(parent_market_id, conn)?;
+    let labels = outcome_labels(&parent, &parent_outcomes);
+    let (emoji, label) = &labels[parent_outcome as usize];
+    Ok(Some(format!(
+        "Only counts if *{}* resolves {} {}, N/A otherwise",
+        parent.title, emoji, label
+    )))
+}
+
+pub fn market_view(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Value, String> {
+    let market = load_market(market_id, conn)?;
+    let outcomes = get_market_outcomes(market_id, conn)?;
+    let date = is_date_market(&outcomes);
+    let tags = get_market_tags(market_id, conn)?;
+    let stats = market_stats(market_id, conn)?;
+    let liquidity = format!("*{:.0}* :dollar: liquidity", market.liquidity.to_f64().unwrap());
+    let condition = condition_text(&market, conn)?;
+
+    let (menu_options, probability_blocks, context_texts) = if market.is_resolved {
+        (
+            vec![overflow_option("Unresolve", "unresolve")],
+            vec![section(format!("Resolved *{}*", resolution_text(&market, &outcomes)))],
+            std::iter::once(liquidity).chain(condition).collect(),
+        )
+    } else {
+        let scalar = scalar_range(&market).is_some();
+        let change = stats.prob_change_24h[0];
+        let change_text = if scalar && change != 0.0 {
+            let value_change = scalar_value(&market, change) - scalar_value(&market, 0.0);
+            format!(" ({}{} today)", if value_change > 0.0 { "+" } else { "" }, format_value(value_change))
+        } else if !scalar && !date && (change * 100.0).round() != 0.0 {
+            format!(" ({:+.0}% today)", change * 100.0)
+        } else {
+            String::new()
+        };
+        let depth_text = DEPTH_TARGETS.iter()
+            .zip(&stats.depth[0])
+            .filter(|(_, cost)| **cost > BigDecimal::zero())
+            .map(|(target, cost)| format!(
+                "{:.0} :dollar: to move to {}",
+                cost.to_f64().unwrap(),
+                if scalar {
+                    format_value(scalar_value(&market, *target))
+                } else {
+                    format!("{:.0}%", target * 100.0)
+                }
+            ))
+            .collect::<Vec<String>>()
+            .join(" · ");
+
+        let mut context_texts = vec![liquidity];
+        let fee_rate = market.fee_rate.to_f64().unwrap();
+        if fee_rate > 0.0 {
+            context_texts.push(format!("*{}%* trading fee", fee_rate * 100.0));
+        }
+        context_texts.push(format!("Resolves on *{}*", slack_date(&market.remind_at)));
+        if stats.trades > 0 {
+            context_texts.push(format!(
+                "*{:.0}* :dollar: volume from {} traders",
+                stats.volume.to_f64().unwrap(),
+                stats.traders
+            ));
+        }
+        let labels = market.category.iter()
+            .map(|category| format!("*{}*", category))
+            .chain(tags.iter().map(|tag| format!("#{}", tag)))
+            .collect::<Vec<String>>();
+        if !labels.is_empty() {
+            context_texts.push(labels.join(" "));
+        }
+        context_texts.extend(condition);
+
+        if let (Some(min), Some(max)) = (&market.scalar_min, &market.scalar_max) {
+            context_texts.push(format!(
+                "Resolves between *{}* and *{}* with `/pmarket resolve {} <value>`",
+                format_value(min.to_f64().unwrap()),
+                format_value(max.to_f64().unwrap()),
+                market.id
+            ));
+        }
+        if date {
+            context_texts.push(format!(
+                "Resolves to the day it happens with `/pmarket resolve {} <YYYY-MM-DD>`",
+                market.id
+            ));
+        }
+        if is_free_response(&outcomes) {
+            context_texts.push(format!(
+                "Resolves to an answer with `/pmarket resolve {} <answer>`",
+                market.id
+            ));
+        }
+
+        // scalar, date and free-response markets resolve to a value, a date or
+        // an answer rather than from this menu
+        let mut menu_options = Vec::new();
+        if !scalar && outcomes.is_empty() {
+            menu_options.push(overflow_option("Resolve :white_check_mark: YES", "resolve_yes"));
+            menu_options.push(overflow_option("Resolve :x: NO", "resolve_no"));
+        }
+        menu_options.push(overflow_option("Resolve :question: N/A", "resolve_na"));
+        // markets with more outcomes ask conditional questions from each outcome's menu
+        if !scalar && outcomes.is_empty() {
+            menu_options.push(overflow_option("Ask a question if :white_check_mark: YES", "if_yes"));
+            menu_options.push(overflow_option("Ask a question if :x: NO", "if_no"));
+        }
+        let probability_blocks = if is_free_response(&outcomes) {
+            vec![section(forecast_text(&market, &outcomes))]
+        } else if date {
+            vec![section(format!(
+                "{}\n80% between *{}* and *{}*",
+                forecast_text(&market, &outcomes),
+                percentile_text(&market, &outcomes, 0.1),
+                percentile_text(&market, &outcomes, 0.9),
+            ))]
+        } else {
+            vec![
+                section(format!("{}{}", forecast_text(&market, &outcomes), change_text)),
+                context(vec![depth_text]),
+            ]
+        };
+        (menu_options, probability_blocks, context_texts)
+    };
+    let labels = outcome_labels(&market, &outcomes);
+
+    let mut blocks = vec![json!({
+        "type": "section",
+        "text": mrkdwn(format!("*{}*", market.title)),
+        "accessory": {
+            "type": "overflow",
+            "options": menu_options,
+            "action_id": "options_menu",
+        },
+    })];
+    blocks.extend(probability_blocks);
+    blocks.extend([
+        context(context_texts),
+        section(market.description.clone()),
+    ]);
+    if outcomes.is_empty() {
+        let [(first_emoji, first), (second_emoji, second)] = [&labels[0], &labels[1]];
+        blocks.extend([
+            json!({
+                "type": "actions",
+                "elements": [
+                    button(&format!("Buy {} {} shares", first_emoji, first), "action_buy_yes", Some("yes")),
+                    button(&format!("Buy {} {} shares", second_emoji, second), "action_buy_no", Some("no")),
+                ],
+            }),
+            json!({
+                "type": "actions",
+                "elements": [
+                    button(&format!("Sell {} {} shares", first_emoji, first), "action_sell_yes", Some("yes")),
+                    button(&format!("Sell {} {} shares", second_emoji, second), "action_sell_no", Some("no")),
+                ],
+            }),
+        ]);
+    } else {
+        // too many outcomes for buttons, so each gets a line with a menu to trade it
+        blocks.extend(probs(&market).iter()
+            .zip(&labels)
+            .enumerate()
+            .map(|(idx, (p, (emoji, label)))| {
+                let mut options = vec![
+                    overflow_option("Buy shares", &format!("buy_{}", idx)),
+                    overflow_option("Sell shares", &format!("sell_{}", idx)),
+                ];
+                if !market.is_resolved {
+                    options.push(overflow_option("Ask a question if this happens", &format!("if_{}", idx)));
+                }
+                json!({
+                    "type": "section",
+                    "text": mrkdwn(format!("{} {}: *{:.0}%*", emoji, label, p * 100.0)),
+                    "accessory": {
+                        "type": "overflow",
+                        "options": options,
+                        "action_id": format!("action_trade_outcome_{}", idx),
+                    },
+                })
+            }));
+        if is_free_response(&outcomes) && !market.is_resolved && outcomes.len() <= MAX_ANSWERS {
+            blocks.push(json!({
+                "type": "actions",
+                "elements": [button("Add an answer :heavy_plus_sign:", "action_add_answer", None)],
+            }));
+        }
+    }
+    blocks.push(context(vec![format!("_Created by <@{}> using /pmarket_", market.owner_id)]));
+    Ok(json!({ "blocks": blocks }))
+}
+
+// what the trade modal shows for trading `shares_amount` shares of one outcome,
+// priced exactly the way create_trade will price it
+#[allow(clippy::too_many_arguments)]
+pub fn trade_view(
+    market_id: i32,
+    user_id: &str,
+    shares_amount: &BigDecimal,
+    buy: bool,
+    share_index: i32,
+    channel_id: &str,
+    ts: &str,
+    conn: &mut PgConnection,
+) -> Result<Value, String> {
+    let shares_change = if buy { shares_amount.clone() } else { -shares_amount };
+    let quote = quote_trade(market_id, user_id, share_index, &TradeSize::Shares(shares_change.clone()), conn)?;
+    let market = load_market(market_id, conn)?;
+    let outcomes = get_market_outcomes(market_id, conn)?;
+    let position = &quote.position - &shares_change;
+    let balance = &quote.balance;
+    let fee = &quote.fee;
+
+    // action and callback ids keep calling the outcomes of binary and scalar
+    // markets yes and no
+    let outcome_key = match (outcomes.is_empty(), share_index) {
+        (true, 0) => "yes".to_string(),
+        (true, _) => "no".to_string(),
+        (false, _) => format!("outcome_{}", share_index),
+    };
+    let (emoji, yes_no) = outcome_labels(&market, &outcomes).swap_remove(share_index as usize);
+    let (buysell, buy_sell) = if buy { ("buy", "Buy") } else { ("sell", "Sell") };
+    // buying costs the price plus the fee, selling pays the price minus it
+    let amount = if buy { quote.cost.clone() } else { -&quote.cost };
+
+    let balance_or_position = if buy {
+        format!("Balance: *{:.0}* :dollar:", balance.to_f64().unwrap())
+    } else {
+        format!("Position: *{:.0}* {} {} shares", position.to_f64().unwrap(), emoji, yes_no)
+    };
+    let fee_text = if *fee > BigDecimal::zero() {
+        format!(" (incl. *{:.0}* :dollar: fee)", fee.to_f64().unwrap())
+    } else {
+        String::new()
+    };
+    let prob_before = quote.prob_before[0].to_f64().unwrap();
+    let prob_after = quote.prob_after[0].to_f64().unwrap();
+    let forecast_change = if is_date_market(&outcomes) {
+        let idx = share_index as usize;
+        let mut after = market.clone();
+        after.bought_shares[idx] = after.bought_shares[idx].take()
+            .map(|shares| shares + &shares_change);
+        format!(
+            "Probability: *{:.0}%* → *{:.0}%*\nMedian: *{}* → *{}*",
+            quote.prob_before[idx].to_f64().unwrap() * 100.0,
+            quote.prob_after[idx].to_f64().unwrap() * 100.0,
+            percentile_text(&market, &outcomes, 0.5),
+            percentile_text(&after, &outcomes, 0.5),
+        )
+    } else if !outcomes.is_empty() {
+        let idx = share_index as usize;
+        format!(
+            "Probability: *{:.0}%* → *{:.0}%*",
+            quote.prob_before[idx].to_f64().unwrap() * 100.0,
+            quote.prob_after[idx].to_f64().unwrap() * 100.0,
+        )
+    } else if scalar_range(&market).is_some() {
+        format!(
+            "Expected: *{}* → *{}*",
+            format_value(scalar_value(&market, prob_before)),
+            format_value(scalar_value(&market, prob_after)),
+        )
+    } else {
+        format!("Probability: *{:.0}%* → *{:.0}%*", prob_before * 100.0, prob_after * 100.0)
+    };
+    let summary = format!(
+        "{}\n{}: *{:.0}* :dollar:{}\n{}",
+        balance_or_position,
+        if buy { "Bet amount" } else { "Payoff" },
+        amount.to_f64().unwrap(),
+        fee_text,
+        forecast_change,
+    );
+
+    Ok(json!({
+        "type": "modal",
+        "callback_id": format!("{}_view_{}", buysell, outcome_key),
+        // modal titles are too short for the names of date ranges
+        "title": plain_text(if outcomes.is_empty() {
+            format!("{} {} shares", buy_sell, yes_no)
+        } else {
+            format!("{} shares", buy_sell)
+        }),
+        "submit": plain_text(buy_sell),
+        "close": plain_text("Cancel"),
+        "blocks": [
+            {
+                "type": "input",
+                "block_id": format!("block_shares_{}_{}", buysell, outcome_key),
+                "dispatch_action": true,
+                "element": {
+                    "type": "number_input",
+                    "dispatch_action_config": {
+                        "trigger_actions_on": ["on_enter_pressed", "on_character_entered"],
+                    },
+                    "is_decimal_allowed": false,
+                    "action_id": format!("action_shares_{}_{}", buysell, outcome_key),
+                    "placeholder": {
+                        "type": "plain_text",
+                        "text": "0",
+                    },
+                    "initial_value": format!("{:.0}", shares_amount.to_f64().unwrap()),
+                },
+                "label": plain_text(format!("Amount of {} {} shares", emoji, yes_no)),
+            },
+            section(summary),
+        ],
+        "private_metadata": json!({
+            "market_id": market_id,
+            "share_index": share_index,
+            "outcome_key": outcome_key,
+            "channel_id": channel_id,
+            "ts": ts,
+        }).to_string(),
+    }))
+}
+
+// the modal for adding an answer to a free-response market, with what it costs
+pub fn add_answer_view(
+    market_id: i32,
+    user_id: &str,
+    channel_id: &str,
+    ts: &str,
+    conn: &mut PgConnection,
+) -> Result<Value, String> {
+    let market = load_market(market_id, conn)?;
+    let balance = users::table
+        .filter(users::id.eq(user_id))
+        .select(users::balance)
+        .first::<BigDecimal>(conn)
+        .map_err(|e| format!("Error fetching user balance: {}", e))?;
+    let other = probs(&market)[OTHER as usize];
+    let summary = format!(
+        "Adding an answer costs *{:.0}* :dollar:, which goes towards paying out the market. It starts out at *{:.0}%*, half of what :grey_question: Other is at, and whoever holds Other shares gets as many shares of it.\nBalance: *{:.0}* :dollar:",
+        answer_cost(&market).to_f64().unwrap(),
+        other * 50.0,
+        balance.to_f64().unwrap(),
+    );
+
+    Ok(json!({
+        "type": "modal",
+        "callback_id": "add_answer_view",
+        "title": plain_text("Add an answer"),
+        "submit": plain_text("Add"),
+        "close": plain_text("Cancel"),
+        "blocks": [
+            {
+                "type": "input",
+                "block_id": "block_answer_add_answer",
+                "element": {
+                    "type": "plain_text_input",
+                    "action_id": "action_answer_add_answer",
+                    "max_length": 100,
+                },
+                "label": plain_text("Answer"),
+            },
+            section(summary),
+        ],
+        "private_metadata": json!({
+            "market_id": market_id,
+            "channel_id": channel_id,
+            "ts": ts,
+        }).to_string(),
+    }))
+}
+
+pub fn reminder_view(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Value, String> {
+    let market = load_market(market_id, conn)?;
+    let outcomes = get_market_outcomes(market_id, conn)?;
+    let msg = main_slack_msg(market_id, conn)?
+        .ok_or_else(|| format!("Market {} was never posted to Slack", market_id))?;
+    let text = format!(
+        "Hey <@{}>, you have a market:\n<https://hackclub.slack.com/archives/{}/p{}|{}>\nYou said it should resolve on {}.\nPlease resolve it now{}, or edit the question to change the resolution date.",
+        market.owner_id,
+        msg.channel_id,
+        msg.ts.replace('.', ""),
+        market.title,
+        slack_date(&market.remind_at),
+        if scalar_range(&market).is_some() {
+            format!(" with `/pmarket resolve {} <value>`", market.id)
+        } else if is_date_market(&outcomes) {
+            format!(" with `/pmarket resolve {} <YYYY-MM-DD>`", market.id)
+        } else if is_free_response(&outcomes) {
+            format!(" with `/pmarket resolve {} <answer>`", market.id)
+        } else {
+            String::new()
+        },
+    );
+    Ok(json!({ "blocks": [section(text)] }))
+}
+
+pub fn market_list_blocks(
+    markets: &[Market],
+    conn: &mut PgConnection,
+) -> Result<Vec<Value>, String> {
+    let now = chrono::Utc::now().naive_utc();
+    markets.iter()
+        .map(|market| {
+            let status = if market.is_resolved {
+                "resolved".to_string()
+            } else if market.remind_at <= now {
+                "closed".to_string()
+            } else {
+                forecast_text(market, &get_market_outcomes(market.id, conn)?)
+            };
+            let location = main_slack_msg(market.id, conn)?
+                .map_or(String::new(), |msg| format!(" in <#{}>", msg.channel_id));
+            Ok(section(format!("*{}*\n{}{}", market.title, status, location)))
+        })
+        .collect()
+}
+
+// blocks for markets picked elsewhere, like search results, in the order given
+pub fn market_list(
+    market_ids: &[i32],
+    conn: &mut PgConnection,
+) -> Result<Vec<Value>, String> {
+    let mut markets = markets::table
+        .filter(markets::id.eq_any(market_ids))
+        .load::<Market>(conn)
+        .map_err(|e| format!("Error fetching markets: {}", e))?;
+    markets.sort_by_key(|market| market_ids.iter().position(|id| *id == market.id));
+    market_list_blocks(&markets, conn)
+}
+
+pub fn home_view(
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<Value, String> {
+    let balance = users::table
+        .filter(users::id.eq(user_id))
+        .select(users::balance)
+        .first::<BigDecimal>(conn)
+        .map_err(|e| format!("Error fetching user balance: {}", e))?;
+    let score_text = match score_user(user_id, conn)? {
+        Some(score) => format!(
+            "*Brier score*: {:.3} (market: {:.3}) over {} resolved markets",
+            score.brier, score.market_brier, score.markets
+        ),
+        None => "Trade on markets to get a forecasting score".to_string(),
+    };
+    let filter = MarketFilter {
+        status: Some(MarketStatus::Open),
+        ..Default::default()
+    };
+    let closing_soon = list_markets(&filter, MarketSort::ClosingSoon, None, CLOSING_SOON_COUNT, conn)?.markets;
+    let browse_blocks = if closing_soon.is_empty() {
+        vec![section("No open markets, create one with `/pmarket`")]
+    } else {
+        market_list_blocks(&closing_soon, conn)?
+    };
+
+    let mut blocks = vec![
+        header("Your account"),
+        section(format!("*Balance*: {:.0} :dollar:", balance.to_f64().unwrap())),
+        context(vec![score_text]),
+        json!({
+            "type": "actions",
+            "elements": [button("Claim allowance :moneybag:", "action_claim_allowance", None)],
+        }),
+        header("Closing soon"),
+    ];
+    blocks.extend(browse_blocks);
+    Ok(json!({
+        "type": "home",
+        "blocks": blocks,
+    }))
+}
\ No newline at end of file
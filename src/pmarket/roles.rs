@@ -0,0 +1,189 @@
+use std::str::FromStr;
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::errors::Error;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    // can do anything, anywhere
+    Admin,
+    // can resolve and edit markets posted in their channel
+    Moderator,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Moderator => "moderator",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(Role::Admin),
+            "moderator" => Ok(Role::Moderator),
+            _ => Err(format!("Unknown role: {}", s)),
+        }
+    }
+}
+
+pub fn is_admin(
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<bool, String> {
+    diesel::select(diesel::dsl::exists(
+        roles::table
+            .filter(roles::user_id.eq(user_id))
+            .filter(roles::role.eq(Role::Admin.as_str()))
+    ))
+        .get_result::<bool>(conn)
+        .map_err(|e| format!("Error fetching roles: {}", e))
+}
+
+pub fn is_moderator(
+    user_id: &str,
+    channel_id: &str,
+    conn: &mut PgConnection,
+) -> Result<bool, String> {
+    diesel::select(diesel::dsl::exists(
+        roles::table
+            .filter(roles::user_id.eq(user_id))
+            .filter(roles::role.eq(Role::Moderator.as_str()))
+            .filter(roles::channel_id.eq(channel_id))
+    ))
+        .get_result::<bool>(conn)
+        .map_err(|e| format!("Error fetching roles: {}", e))
+}
+
+pub fn is_market_moderator(
+    user_id: &str,
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<bool, String> {
+    if is_admin(user_id, conn)? {
+        return Ok(true);
+    }
+
+    // a market belongs to the channel it was announced in
+    let channel_id = market_slack_msg::table
+        .filter(market_slack_msg::market_id.eq(market_id))
+        .filter(market_slack_msg::main.eq(true))
+        .select(market_slack_msg::channel_id)
+        .first::<String>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching market slack message: {}", e))?;
+    match channel_id {
+        Some(channel_id) => is_moderator(user_id, &channel_id, conn),
+        None => Ok(false),
+    }
+}
+
+pub fn require_admin(
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    if !is_admin(user_id, conn)? {
+        return Err(Error::forbidden("Only admins can do this"));
+    }
+    Ok(())
+}
+
+pub fn require_market_manager(
+    user_id: &str,
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    let owner_id = markets::table
+        .filter(markets::id.eq(market_id))
+        .select(markets::owner_id)
+        .first::<String>(conn)
+        .optional()
+        .map_err(|e| format!("Error fetching market: {}", e))?
+        .ok_or_else(|| Error::not_found("Market not found"))?;
+    if owner_id != user_id && !is_market_moderator(user_id, market_id, conn)? {
+        return Err(Error::forbidden("Only the market owner or a moderator can do this"));
+    }
+    Ok(())
+}
+
+pub fn add_role(
+    user_id: &str,
+    role: Role,
+    channel_id: Option<&str>,
+    granted_by: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    if (role == Role::Moderator) != channel_id.is_some() {
+        return Err("Moderators need a channel, admins can't have one".to_string());
+    }
+
+    let new_role = NewRole {
+        user_id: user_id.to_string(),
+        role: role.as_str().to_string(),
+        channel_id: channel_id.map(|c| c.to_string()),
+        granted_by: granted_by.map(|g| g.to_string()),
+    };
+
+    diesel::insert_into(roles::table)
+        .values(new_role)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| format!("Error granting role: {}", e))
+}
+
+pub fn grant_role(
+    actor_id: &str,
+    user_id: &str,
+    role: Role,
+    channel_id: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    require_admin(actor_id, conn)?;
+    add_role(user_id, role, channel_id, Some(actor_id), conn)
+}
+
+pub fn remove_role(
+    user_id: &str,
+    role: Role,
+    channel_id: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let revoked = match channel_id {
+        Some(channel_id) => diesel::delete(
+            roles::table
+                .filter(roles::user_id.eq(user_id))
+                .filter(roles::role.eq(role.as_str()))
+                .filter(roles::channel_id.eq(channel_id))
+        )
+            .execute(conn),
+        None => diesel::delete(
+            roles::table
+                .filter(roles::user_id.eq(user_id))
+                .filter(roles::role.eq(role.as_str()))
+                .filter(roles::channel_id.is_null())
+        )
+            .execute(conn),
+    }
+        .map_err(|e| format!("Error revoking role: {}", e))?;
+    if revoked == 0 {
+        return Err("User doesn't have that role".to_string());
+    }
+    Ok(())
+}
+
+pub fn revoke_role(
+    actor_id: &str,
+    user_id: &str,
+    role: Role,
+    channel_id: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    require_admin(actor_id, conn)?;
+    remove_role(user_id, role, channel_id, conn)
+}
\ No newline at end of file
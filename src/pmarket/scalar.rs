@@ -0,0 +1,36 @@
+use bigdecimal::{BigDecimal, One, RoundingMode};
+use crate::models::*;
+use crate::pmarket::lmsr::prob;
+
+// the outcomes of a scalar market: LONG pays more the higher it resolves
+pub const LONG: i32 = 0;
+pub const SHORT: i32 = 1;
+
+pub fn scalar_range(
+    market: &Market,
+) -> Option<(&BigDecimal, &BigDecimal)> {
+    market.scalar_min.as_ref().zip(market.scalar_max.as_ref())
+}
+
+// the value the market expects to resolve to, going by LONG's probability
+pub fn expected_value(
+    market: &Market,
+) -> Option<BigDecimal> {
+    let (min, max) = scalar_range(market)?;
+    let long = &prob(market)[LONG as usize];
+    Some((min + (max - min) * long).with_scale_round(4, RoundingMode::HalfEven))
+}
+
+// the value a resolution to `value` is recorded as, and the resolution
+// probabilities it pays out at: values outside the range resolve to its ends
+pub fn value_resolution(
+    market: &Market,
+    value: &BigDecimal,
+) -> Option<(BigDecimal, Vec<BigDecimal>)> {
+    let (min, max) = scalar_range(market)?;
+    let value = value.clone().clamp(min.clone(), max.clone())
+        .with_scale_round(4, RoundingMode::HalfEven);
+    let long = ((&value - min) / (max - min)).with_scale_round(4, RoundingMode::HalfEven);
+    let short = BigDecimal::one() - &long;
+    Some((value, vec![long, short]))
+}
\ No newline at end of file
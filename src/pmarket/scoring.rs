@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::methods::ResolutionKind;
+
+// keeps log scores finite when a forecast put 0% on what happened
+const MIN_PROB: f64 = 1e-6;
+
+pub struct UserScore {
+    pub user_id: String,
+    pub markets: usize,
+    // time-weighted averages over the markets the user traded on, lower is better
+    pub brier: f64,
+    pub market_brier: f64,
+    // time-weighted averages of the log probability given to the outcome, higher is better
+    pub log: f64,
+    pub market_log: f64,
+}
+
+impl UserScore {
+    // positive when the user's forecasts beat the market over the same stretches of time
+    pub fn brier_skill(&self) -> f64 {
+        self.market_brier - self.brier
+    }
+
+    pub fn log_skill(&self) -> f64 {
+        self.log - self.market_log
+    }
+}
+
+pub struct CalibrationBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    pub mean_predicted: f64,
+    // how often outcomes forecast in this bucket actually happened
+    pub realized: f64,
+}
+
+struct ResolvedMarket {
+    outcome: Vec<f64>,
+    resolved_at: NaiveDateTime,
+    trades: Vec<Trade>,
+}
+
+// per market and user: brier, market brier, log, market log
+type Scores = (f64, f64, f64, f64);
+// a user's current forecast, how many seconds they've held forecasts and the running integrals
+type Forecast = (Vec<f64>, f64, Scores);
+
+fn to_f64s(values: &[Option<BigDecimal>]) -> Vec<f64> {
+    values.iter()
+        .map(|v| v.as_ref().map_or(0.0, |v| v.to_f64().unwrap()))
+        .collect()
+}
+
+fn brier_score(probs: &[f64], outcome: &[f64]) -> f64 {
+    probs.iter()
+        .zip(outcome)
+        .map(|(p, o)| (p - o).powi(2))
+        .sum()
+}
+
+fn log_score(probs: &[f64], outcome: &[f64]) -> f64 {
+    probs.iter()
+        .zip(outcome)
+        .map(|(p, o)| o * p.max(MIN_PROB).ln())
+        .sum()
+}
+
+fn load_resolved_markets(
+    user_id: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<Vec<ResolvedMarket>, String> {
+    // N/A resolutions have no outcome to score against
+    let mut query = markets::table
+        .filter(markets::is_resolved.eq(true))
+        .filter(markets::resolution_kind.ne(ResolutionKind::NotApplicable.as_str()))
+        .order(markets::id)
+        .into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(markets::id.eq_any(
+            trades::table
+                .filter(trades::user_id.eq(user_id.to_string()))
+                .filter(trades::from_answer.eq(false))
+                .select(trades::market_id)
+        ));
+    }
+    let resolved_markets = query
+        .load::<Market>(conn)
+        .map_err(|e| format!("Error fetching markets: {}", e))?;
+    let market_ids = resolved_markets.iter()
+        .map(|market| market.id)
+        .collect::<Vec<i32>>();
+
+    let resolved_at = resolutions::table
+        .filter(resolutions::market_id.eq_any(&market_ids))
+        .filter(resolutions::unresolved_at.is_null())
+        .order(resolutions::id)
+        .select((resolutions::market_id, resolutions::created_at))
+        .load::<(i32, NaiveDateTime)>(conn)
+        .map_err(|e| format!("Error fetching resolutions: {}", e))?
+        .into_iter()
+        .collect::<HashMap<i32, NaiveDateTime>>();
+    let mut market_trades = trades::table
+        .filter(trades::market_id.eq_any(&market_ids))
+        .order(trades::id)
+        .load::<Trade>(conn)
+        .map_err(|e| format!("Error fetching trades: {}", e))?
+        .into_iter()
+        .fold(HashMap::new(), |mut acc: HashMap<i32, Vec<Trade>>, trade| {
+            acc.entry(trade.market_id).or_default().push(trade);
+            acc
+        });
+
+    Ok(resolved_markets.into_iter()
+        .map(|market| {
+            let trades = market_trades.remove(&market.id).unwrap_or_default();
+            // markets resolved before resolutions were recorded close at their
+            // resolution date, or their last trade if that came later
+            let resolved_at = resolved_at.get(&market.id).copied().unwrap_or_else(|| {
+                trades.iter()
+                    .map(|trade| trade.created_at)
+                    .fold(market.remind_at, NaiveDateTime::max)
+            });
+            ResolvedMarket {
+                outcome: to_f64s(market.resolution_probs.as_ref().unwrap()),
+                resolved_at,
+                trades,
+            }
+        })
+        .collect())
+}
+
+// a trader's forecast is the probability they moved the market to, held until
+// their next trade on it or the resolution, and is compared to the market's own
+// probability over the same time
+fn score_market(market: &ResolvedMarket) -> HashMap<String, Scores> {
+    let outcome = &market.outcome;
+    let mut market_probs = vec![1.0 / outcome.len() as f64; outcome.len()];
+    let mut forecasts: HashMap<&str, Forecast> = HashMap::new();
+    let accumulate = |forecasts: &mut HashMap<&str, Forecast>, market_probs: &[f64], dt: f64| {
+        let market_brier = brier_score(market_probs, outcome);
+        let market_log = log_score(market_probs, outcome);
+        for (forecast, held, scores) in forecasts.values_mut() {
+            *held += dt;
+            scores.0 += dt * brier_score(forecast, outcome);
+            scores.1 += dt * market_brier;
+            scores.2 += dt * log_score(forecast, outcome);
+            scores.3 += dt * market_log;
+        }
+    };
+
+    let mut last_time: Option<NaiveDateTime> = None;
+    for trade in &market.trades {
+        if let Some(last_time) = last_time {
+            let dt = (trade.created_at - last_time).num_milliseconds().max(0) as f64 / 1000.0;
+            accumulate(&mut forecasts, &market_probs, dt);
+        }
+        last_time = Some(trade.created_at);
+        market_probs = to_f64s(&trade.probs_after);
+        // adding an answer moves the market without anyone forecasting
+        if trade.from_answer {
+            continue;
+        }
+        forecasts.entry(trade.user_id.as_str())
+            .or_insert_with(|| (Vec::new(), 0.0, (0.0, 0.0, 0.0, 0.0)))
+            .0 = market_probs.clone();
+    }
+    if let Some(last_time) = last_time {
+        let dt = (market.resolved_at - last_time).num_milliseconds().max(0) as f64 / 1000.0;
+        accumulate(&mut forecasts, &market_probs, dt);
+    }
+
+    forecasts.into_iter()
+        .map(|(user_id, (forecast, held, scores))| {
+            let scores = if held > 0.0 {
+                (scores.0 / held, scores.1 / held, scores.2 / held, scores.3 / held)
+            } else {
+                // traded right as the market resolved, score the final probabilities
+                (
+                    brier_score(&forecast, outcome),
+                    brier_score(&market_probs, outcome),
+                    log_score(&forecast, outcome),
+                    log_score(&market_probs, outcome),
+                )
+            };
+            (user_id.to_string(), scores)
+        })
+        .collect()
+}
+
+fn aggregate_scores(
+    resolved_markets: &[ResolvedMarket],
+) -> Vec<UserScore> {
+    let mut totals: HashMap<String, (usize, Scores)> = HashMap::new();
+    for market in resolved_markets {
+        for (user_id, scores) in score_market(market) {
+            let total = totals.entry(user_id).or_insert((0, (0.0, 0.0, 0.0, 0.0)));
+            total.0 += 1;
+            total.1.0 += scores.0;
+            total.1.1 += scores.1;
+            total.1.2 += scores.2;
+            total.1.3 += scores.3;
+        }
+    }
+
+    // every market counts the same, however long the user held a forecast on it
+    let mut user_scores = totals.into_iter()
+        .map(|(user_id, (markets, scores))| UserScore {
+            user_id,
+            markets,
+            brier: scores.0 / markets as f64,
+            market_brier: scores.1 / markets as f64,
+            log: scores.2 / markets as f64,
+            market_log: scores.3 / markets as f64,
+        })
+        .collect::<Vec<UserScore>>();
+    user_scores.sort_by(|a, b| b.brier_skill().total_cmp(&a.brier_skill())
+        .then_with(|| a.user_id.cmp(&b.user_id)));
+    user_scores
+}
+
+pub fn score_users(
+    conn: &mut PgConnection,
+) -> Result<Vec<UserScore>, String> {
+    let resolved_markets = load_resolved_markets(None, conn)?;
+    Ok(aggregate_scores(&resolved_markets))
+}
+
+pub fn score_user(
+    user_id: &str,
+    conn: &mut PgConnection,
+) -> Result<Option<UserScore>, String> {
+    let resolved_markets = load_resolved_markets(Some(user_id), conn)?;
+    Ok(aggregate_scores(&resolved_markets).into_iter()
+        .find(|score| score.user_id == user_id))
+}
+
+pub fn calibration(
+    bucket_count: usize,
+    conn: &mut PgConnection,
+) -> Result<Vec<CalibrationBucket>, String> {
+    if bucket_count == 0 {
+        return Err("Need at least one bucket".to_string());
+    }
+    let resolved_markets = load_resolved_markets(None, conn)?;
+
+    // every trade forecasts each outcome with the probability it left the market at
+    let mut sums = vec![(0usize, 0.0, 0.0); bucket_count];
+    for market in &resolved_markets {
+        for trade in market.trades.iter().filter(|trade| !trade.from_answer) {
+            for (p, o) in to_f64s(&trade.probs_after).iter().zip(&market.outcome) {
+                let idx = ((p * bucket_count as f64) as usize).min(bucket_count - 1);
+                sums[idx].0 += 1;
+                sums[idx].1 += p;
+                sums[idx].2 += o;
+            }
+        }
+    }
+
+    Ok(sums.into_iter()
+        .enumerate()
+        .map(|(idx, (count, predicted, realized))| CalibrationBucket {
+            lower: idx as f64 / bucket_count as f64,
+            upper: (idx + 1) as f64 / bucket_count as f64,
+            count,
+            mean_predicted: if count > 0 { predicted / count as f64 } else { 0.0 },
+            realized: if count > 0 { realized / count as f64 } else { 0.0 },
+        })
+        .collect())
+}
\ No newline at end of file
@@ -0,0 +1,164 @@
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use bigdecimal::{BigDecimal, One, Zero};
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::allowance::AllowancePeriod;
+use crate::pmarket::roles::require_admin;
+
+// how long a process goes by its cached settings before reading them again,
+// which is how changes made by pmarket-admin, the API or the bot reach the
+// other processes; the process making a change sees it right away
+const SETTINGS_TTL: Duration = Duration::from_secs(60);
+
+// the settings and when they were read from the database
+static SETTINGS: RwLock<Option<(Settings, Instant)>> = RwLock::new(None);
+
+pub const SETTING_KEYS: [&str; 11] = [
+    "starting_balance",
+    "min_liquidity",
+    "max_liquidity",
+    "min_trade_size",
+    "default_fee_rate",
+    "max_fee_rate",
+    "max_open_markets_per_user",
+    "allowance_amount",
+    "allowance_period",
+    "allowance_net_worth_cap",
+    "house_account",
+];
+
+fn parse_decimal(
+    key: &str,
+    value: &str,
+) -> Result<BigDecimal, String> {
+    BigDecimal::from_str(value)
+        .map_err(|e| format!("Invalid value for {}: {}", key, e))
+}
+
+// "none" lifts an optional limit
+fn parse_optional<T>(
+    value: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    if value.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    parse(value).map(Some)
+}
+
+impl Settings {
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "starting_balance" => self.starting_balance = parse_decimal(key, value)?,
+            "min_liquidity" => self.min_liquidity = parse_decimal(key, value)?,
+            "max_liquidity" => self.max_liquidity = parse_optional(value, |v| parse_decimal(key, v))?,
+            "min_trade_size" => self.min_trade_size = parse_decimal(key, value)?,
+            "default_fee_rate" => self.default_fee_rate = parse_decimal(key, value)?,
+            "max_fee_rate" => self.max_fee_rate = parse_decimal(key, value)?,
+            "max_open_markets_per_user" => self.max_open_markets_per_user = parse_optional(value, |v| {
+                v.parse::<i32>().map_err(|e| format!("Invalid value for {}: {}", key, e))
+            })?,
+            "allowance_amount" => self.allowance_amount = parse_decimal(key, value)?,
+            "allowance_period" => {
+                AllowancePeriod::from_str(value)?;
+                self.allowance_period = value.to_string();
+            }
+            "allowance_net_worth_cap" => self.allowance_net_worth_cap = parse_optional(value, |v| parse_decimal(key, v))?,
+            "house_account" => self.house_account = parse_optional(value, |v| Ok(v.to_string()))?,
+            _ => return Err(format!(
+                "Unknown setting: {}, expected one of {}",
+                key,
+                SETTING_KEYS.join(", ")
+            )),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.starting_balance < BigDecimal::zero() {
+            return Err("Starting balance can't be negative".to_string());
+        }
+        if self.min_liquidity <= BigDecimal::zero() {
+            return Err("Minimum liquidity must be positive".to_string());
+        }
+        if let Some(max_liquidity) = &self.max_liquidity
+            && *max_liquidity < self.min_liquidity
+        {
+            return Err("Maximum liquidity can't be below the minimum".to_string());
+        }
+        if self.min_trade_size < BigDecimal::zero() {
+            return Err("Minimum trade size can't be negative".to_string());
+        }
+        if self.max_fee_rate < BigDecimal::zero() || self.max_fee_rate >= BigDecimal::one() {
+            return Err("Maximum fee rate must be between 0 and 1".to_string());
+        }
+        if self.default_fee_rate < BigDecimal::zero() || self.default_fee_rate > self.max_fee_rate {
+            return Err("Default fee rate must be between 0 and the maximum fee rate".to_string());
+        }
+        if let Some(max_open_markets) = self.max_open_markets_per_user
+            && max_open_markets <= 0
+        {
+            return Err("Maximum open markets per user must be positive".to_string());
+        }
+        if self.allowance_amount < BigDecimal::zero() {
+            return Err("Allowance amount can't be negative".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn allowance_period(&self) -> Result<AllowancePeriod, String> {
+        AllowancePeriod::from_str(&self.allowance_period)
+    }
+}
+
+pub fn load_settings(
+    conn: &mut PgConnection,
+) -> Result<Settings, String> {
+    let current = settings::table
+        .filter(settings::id.eq(1))
+        .first::<Settings>(conn)
+        .map_err(|e| format!("Error fetching settings: {}", e))?;
+    *SETTINGS.write().unwrap() = Some((current.clone(), Instant::now()));
+    Ok(current)
+}
+
+pub fn get_settings(
+    conn: &mut PgConnection,
+) -> Result<Settings, String> {
+    if let Some((current, loaded_at)) = SETTINGS.read().unwrap().as_ref()
+        && loaded_at.elapsed() < SETTINGS_TTL
+    {
+        return Ok(current.clone());
+    }
+    load_settings(conn)
+}
+
+pub fn apply_setting(
+    key: &str,
+    value: &str,
+    conn: &mut PgConnection,
+) -> Result<Settings, String> {
+    let mut updated = load_settings(conn)?;
+    updated.set(key, value)?;
+    updated.validate()?;
+    updated.updated_at = chrono::Utc::now().naive_utc();
+
+    diesel::update(settings::table.filter(settings::id.eq(1)))
+        .set(&updated)
+        .execute(conn)
+        .map_err(|e| format!("Error updating settings: {}", e))?;
+    load_settings(conn)
+}
+
+pub fn update_setting(
+    actor_id: &str,
+    key: &str,
+    value: &str,
+    conn: &mut PgConnection,
+) -> Result<Settings, String> {
+    require_admin(actor_id, conn)?;
+    apply_setting(key, value, conn)
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use chrono::Duration;
+use diesel::prelude::*;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::errors::Error;
+use crate::pmarket::methods::find_market;
+use crate::pmarket::lmsr::{cost_to_move, prob};
+
+// the probabilities the cost to move an outcome to is reported for
+pub const DEPTH_TARGETS: [f64; 2] = [0.1, 0.9];
+
+pub struct MarketStats {
+    // coins that changed hands in trades, buys and sells alike, fees excluded
+    pub volume: BigDecimal,
+    pub trades: usize,
+    pub traders: usize,
+    // per outcome, the current probability minus the probability 24 hours ago
+    pub prob_change_24h: Vec<f64>,
+    // per outcome, the coins it takes to move it to each of DEPTH_TARGETS
+    pub depth: Vec<Vec<BigDecimal>>,
+}
+
+pub fn market_stats(
+    market_id: i32,
+    conn: &mut PgConnection,
+) -> Result<MarketStats, Error> {
+    let market = find_market(market_id, conn)?;
+    let market_trades = trades::table
+        .filter(trades::market_id.eq(market_id))
+        .order(trades::id)
+        .load::<Trade>(conn)
+        .map_err(|e| format!("Error fetching trades: {}", e))?;
+
+    // what adding answers records moves the price but isn't trading
+    let traded = market_trades.iter()
+        .filter(|trade| !trade.from_answer)
+        .collect::<Vec<&Trade>>();
+    let volume = traded.iter()
+        .map(|trade| trade.balance_change.abs())
+        .fold(BigDecimal::zero(), |acc, v| acc + v);
+    let traders = traded.iter()
+        .map(|trade| trade.user_id.as_str())
+        .collect::<HashSet<&str>>()
+        .len();
+
+    let current = prob(&market).iter()
+        .map(|p| p.to_f64().unwrap())
+        .collect::<Vec<f64>>();
+    // markets start out even, so with no trade before the cutoff that's the old price
+    let cutoff = chrono::Utc::now().naive_utc() - Duration::hours(24);
+    let mut previous = market_trades.iter()
+        .rev()
+        .find(|trade| trade.created_at <= cutoff)
+        .map(|trade| trade.probs_after.iter()
+            .map(|p| p.as_ref().map_or(0.0, |p| p.to_f64().unwrap()))
+            .collect::<Vec<f64>>())
+        .unwrap_or_else(|| vec![1.0 / current.len() as f64; current.len()]);
+    // answers added since then had no price yet
+    previous.resize(current.len(), 0.0);
+    let prob_change_24h = current.iter()
+        .zip(&previous)
+        .map(|(now, before)| now - before)
+        .collect();
+
+    let depth = (0..market.bought_shares.len() as i32)
+        .map(|share_index| DEPTH_TARGETS.iter()
+            .map(|&target| cost_to_move(&market, share_index, target))
+            .collect())
+        .collect();
+
+    Ok(MarketStats {
+        volume,
+        trades: traded.len(),
+        traders,
+        prob_change_24h,
+        depth,
+    })
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+use std::fmt;
+use bigdecimal::{BigDecimal, Zero};
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use crate::models::*;
+use crate::schema::*;
+use crate::pmarket::methods::{change_balance, try_create_user};
+
+#[derive(Debug)]
+pub enum TransferError {
+    InvalidAmount,
+    SelfTransfer,
+    UnknownSender,
+    InsufficientBalance { balance: BigDecimal, amount: BigDecimal },
+    Database(String),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransferError::InvalidAmount => write!(f, "Transfer amount must be positive"),
+            TransferError::SelfTransfer => write!(f, "You can't send coins to yourself"),
+            TransferError::UnknownSender => write!(f, "Sender doesn't exist"),
+            TransferError::InsufficientBalance { balance, amount } => write!(
+                f,
+                "Insufficient balance: you have {} but tried to send {}",
+                balance, amount
+            ),
+            TransferError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<TransferError> for String {
+    fn from(e: TransferError) -> String {
+        e.to_string()
+    }
+}
+
+pub fn transfer(
+    from_user_id: &str,
+    to_user_id: &str,
+    amount: &BigDecimal,
+    memo: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<i32, TransferError> {
+    // balances are stored with 4 decimals, anything finer would be lost
+    let amount = amount.with_scale(4);
+    if amount <= BigDecimal::zero() {
+        return Err(TransferError::InvalidAmount);
+    }
+    if from_user_id == to_user_id {
+        return Err(TransferError::SelfTransfer);
+    }
+    // outside the transaction, a duplicate user would abort it
+    try_create_user(to_user_id, conn).map_err(TransferError::Database)?;
+
+    let new_transfer = NewTransfer {
+        from_user_id: from_user_id.to_string(),
+        to_user_id: to_user_id.to_string(),
+        amount: amount.clone(),
+        memo: memo.map(|m| m.to_string()),
+    };
+
+    let mut err = None;
+    let mut id = None;
+    let transaction = conn.transaction::<(), DieselError, _>(|conn| {
+        // lock both rows in id order so opposite transfers can't deadlock
+        let balances = users::table
+            .filter(users::id.eq_any([from_user_id, to_user_id]))
+            .order(users::id)
+            .select((users::id, users::balance))
+            .for_update()
+            .load::<(String, BigDecimal)>(conn)
+            .map_err(|e| {
+                err = Some(TransferError::Database(format!("Error fetching user balances: {}", e)));
+                DieselError::RollbackTransaction
+            })?;
+        let balance = balances.into_iter()
+            .find(|(id, _)| id == from_user_id)
+            .map(|(_, balance)| balance)
+            .ok_or_else(|| {
+                err = Some(TransferError::UnknownSender);
+                DieselError::RollbackTransaction
+            })?;
+        if balance < amount {
+            err = Some(TransferError::InsufficientBalance { balance, amount: amount.clone() });
+            return Err(DieselError::RollbackTransaction);
+        }
+
+        change_balance(from_user_id, &-amount.clone(), conn)
+            .and_then(|_| change_balance(to_user_id, &amount, conn))
+            .map_err(|e| {
+                err = Some(TransferError::Database(e));
+                DieselError::RollbackTransaction
+            })?;
+        let transfer_id = diesel::insert_into(transfers::table)
+            .values(&new_transfer)
+            .returning(transfers::id)
+            .get_result::<i32>(conn)
+            .map_err(|e| {
+                err = Some(TransferError::Database(format!("Error recording transfer: {}", e)));
+                DieselError::RollbackTransaction
+            })?;
+        id = Some(transfer_id);
+        Ok(())
+    });
+    if let Err(e) = transaction {
+        return Err(err.unwrap_or_else(|| TransferError::Database(format!("Transaction failed: {}", e))));
+    }
+    Ok(id.unwrap())
+}
\ No newline at end of file
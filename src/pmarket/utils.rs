@@ -1,35 +1,60 @@
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
-use bigdecimal::{BigDecimal, ToPrimitive};
+use bigdecimal::ToPrimitive;
 use serde_json::{Value, json};
-use crate::{pmarket::lmsr::{cost_function_algo, prob, prob_algo}, python::classes::*};
+use crate::pmarket::answers::is_free_response;
+use crate::pmarket::errors::Error;
+use crate::pmarket::dates::{DATE_PERCENTILES, date_percentile, get_market_outcomes, is_date_market};
+use crate::pmarket::lmsr::prob;
+use crate::pmarket::quotes::{TradeSize, quote_trade};
+use crate::pmarket::scalar::{expected_value, scalar_range};
+use crate::pmarket::methods::get_withdrawable_subsidy;
+use crate::pmarket::audit::audit;
+use crate::pmarket::browse::get_market_tags;
+use crate::pmarket::settings::get_settings;
+use crate::pmarket::scoring::{UserScore, calibration, score_user, score_users};
+use crate::pmarket::stats::{DEPTH_TARGETS, market_stats};
 
 pub fn get_user_data(
     id: &str, 
     conn: &mut PgConnection
-) -> Result<Value, String> {
-    use crate::schema::users::dsl::*;
+) -> Result<Value, Error> {
+    use crate::schema::users::dsl::users;
 
     match users
         .find(id)
         .first::<crate::models::User>(conn) 
     {
         Ok(user) => {
+            let user_roles = {
+                use crate::schema::roles::dsl as roles_dsl;
+                roles_dsl::roles
+                    .filter(roles_dsl::user_id.eq(id))
+                    .select((roles_dsl::role, roles_dsl::channel_id))
+                    .load::<(String, Option<String>)>(conn)
+                    .map_err(|e| format!("Database error: {}", e))?
+            };
             let user_data = json!({
                 "id": user.id,
                 "balance": user.balance.to_f64().unwrap(),
+                "roles": user_roles.into_iter()
+                    .map(|(role, channel_id)| json!({
+                        "role": role,
+                        "channel_id": channel_id,
+                    }))
+                    .collect::<Vec<Value>>(),
             });
             Ok(user_data)
         },
-        Err(DieselError::NotFound) => Err("User not found".to_string()),
-        Err(e) => Err(format!("Database error: {}", e)),
+        Err(DieselError::NotFound) => Err(Error::not_found("User not found")),
+        Err(e) => Err(format!("Database error: {}", e).into()),
     }
 }
 
 pub fn get_market_data(
     market_id: i32, 
     conn: &mut PgConnection
-) -> Result<Value, String> {
+) -> Result<Value, Error> {
     use crate::schema::markets::dsl as markets_dsl;
     use crate::schema::market_slack_msg::dsl as msm_dsl;
     let main_slack_msg = msm_dsl::market_slack_msg
@@ -44,6 +69,54 @@ pub fn get_market_data(
         .first::<crate::models::Market>(conn) 
     {
         Ok(market) => {
+            let withdrawable_subsidy = get_withdrawable_subsidy(&market, conn)?;
+            let open_dispute_id = {
+                use crate::schema::disputes::dsl as disputes_dsl;
+                disputes_dsl::disputes
+                    .filter(disputes_dsl::market_id.eq(market_id))
+                    .filter(disputes_dsl::closed_at.is_null())
+                    .select(disputes_dsl::id)
+                    .first::<i32>(conn)
+                    .optional()
+                    .map_err(|e| format!("Database error: {}", e))?
+            };
+            let fees_collected = {
+                use crate::schema::trades::dsl as trades_dsl;
+                trades_dsl::trades
+                    .filter(trades_dsl::market_id.eq(market_id))
+                    .select(diesel::dsl::sum(trades_dsl::fee))
+                    .first::<Option<bigdecimal::BigDecimal>>(conn)
+                    .map_err(|e| format!("Database error: {}", e))?
+                    .unwrap_or_default()
+            };
+            let tags = get_market_tags(market_id, conn)?;
+            let outcomes = get_market_outcomes(market_id, conn)?;
+            let conditional_market_ids = markets_dsl::markets
+                .filter(markets_dsl::parent_market_id.eq(market_id))
+                .order(markets_dsl::id.asc())
+                .select(markets_dsl::id)
+                .load::<i32>(conn)
+                .map_err(|e| format!("Database error: {}", e))?;
+            let market_type = if scalar_range(&market).is_some() {
+                "scalar"
+            } else if is_date_market(&outcomes) {
+                "date"
+            } else if is_free_response(&outcomes) {
+                "free_response"
+            } else {
+                "binary"
+            };
+            let date_percentiles = if is_date_market(&outcomes) {
+                DATE_PERCENTILES.iter()
+                    .map(|&q| (
+                        format!("{}", (q * 100.0).round()),
+                        json!(date_percentile(&market, &outcomes, q).map(|d| d.to_string())),
+                    ))
+                    .collect::<serde_json::Map<String, Value>>()
+                    .into()
+            } else {
+                Value::Null
+            };
             let market_data = json!({
                 "id": market.id,
                 "title": market.title,
@@ -56,7 +129,38 @@ pub fn get_market_data(
                 "remind_at": market.remind_at.and_utc().timestamp(),
                 "is_resolved": market.is_resolved,
                 "resolution": market.resolution,
+                "resolution_probs": market.resolution_probs.as_ref().map(|probs| probs.iter()
+                    .map(|p| p.clone().unwrap().to_f64().unwrap())
+                    .collect::<Vec<f64>>()),
+                "resolution_kind": market.resolution_kind,
                 "created_at": market.created_at.and_utc().timestamp(),
+                "subsidy": market.subsidy.to_f64().unwrap(),
+                "withdrawable_subsidy": withdrawable_subsidy.to_f64().unwrap(),
+                "open_dispute_id": open_dispute_id,
+                "fee_rate": market.fee_rate.to_f64().unwrap(),
+                "fee_recipient": market.fee_recipient,
+                "fees_collected": fees_collected.to_f64().unwrap(),
+                "category": market.category,
+                "tags": tags,
+                "market_type": market_type,
+                "scalar_min": market.scalar_min.as_ref().map(|v| v.to_f64().unwrap()),
+                "scalar_max": market.scalar_max.as_ref().map(|v| v.to_f64().unwrap()),
+                "expected_value": expected_value(&market).map(|v| v.to_f64().unwrap()),
+                "resolution_value": market.resolution_value.as_ref().map(|v| v.to_f64().unwrap()),
+                "outcomes": outcomes.iter()
+                    .map(|outcome| json!({
+                        "share_index": outcome.share_index,
+                        "label": outcome.label,
+                        "starts_on": outcome.starts_on.map(|d| d.to_string()),
+                        "ends_on": outcome.ends_on.map(|d| d.to_string()),
+                    }))
+                    .collect::<Vec<Value>>(),
+                "median_date": date_percentile(&market, &outcomes, 0.5).map(|d| d.to_string()),
+                "date_percentiles": date_percentiles,
+                "resolution_date": market.resolution_date.map(|d| d.to_string()),
+                "parent_market_id": market.parent_market_id,
+                "parent_outcome": market.parent_outcome,
+                "conditional_market_ids": conditional_market_ids,
 
                 "prob": prob(&market).iter()
                     .map(|p| p.to_f64().unwrap())
@@ -69,17 +173,186 @@ pub fn get_market_data(
             });
             Ok(market_data)
         },
-        Err(DieselError::NotFound) => Err("Market not found".to_string()),
-        Err(e) => Err(format!("Database error: {}", e)),
+        Err(DieselError::NotFound) => Err(Error::not_found("Market not found")),
+        Err(e) => Err(format!("Database error: {}", e).into()),
+    }
+}
+
+pub fn get_dispute_data(
+    dispute_id: i32,
+    conn: &mut PgConnection
+) -> Result<Value, Error> {
+    use crate::schema::disputes::dsl as disputes_dsl;
+    use crate::schema::dispute_votes::dsl as votes_dsl;
+
+    match disputes_dsl::disputes
+        .find(dispute_id)
+        .first::<crate::models::Dispute>(conn)
+    {
+        Ok(dispute) => {
+            let votes = votes_dsl::dispute_votes
+                .filter(votes_dsl::dispute_id.eq(dispute_id))
+                .select(votes_dsl::in_favor)
+                .load::<bool>(conn)
+                .map_err(|e| format!("Database error: {}", e))?;
+            let votes_for = votes.iter().filter(|v| **v).count();
+            let dispute_data = json!({
+                "id": dispute.id,
+                "market_id": dispute.market_id,
+                "opened_by": dispute.opened_by,
+                "proposed_resolution": dispute.proposed_resolution,
+                "proposed_probs": dispute.proposed_probs.as_ref().map(|probs| probs.iter()
+                    .map(|p| p.clone().unwrap().to_f64().unwrap())
+                    .collect::<Vec<f64>>()),
+                "proposed_value": dispute.proposed_value.as_ref().map(|value| value.to_f64().unwrap()),
+                "proposed_date": dispute.proposed_date.map(|date| date.to_string()),
+                "reason": dispute.reason,
+                "created_at": dispute.created_at.and_utc().timestamp(),
+                "closes_at": dispute.closes_at.and_utc().timestamp(),
+                "closed_at": dispute.closed_at.map(|t| t.and_utc().timestamp()),
+                "overturned": dispute.overturned,
+                "close_error": dispute.close_error,
+                "votes_for": votes_for,
+                "votes_against": votes.len() - votes_for,
+            });
+            Ok(dispute_data)
+        },
+        Err(DieselError::NotFound) => Err(Error::not_found("Dispute not found")),
+        Err(e) => Err(format!("Database error: {}", e).into()),
     }
 }
 
-pub fn get_lmsr_info(
-    shares: &Vec<f64>,
-    liquidity: f64,
-) -> Value {
+pub fn get_solvency_report(
+    conn: &mut PgConnection
+) -> Result<Value, String> {
+    let report = audit(conn)?;
+    Ok(json!({
+        "is_consistent": report.is_consistent(),
+        "total_balances": report.total_balances.to_f64().unwrap(),
+        "total_bankrolls": report.total_bankrolls.to_f64().unwrap(),
+        "total_fees_held": report.total_fees_held.to_f64().unwrap(),
+        "total_grants": report.total_grants.to_f64().unwrap(),
+        "discrepancy": report.discrepancy().to_f64().unwrap(),
+        "markets": report.markets.iter()
+            .map(|m| json!({
+                "market_id": m.market_id,
+                "is_solvent": m.is_solvent(),
+                "bankroll": m.bankroll.to_f64().unwrap(),
+                "fees_held": m.fees_held.to_f64().unwrap(),
+                "cost_bound": m.cost_bound.to_f64().unwrap(),
+                "worst_case_payout": m.worst_case_payout.to_f64().unwrap(),
+                "negative_positions": m.negative_positions,
+            }))
+            .collect::<Vec<Value>>(),
+    }))
+}
+
+pub fn get_settings_data(
+    conn: &mut PgConnection
+) -> Result<Value, String> {
+    let settings = get_settings(conn)?;
+    Ok(json!({
+        "starting_balance": settings.starting_balance.to_f64().unwrap(),
+        "min_liquidity": settings.min_liquidity.to_f64().unwrap(),
+        "max_liquidity": settings.max_liquidity.map(|l| l.to_f64().unwrap()),
+        "min_trade_size": settings.min_trade_size.to_f64().unwrap(),
+        "default_fee_rate": settings.default_fee_rate.to_f64().unwrap(),
+        "max_fee_rate": settings.max_fee_rate.to_f64().unwrap(),
+        "max_open_markets_per_user": settings.max_open_markets_per_user,
+        "allowance_amount": settings.allowance_amount.to_f64().unwrap(),
+        "allowance_period": settings.allowance_period,
+        "allowance_net_worth_cap": settings.allowance_net_worth_cap.map(|c| c.to_f64().unwrap()),
+        "house_account": settings.house_account,
+        "updated_at": settings.updated_at.and_utc().timestamp(),
+    }))
+}
+
+fn user_score_data(score: &UserScore) -> Value {
     json!({
-        "probs": prob_algo(liquidity, &shares),
-        "cost_func": cost_function_algo(liquidity, &shares),
+        "user_id": score.user_id,
+        "markets": score.markets,
+        "brier": score.brier,
+        "market_brier": score.market_brier,
+        "brier_skill": score.brier_skill(),
+        "log": score.log,
+        "market_log": score.market_log,
+        "log_skill": score.log_skill(),
     })
+}
+
+pub fn get_leaderboard(
+    min_markets: usize,
+    conn: &mut PgConnection
+) -> Result<Value, String> {
+    let scores = score_users(conn)?;
+    Ok(Value::Array(scores.iter()
+        .filter(|score| score.markets >= min_markets)
+        .map(user_score_data)
+        .collect()))
+}
+
+pub fn get_user_score(
+    user_id: &str,
+    conn: &mut PgConnection
+) -> Result<Value, String> {
+    let score = score_user(user_id, conn)?;
+    Ok(score.as_ref().map_or(Value::Null, user_score_data))
+}
+
+pub fn get_calibration_data(
+    bucket_count: usize,
+    conn: &mut PgConnection
+) -> Result<Value, String> {
+    let buckets = calibration(bucket_count, conn)?;
+    Ok(Value::Array(buckets.iter()
+        .map(|bucket| json!({
+            "lower": bucket.lower,
+            "upper": bucket.upper,
+            "count": bucket.count,
+            "mean_predicted": bucket.mean_predicted,
+            "realized": bucket.realized,
+        }))
+        .collect()))
+}
+
+pub fn get_market_stats(
+    market_id: i32,
+    conn: &mut PgConnection
+) -> Result<Value, Error> {
+    let stats = market_stats(market_id, conn)?;
+    Ok(json!({
+        "volume": stats.volume.to_f64().unwrap(),
+        "trades": stats.trades,
+        "traders": stats.traders,
+        "prob_change_24h": stats.prob_change_24h,
+        "depth_targets": DEPTH_TARGETS,
+        "depth": stats.depth.iter()
+            .map(|costs| costs.iter()
+                .map(|c| c.to_f64().unwrap())
+                .collect::<Vec<f64>>())
+            .collect::<Vec<Vec<f64>>>(),
+    }))
+}
+
+pub fn get_trade_quote(
+    market_id: i32,
+    user_id: &str,
+    share_index: i32,
+    size: &TradeSize,
+    conn: &mut PgConnection
+) -> Result<Value, Error> {
+    let quote = quote_trade(market_id, user_id, share_index, size, conn)?;
+    Ok(json!({
+        "outcome": quote.share_index,
+        "shares": quote.shares_amount.to_f64().unwrap(),
+        "cost": quote.cost.to_f64().unwrap(),
+        "fee": quote.fee.to_f64().unwrap(),
+        "average_price": quote.average_price.to_f64().unwrap(),
+        "prob_before": quote.prob_before.iter().map(|p| p.to_f64().unwrap()).collect::<Vec<f64>>(),
+        "prob_after": quote.prob_after.iter().map(|p| p.to_f64().unwrap()).collect::<Vec<f64>>(),
+        "balance": quote.balance.to_f64().unwrap(),
+        "position": quote.position.to_f64().unwrap(),
+        "max_payout": quote.max_payout.to_f64().unwrap(),
+        "affordable": quote.affordable,
+    }))
 }
\ No newline at end of file
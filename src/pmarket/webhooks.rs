@@ -0,0 +1,297 @@
+use std::thread;
+use std::time::Duration;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use crate::models::*;
+use crate::schema::*;
+
+pub const WEBHOOK_EVENTS: [&str; 4] = [
+    "trade",
+    "market_created",
+    "market_resolved",
+    "market_unresolved",
+];
+
+// with the delay doubling from 30 seconds, the last attempt is about an hour
+// after the event; no retry waits longer than 6 hours
+const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 6 * 60 * 60;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// a claimed delivery isn't due again until this long after the claim, well
+// past the timeout, so it's only sent again if its worker died sending it
+const DELIVERY_LEASE_SECS: i64 = 60;
+// how many deliveries one pass of the worker sends at most
+const DELIVERY_BATCH: usize = 100;
+
+pub fn add_webhook(
+    url: &str,
+    events: &[String],
+    conn: &mut PgConnection,
+) -> Result<(i32, String), String> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(format!("Webhook URLs must be http or https: {}", url));
+    }
+    if let Some(event) = events.iter().find(|event| !WEBHOOK_EVENTS.contains(&event.as_str())) {
+        return Err(format!(
+            "Unknown webhook event: {}, expected one of {}",
+            event,
+            WEBHOOK_EVENTS.join(", ")
+        ));
+    }
+    let mut secret = [0u8; 32];
+    rand::rng().fill_bytes(&mut secret);
+    let secret = format!("whsec_{}", hex::encode(secret));
+
+    let id = diesel::insert_into(webhooks::table)
+        .values(&NewWebhook {
+            url: url.to_string(),
+            secret: secret.clone(),
+            events: events.to_vec(),
+        })
+        .returning(webhooks::id)
+        .get_result::<i32>(conn)
+        .map_err(|e| format!("Error adding webhook: {}", e))?;
+    Ok((id, secret))
+}
+
+// deliveries already queued for a disabled webhook are dropped by the worker
+pub fn disable_webhook(
+    webhook_id: i32,
+    conn: &mut PgConnection,
+) -> Result<(), String> {
+    let disabled = diesel::update(webhooks::table
+        .filter(webhooks::id.eq(webhook_id))
+        .filter(webhooks::disabled_at.is_null()))
+        .set(webhooks::disabled_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+        .map_err(|e| format!("Error disabling webhook: {}", e))?;
+    if disabled == 0 {
+        return Err(format!("No active webhook with id {}", webhook_id));
+    }
+    Ok(())
+}
+
+pub fn list_webhooks(
+    conn: &mut PgConnection,
+) -> Result<Vec<Webhook>, String> {
+    webhooks::table
+        .order(webhooks::id)
+        .load::<Webhook>(conn)
+        .map_err(|e| format!("Error fetching webhooks: {}", e))
+}
+
+pub fn list_deliveries(
+    webhook_id: i32,
+    limit: i64,
+    conn: &mut PgConnection,
+) -> Result<Vec<WebhookDelivery>, String> {
+    webhook_deliveries::table
+        .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+        .order(webhook_deliveries::id.desc())
+        .limit(limit)
+        .load::<WebhookDelivery>(conn)
+        .map_err(|e| format!("Error fetching webhook deliveries: {}", e))
+}
+
+// receivers recompute this over the X-Pmarket-Timestamp header, a dot and the
+// raw body, and should reject old timestamps so deliveries can't be replayed
+pub fn sign_payload(
+    secret: &str,
+    timestamp: i64,
+    payload: &str,
+) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, payload).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn retry_delay(
+    attempts: i32,
+) -> chrono::Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    chrono::Duration::seconds((BASE_RETRY_DELAY_SECS << exponent).min(MAX_RETRY_DELAY_SECS))
+}
+
+pub fn new_agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(DELIVERY_TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into()
+}
+
+// the status code the receiver answered with, or why there wasn't one
+fn send(
+    agent: &ureq::Agent,
+    webhook: &Webhook,
+    delivery: &WebhookDelivery,
+    now: NaiveDateTime,
+) -> (Option<i32>, Option<String>) {
+    let timestamp = now.and_utc().timestamp();
+    let signature = sign_payload(&webhook.secret, timestamp, &delivery.payload);
+    let response = agent.post(&webhook.url)
+        .header("X-Pmarket-Event", &delivery.event)
+        .header("X-Pmarket-Delivery", delivery.id.to_string())
+        .header("X-Pmarket-Timestamp", timestamp.to_string())
+        .header("X-Pmarket-Signature", format!("sha256={}", signature))
+        .content_type("application/json")
+        .send(&delivery.payload);
+    match response {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16() as i32), None),
+        Ok(response) => (
+            Some(response.status().as_u16() as i32),
+            Some(format!("Receiver responded with {}", response.status())),
+        ),
+        Err(e) => (None, Some(format!("Error sending webhook: {}", e))),
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+enum Claim {
+    Send(Webhook, WebhookDelivery),
+    // marked failed without sending
+    Dropped,
+}
+
+// claims a due delivery, counting the attempt and leasing it so no other
+// worker sends it meanwhile; None once there are none left
+fn claim_next(
+    conn: &mut PgConnection,
+) -> Result<Option<Claim>, String> {
+    let mut err = None;
+    let transaction = conn.transaction::<_, DieselError, _>(|conn| {
+        let now = chrono::Utc::now().naive_utc();
+        // skip locked, so several workers never claim the same delivery
+        let delivery = webhook_deliveries::table
+            .filter(webhook_deliveries::delivered_at.is_null())
+            .filter(webhook_deliveries::failed_at.is_null())
+            .filter(webhook_deliveries::next_attempt_at.le(now))
+            .order(webhook_deliveries::next_attempt_at)
+            .for_update()
+            .skip_locked()
+            .first::<WebhookDelivery>(conn)
+            .optional()
+            .map_err(|e| {
+                err = Some(format!("Error fetching webhook deliveries: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        let Some(mut delivery) = delivery else {
+            return Ok(None);
+        };
+        let webhook = webhooks::table
+            .filter(webhooks::id.eq(delivery.webhook_id))
+            .first::<Webhook>(conn)
+            .map_err(|e| {
+                err = Some(format!("Error fetching webhook: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+
+        let target = webhook_deliveries::table.filter(webhook_deliveries::id.eq(delivery.id));
+        let dropped_because = if webhook.disabled_at.is_some() {
+            Some("Webhook disabled")
+        } else if delivery.attempts >= MAX_ATTEMPTS {
+            // the last attempt's lease ran out without a result, its worker died
+            Some("Gave up after the last attempt didn't finish")
+        } else {
+            None
+        };
+        if let Some(reason) = dropped_because {
+            diesel::update(target)
+                .set((
+                    webhook_deliveries::last_error.eq(reason),
+                    webhook_deliveries::failed_at.eq(now),
+                ))
+                .execute(conn)
+                .map_err(|e| {
+                    err = Some(format!("Error updating webhook delivery: {}", e));
+                    DieselError::RollbackTransaction
+                })?;
+            return Ok(Some(Claim::Dropped));
+        }
+
+        delivery.attempts += 1;
+        diesel::update(target)
+            .set((
+                webhook_deliveries::attempts.eq(delivery.attempts),
+                webhook_deliveries::next_attempt_at.eq(now + chrono::Duration::seconds(DELIVERY_LEASE_SECS)),
+            ))
+            .execute(conn)
+            .map_err(|e| {
+                err = Some(format!("Error claiming webhook delivery: {}", e));
+                DieselError::RollbackTransaction
+            })?;
+        Ok(Some(Claim::Send(webhook, delivery)))
+    });
+    transaction.map_err(|e| err.unwrap_or_else(|| format!("Transaction failed: {}", e)))
+}
+
+// sends one due delivery outside of any transaction, returning false once
+// there are none left
+fn deliver_next(
+    agent: &ureq::Agent,
+    conn: &mut PgConnection,
+) -> Result<bool, String> {
+    let (webhook, delivery) = match claim_next(conn)? {
+        None => return Ok(false),
+        Some(Claim::Dropped) => return Ok(true),
+        Some(Claim::Send(webhook, delivery)) => (webhook, delivery),
+    };
+
+    let (status, error) = send(agent, &webhook, &delivery, chrono::Utc::now().naive_utc());
+    let now = chrono::Utc::now().naive_utc();
+    let delivered = error.is_none();
+    let gave_up = !delivered && delivery.attempts >= MAX_ATTEMPTS;
+    // matching the attempt leaves alone a delivery another worker claimed
+    // again after the lease ran out
+    diesel::update(webhook_deliveries::table
+        .filter(webhook_deliveries::id.eq(delivery.id))
+        .filter(webhook_deliveries::attempts.eq(delivery.attempts))
+        .filter(webhook_deliveries::delivered_at.is_null()))
+        .set((
+            webhook_deliveries::last_status.eq(status),
+            webhook_deliveries::last_error.eq(error),
+            webhook_deliveries::delivered_at.eq(delivered.then_some(now)),
+            webhook_deliveries::failed_at.eq(gave_up.then_some(now)),
+            webhook_deliveries::next_attempt_at.eq(now + retry_delay(delivery.attempts)),
+        ))
+        .execute(conn)
+        .map_err(|e| format!("Error updating webhook delivery: {}", e))?;
+    Ok(true)
+}
+
+// sends every delivery that is due, up to a batch, and returns how many it tried
+pub fn deliver_due(
+    agent: &ureq::Agent,
+    conn: &mut PgConnection,
+) -> Result<usize, String> {
+    let mut sent = 0;
+    while sent < DELIVERY_BATCH && deliver_next(agent, conn)? {
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+pub fn run_worker(
+    poll_interval: Duration,
+) {
+    let agent = new_agent();
+    let mut conn = crate::db::establish_connection_with_retry();
+    loop {
+        match deliver_due(&agent, &mut conn) {
+            // more may be due already, go again without waiting
+            Ok(DELIVERY_BATCH) => continue,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}, reconnecting", e.trim_end());
+                conn = crate::db::establish_connection_with_retry();
+            }
+        }
+        thread::sleep(poll_interval);
+    }
+}
\ No newline at end of file
@@ -1,5 +1,28 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    allowance_claims (user_id, period_start) {
+        user_id -> Text,
+        period_start -> Date,
+        period_end -> Date,
+        amount -> Numeric,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    api_keys (id) {
+        id -> Int4,
+        name -> Text,
+        key_hash -> Text,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        user_id -> Nullable<Text>,
+        role -> Text,
+    }
+}
+
 diesel::table! {
     connections (main_chan_id) {
         main_chan_id -> Text,
@@ -7,6 +30,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dispute_votes (dispute_id, user_id) {
+        dispute_id -> Int4,
+        user_id -> Text,
+        in_favor -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    disputes (id) {
+        id -> Int4,
+        market_id -> Int4,
+        resolution_id -> Int4,
+        opened_by -> Text,
+        proposed_resolution -> Nullable<Int4>,
+        proposed_probs -> Nullable<Array<Nullable<Numeric>>>,
+        reason -> Text,
+        created_at -> Timestamp,
+        closes_at -> Timestamp,
+        closed_at -> Nullable<Timestamp>,
+        overturned -> Nullable<Bool>,
+        proposed_value -> Nullable<Numeric>,
+        proposed_date -> Nullable<Date>,
+        close_error -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     global_vars (id) {
         id -> Int4,
@@ -14,6 +65,49 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    grants (id) {
+        id -> Int4,
+        user_id -> Text,
+        amount -> Numeric,
+        reason -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    idempotency_keys (user_id, key) {
+        user_id -> Text,
+        key -> Text,
+        action -> Text,
+        request_hash -> Text,
+        result -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    liquidity_changes (id) {
+        id -> Int4,
+        market_id -> Int4,
+        user_id -> Text,
+        amount -> Numeric,
+        liquidity_before -> Numeric,
+        liquidity_after -> Numeric,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    market_outcomes (market_id, share_index) {
+        market_id -> Int4,
+        share_index -> Int4,
+        label -> Text,
+        starts_on -> Nullable<Date>,
+        ends_on -> Nullable<Date>,
+    }
+}
+
 diesel::table! {
     market_slack_msg (market_id, channel_id, ts) {
         market_id -> Int4,
@@ -23,6 +117,13 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    market_tags (market_id, tag) {
+        market_id -> Int4,
+        tag -> Text,
+    }
+}
+
 diesel::table! {
     markets (id) {
         id -> Int4,
@@ -36,6 +137,27 @@ diesel::table! {
         is_resolved -> Bool,
         resolution -> Nullable<Int4>,
         created_at -> Timestamp,
+        subsidy -> Numeric,
+        resolution_probs -> Nullable<Array<Nullable<Numeric>>>,
+        resolution_kind -> Nullable<Text>,
+        fee_rate -> Numeric,
+        fee_recipient -> Text,
+        category -> Nullable<Text>,
+        scalar_min -> Nullable<Numeric>,
+        scalar_max -> Nullable<Numeric>,
+        resolution_value -> Nullable<Numeric>,
+        resolution_date -> Nullable<Date>,
+        parent_market_id -> Nullable<Int4>,
+        parent_outcome -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    payouts (id) {
+        id -> Int4,
+        resolution_id -> Int4,
+        user_id -> Text,
+        amount -> Numeric,
     }
 }
 
@@ -53,6 +175,48 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    resolutions (id) {
+        id -> Int4,
+        market_id -> Int4,
+        resolution -> Nullable<Int4>,
+        resolution_probs -> Nullable<Array<Nullable<Numeric>>>,
+        resolution_kind -> Text,
+        created_at -> Timestamp,
+        unresolved_at -> Nullable<Timestamp>,
+        parent_resolution_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    roles (id) {
+        id -> Int4,
+        user_id -> Text,
+        role -> Text,
+        channel_id -> Nullable<Text>,
+        granted_by -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    settings (id) {
+        id -> Int4,
+        starting_balance -> Numeric,
+        min_liquidity -> Numeric,
+        max_liquidity -> Nullable<Numeric>,
+        min_trade_size -> Numeric,
+        default_fee_rate -> Numeric,
+        max_fee_rate -> Numeric,
+        max_open_markets_per_user -> Nullable<Int4>,
+        allowance_amount -> Numeric,
+        allowance_period -> Text,
+        allowance_net_worth_cap -> Nullable<Numeric>,
+        updated_at -> Timestamp,
+        house_account -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     trades (id) {
         id -> Int4,
@@ -62,6 +226,20 @@ diesel::table! {
         share_index -> Int4,
         balance_change -> Numeric,
         created_at -> Timestamp,
+        fee -> Numeric,
+        probs_after -> Array<Nullable<Numeric>>,
+        from_answer -> Bool,
+    }
+}
+
+diesel::table! {
+    transfers (id) {
+        id -> Int4,
+        from_user_id -> Text,
+        to_user_id -> Text,
+        amount -> Numeric,
+        memo -> Nullable<Text>,
+        created_at -> Timestamp,
     }
 }
 
@@ -72,18 +250,78 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Int4,
+        webhook_id -> Int4,
+        event -> Text,
+        payload -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamp>,
+        failed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Int4,
+        url -> Text,
+        secret -> Text,
+        events -> Array<Nullable<Text>>,
+        created_at -> Timestamp,
+        disabled_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::joinable!(allowance_claims -> users (user_id));
+diesel::joinable!(api_keys -> users (user_id));
+diesel::joinable!(dispute_votes -> disputes (dispute_id));
+diesel::joinable!(dispute_votes -> users (user_id));
+diesel::joinable!(disputes -> markets (market_id));
+diesel::joinable!(disputes -> resolutions (resolution_id));
+diesel::joinable!(disputes -> users (opened_by));
+diesel::joinable!(grants -> users (user_id));
+diesel::joinable!(idempotency_keys -> users (user_id));
+diesel::joinable!(liquidity_changes -> markets (market_id));
+diesel::joinable!(liquidity_changes -> users (user_id));
+diesel::joinable!(market_outcomes -> markets (market_id));
 diesel::joinable!(market_slack_msg -> markets (market_id));
-diesel::joinable!(markets -> users (owner_id));
+diesel::joinable!(market_tags -> markets (market_id));
+diesel::joinable!(payouts -> resolutions (resolution_id));
+diesel::joinable!(payouts -> users (user_id));
+diesel::joinable!(resolutions -> markets (market_id));
+diesel::joinable!(settings -> users (house_account));
 diesel::joinable!(trades -> markets (market_id));
 diesel::joinable!(trades -> users (user_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    allowance_claims,
+    api_keys,
     connections,
+    dispute_votes,
+    disputes,
     global_vars,
+    grants,
+    idempotency_keys,
+    liquidity_changes,
+    market_outcomes,
     market_slack_msg,
+    market_tags,
     markets,
+    payouts,
     ping_managers,
     pingers,
+    resolutions,
+    roles,
+    settings,
     trades,
+    transfers,
     users,
+    webhook_deliveries,
+    webhooks,
 );
@@ -0,0 +1,27 @@
+mod common;
+
+use chrono::{Datelike, Duration};
+use common::{balance, connect, decimal, user_with_balance};
+use pmarket_slack::pmarket::allowance::claim_allowance;
+use pmarket_slack::pmarket::settings::apply_setting;
+
+const USER: &str = "UALLOWANCETEST";
+
+#[test]
+fn changing_the_period_doesnt_allow_a_second_claim() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(USER, "0", conn);
+    apply_setting("allowance_net_worth_cap", "none", conn).unwrap();
+    apply_setting("allowance_amount", "10", conn).unwrap();
+    apply_setting("allowance_period", "daily", conn).unwrap();
+
+    assert_eq!(claim_allowance(USER, conn).unwrap(), decimal("10"));
+    // today's claim falls in this week, so the weekly allowance waits for next week
+    apply_setting("allowance_period", "weekly", conn).unwrap();
+    let today = chrono::Utc::now().date_naive();
+    let next_week = today + Duration::days(7 - today.weekday().num_days_from_monday() as i64);
+    let err = claim_allowance(USER, conn).unwrap_err();
+    assert!(err.ends_with(&format!("available on {}", next_week)), "{}", err);
+    assert_eq!(balance(USER, conn), decimal("10"));
+}
\ No newline at end of file
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{balance, connect, decimal, remind_at, user_with_balance};
+use pmarket_slack::pmarket::answers::{OTHER, add_answer, answer_cost};
+use pmarket_slack::pmarket::methods;
+
+const OWNER: &str = "UANSWERSOWNER";
+const TRADER: &str = "UANSWERSTRADER";
+const ADDER: &str = "UANSWERSADDER";
+
+#[test]
+fn added_answers_are_paid_for_and_copy_other() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(TRADER, "1000", conn);
+    user_with_balance(ADDER, "1000", conn);
+    let market_id = methods::create_free_response_market(
+        "What color?", "", OWNER, &decimal("100"), &remind_at(), None, &["Red".to_string()], None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("20"), OTHER, None, conn).unwrap();
+    let after_trade = balance(TRADER, conn);
+
+    let cost = answer_cost(&methods::find_market(market_id, conn).unwrap());
+    let blue = add_answer(market_id, ADDER, "Blue", None, conn).unwrap();
+    assert_eq!(blue, 2);
+    assert_eq!(balance(ADDER, conn), decimal("1000") - &cost);
+    assert!(add_answer(market_id, ADDER, "blue", None, conn).is_err());
+
+    // holding Other was a bet on Blue too
+    let positions = methods::get_positions(market_id, conn).unwrap();
+    assert_eq!(positions[TRADER][OTHER as usize], decimal("20"));
+    assert_eq!(positions[TRADER][blue as usize], decimal("20"));
+
+    methods::resolve_market_answer(market_id, "Blue", OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &after_trade + decimal("20"));
+
+    // N/A gives the adder back what the answer cost
+    methods::unresolve_market(market_id, OWNER, conn).unwrap();
+    methods::resolve_market(market_id, None, OWNER, None, conn).unwrap();
+    assert_eq!(balance(ADDER, conn), decimal("1000"));
+    assert_eq!(balance(TRADER, conn), decimal("1000"));
+}
\ No newline at end of file
@@ -0,0 +1,50 @@
+// shared setup for the tests that need a database with the migrations applied;
+// they only run when DATABASE_URL is set, in a transaction that's rolled back
+#![allow(dead_code)]
+
+use std::env;
+use std::str::FromStr;
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use pmarket_slack::db;
+use pmarket_slack::pmarket::methods;
+use pmarket_slack::schema::users;
+
+pub fn connect() -> Option<PgConnection> {
+    if env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL isn't set, skipping");
+        return None;
+    }
+    let mut conn = db::establish_connection();
+    conn.begin_test_transaction().unwrap();
+    Some(conn)
+}
+
+pub fn decimal(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+pub fn remind_at() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2099, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+}
+
+pub fn user_with_balance(user_id: &str, balance: &str, conn: &mut PgConnection) {
+    methods::try_create_user(user_id, conn).unwrap();
+    set_balance(user_id, balance, conn);
+}
+
+pub fn set_balance(user_id: &str, balance: &str, conn: &mut PgConnection) {
+    diesel::update(users::table.filter(users::id.eq(user_id)))
+        .set(users::balance.eq(decimal(balance)))
+        .execute(conn)
+        .unwrap();
+}
+
+pub fn balance(user_id: &str, conn: &mut PgConnection) -> BigDecimal {
+    users::table
+        .filter(users::id.eq(user_id))
+        .select(users::balance)
+        .first::<BigDecimal>(conn)
+        .unwrap()
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::{balance, connect, decimal, remind_at, user_with_balance};
+use pmarket_slack::pmarket::methods::{self, ResolutionKind};
+
+const OWNER: &str = "UCONDITIONALOWNER";
+const TRADER: &str = "UCONDITIONALTRADER";
+
+#[test]
+fn unmet_conditions_refund_the_conditional_market() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(TRADER, "1000", conn);
+    let parent_id = methods::create_market(
+        "Will it rain?", "", OWNER, &decimal("100"), &remind_at(), None, None, conn,
+    ).unwrap();
+    let child_id = methods::create_conditional_market(
+        "If it rains, will the game be called off?", "", OWNER, &decimal("100"), &remind_at(), None, parent_id, 0, None, conn,
+    ).unwrap();
+    methods::create_trade(child_id, TRADER, &decimal("30"), 0, None, conn).unwrap();
+    let after_trade = balance(TRADER, conn);
+
+    // it can't resolve to an outcome before its parent meets the condition
+    assert!(methods::resolve_market(child_id, Some(0), OWNER, None, conn).is_err());
+
+    methods::resolve_market(parent_id, Some(1), OWNER, None, conn).unwrap();
+    let child = methods::find_market(child_id, conn).unwrap();
+    assert_eq!(methods::resolution_kind(&child), Some(ResolutionKind::NotApplicable));
+    assert_eq!(balance(TRADER, conn), decimal("1000"));
+
+    // unresolving the parent reopens it, trades and all
+    methods::unresolve_market(parent_id, OWNER, conn).unwrap();
+    assert!(!methods::find_market(child_id, conn).unwrap().is_resolved);
+    assert_eq!(balance(TRADER, conn), after_trade);
+
+    methods::resolve_market(parent_id, Some(0), OWNER, None, conn).unwrap();
+    assert!(!methods::find_market(child_id, conn).unwrap().is_resolved);
+    methods::resolve_market(child_id, Some(0), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &after_trade + decimal("30"));
+}
\ No newline at end of file
@@ -0,0 +1,109 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::{balance, connect, decimal, remind_at, user_with_balance};
+use diesel::prelude::*;
+use pmarket_slack::models::Market;
+use pmarket_slack::pmarket::dates::BucketSize;
+use pmarket_slack::pmarket::disputes::{self, Proposal};
+use pmarket_slack::pmarket::methods;
+use pmarket_slack::schema::{disputes as disputes_table, markets};
+
+const OWNER: &str = "UDISPUTETESTOWNER";
+const TRADER: &str = "UDISPUTETESTTRADER";
+const VOTER: &str = "UDISPUTETESTVOTER";
+
+fn setup(conn: &mut PgConnection) {
+    for user_id in [OWNER, TRADER, VOTER] {
+        user_with_balance(user_id, "1000", conn);
+    }
+}
+
+fn market(market_id: i32, conn: &mut PgConnection) -> Market {
+    markets::table
+        .filter(markets::id.eq(market_id))
+        .first::<Market>(conn)
+        .unwrap()
+}
+
+// opens a dispute both traders vote for and closes it once voting ends
+fn overturn(market_id: i32, proposal: Proposal, conn: &mut PgConnection) -> bool {
+    let dispute_id = disputes::open_dispute(market_id, TRADER, proposal, "wrong outcome", conn).unwrap();
+    disputes::vote_on_dispute(dispute_id, VOTER, true, conn).unwrap();
+    diesel::update(disputes_table::table.filter(disputes_table::id.eq(dispute_id)))
+        .set(disputes_table::closes_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+        .unwrap();
+    disputes::close_dispute(dispute_id, conn).unwrap()
+}
+
+#[test]
+fn disputes_re_resolve_to_the_proposed_outcome() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    setup(conn);
+    let market_id = methods::create_market(
+        "Did it rain?", "", OWNER, &decimal("100"), &remind_at(), None, None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("30"), 0, None, conn).unwrap();
+    methods::create_trade(market_id, VOTER, &decimal("10"), 1, None, conn).unwrap();
+    let trader_before = balance(TRADER, conn);
+
+    methods::resolve_market(market_id, Some(1), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), trader_before);
+
+    let err = disputes::open_dispute(market_id, TRADER, Proposal::Outcome(1), "", conn).unwrap_err();
+    assert_eq!(err, "Proposed resolution is the current resolution");
+
+    assert!(overturn(market_id, Proposal::Outcome(0), conn));
+    assert_eq!(market(market_id, conn).resolution, Some(0));
+    assert_eq!(balance(TRADER, conn), trader_before + decimal("30"));
+}
+
+#[test]
+fn disputes_re_resolve_scalar_markets_to_the_proposed_value() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    setup(conn);
+    let market_id = methods::create_scalar_market(
+        "How many came?", "", OWNER, &decimal("100"), &remind_at(), None, &decimal("0"), &decimal("200"), None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("40"), 0, None, conn).unwrap();
+    methods::create_trade(market_id, VOTER, &decimal("10"), 1, None, conn).unwrap();
+    let trader_before = balance(TRADER, conn);
+
+    methods::resolve_market_value(market_id, &decimal("50"), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &trader_before + decimal("10"));
+
+    // each LONG share pays the fraction of the range below the value
+    assert!(overturn(market_id, Proposal::Value(decimal("150")), conn));
+    let market = market(market_id, conn);
+    assert_eq!(market.resolution_value, Some(decimal("150")));
+    assert_eq!(balance(TRADER, conn), trader_before + decimal("30"));
+}
+
+#[test]
+fn disputes_re_resolve_date_markets_to_the_proposed_date() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    setup(conn);
+    let market_id = methods::create_date_market(
+        "When will it open?", "", OWNER, &decimal("100"), &remind_at(), None,
+        NaiveDate::from_ymd_opt(2099, 1, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2099, 4, 1).unwrap(),
+        BucketSize::Month, None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("20"), 1, None, conn).unwrap();
+    methods::create_trade(market_id, VOTER, &decimal("10"), 0, None, conn).unwrap();
+    let trader_before = balance(TRADER, conn);
+
+    let january = NaiveDate::from_ymd_opt(2099, 1, 15).unwrap();
+    methods::resolve_market_date(market_id, january, OWNER, None, conn).unwrap();
+
+    let february = NaiveDate::from_ymd_opt(2099, 2, 10).unwrap();
+    assert!(overturn(market_id, Proposal::Date(february), conn));
+    let market = market(market_id, conn);
+    assert_eq!(market.resolution_date, Some(february));
+    assert_eq!(market.resolution, Some(1));
+    assert_eq!(balance(TRADER, conn), trader_before + decimal("20"));
+}
@@ -0,0 +1,65 @@
+// needs a database with the migrations applied, so it only runs when
+// DATABASE_URL is set
+use std::env;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use diesel::prelude::*;
+use pmarket_slack::db;
+use pmarket_slack::pmarket::events::{EVENTS_CHANNEL, MarketEvent, run_listener};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+fn notify(market_id: i32, conn: &mut PgConnection) {
+    let payload = format!(r#"{{"event": "trade", "market_id": {}, "probs_after": [0.6, 0.4]}}"#, market_id);
+    diesel::sql_query(format!("SELECT pg_notify('{}', '{}')", EVENTS_CHANNEL, payload))
+        .execute(conn)
+        .unwrap();
+}
+
+// the listener may not be listening yet, so keeps notifying until the
+// events for `market_id` arrive
+fn wait_for_events(market_id: i32, events: &mpsc::Receiver<MarketEvent>, conn: &mut PgConnection) -> Vec<MarketEvent> {
+    let started = Instant::now();
+    while started.elapsed() < TIMEOUT {
+        notify(market_id, conn);
+        let received = events.recv_timeout(Duration::from_millis(500))
+            .into_iter()
+            .chain(events.try_iter())
+            .filter(|event| event.market_id == market_id)
+            .collect::<Vec<MarketEvent>>();
+        if !received.is_empty() {
+            return received;
+        }
+    }
+    panic!("No events for market {}", market_id);
+}
+
+#[test]
+fn listener_delivers_events_and_reconnects() {
+    if env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL isn't set, skipping");
+        return;
+    }
+    let (sender, events) = mpsc::channel();
+    thread::spawn(move || run_listener(POLL_INTERVAL, |event| sender.send(event).is_ok()));
+    let mut conn = db::establish_connection();
+
+    // a trade also tells subscribers the price changed
+    let received = wait_for_events(-1, &events, &mut conn);
+    let kinds = received.iter().map(|event| event.event.as_str()).collect::<Vec<&str>>();
+    assert_eq!(kinds[..2], ["trade", "prob_change"]);
+    assert_eq!(received[1].data["prob"], serde_json::json!([0.6, 0.4]));
+
+    // dropping the listener's connection makes it connect and listen again
+    let terminated = diesel::sql_query(format!(
+        "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE query = 'LISTEN {}'",
+        EVENTS_CHANNEL,
+    ))
+        .execute(&mut conn)
+        .unwrap();
+    assert!(terminated > 0);
+    let received = wait_for_events(-2, &events, &mut conn);
+    assert_eq!(received[0].event, "trade");
+}
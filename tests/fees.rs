@@ -0,0 +1,56 @@
+mod common;
+
+use bigdecimal::{BigDecimal, Zero};
+use common::{balance, connect, decimal, remind_at, set_balance, user_with_balance};
+use pmarket_slack::pmarket::methods;
+
+const OWNER: &str = "UFEEOWNER";
+const TRADER: &str = "UFEETRADER";
+
+#[test]
+fn fees_are_held_until_resolution() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(TRADER, "1000", conn);
+    let market_id = methods::create_market(
+        "Will the fees be paid?", "", OWNER, &decimal("100"), &remind_at(), Some(&decimal("0.02")), None, conn,
+    ).unwrap();
+
+    methods::create_trade(market_id, TRADER, &decimal("50"), 0, None, conn).unwrap();
+    let fees = methods::market_fees(market_id, conn).unwrap();
+    assert!(fees > BigDecimal::zero());
+    assert_eq!(balance(OWNER, conn), decimal("900"));
+    let paid = decimal("1000") - balance(TRADER, conn);
+
+    // the owner gets the bankroll the trader paid into and the fees on top
+    methods::resolve_market(market_id, Some(1), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), decimal("1000") - &paid);
+    assert_eq!(balance(OWNER, conn), decimal("1000") + &paid);
+}
+
+#[test]
+fn na_refunds_fees_the_recipient_has_spent() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(TRADER, "1000", conn);
+    let market_id = methods::create_market(
+        "Will the market be cancelled?", "", OWNER, &decimal("100"), &remind_at(), Some(&decimal("0.02")), None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("50"), 0, None, conn).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("-20"), 0, None, conn).unwrap();
+
+    // the recipient has no coins left to give anything back
+    set_balance(OWNER, "0", conn);
+    methods::resolve_market(market_id, None, OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), decimal("1000"));
+    assert_eq!(balance(OWNER, conn), decimal("100"));
+
+    // and resolving again after an unresolution pays the fees out
+    methods::unresolve_market(market_id, OWNER, conn).unwrap();
+    assert_eq!(balance(OWNER, conn), decimal("0"));
+    let paid = decimal("1000") - balance(TRADER, conn);
+    methods::resolve_market(market_id, Some(1), OWNER, None, conn).unwrap();
+    assert_eq!(balance(OWNER, conn), paid + decimal("100"));
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::{connect, decimal, remind_at, user_with_balance};
+use pmarket_slack::pmarket::errors::ErrorKind;
+use pmarket_slack::pmarket::methods;
+
+const OWNER: &str = "UIDEMPOTENTOWNER";
+const OTHER_OWNER: &str = "UIDEMPOTENTOTHER";
+
+#[test]
+fn keys_are_per_user_and_per_request() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(OTHER_OWNER, "1000", conn);
+    let create = |title: &str, owner_id: &str, liquidity: &str, conn: &mut _| methods::create_market(
+        title, "", owner_id, &decimal(liquidity), &remind_at(), None, Some("create-1"), conn,
+    );
+
+    let market_id = create("Will it snow?", OWNER, "100", conn).unwrap();
+    // the same amount written differently is the same request
+    assert_eq!(create("Will it snow?", OWNER, "100.00", conn).unwrap(), market_id);
+
+    let err = create("Will it hail?", OWNER, "100", conn).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Conflict);
+    assert!(err.message.contains("different request"), "{}", err);
+
+    // another user's key is their own
+    let other_id = create("Will it hail?", OTHER_OWNER, "100", conn).unwrap();
+    assert_ne!(other_id, market_id);
+}
@@ -0,0 +1,163 @@
+// snapshots of the Slack views for each kind of market; they need a database
+// with the migrations applied, so they only run when DATABASE_URL is set, in a
+// transaction that's rolled back
+use std::env;
+use std::str::FromStr;
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use pmarket_slack::db;
+use pmarket_slack::pmarket::dates::BucketSize;
+use pmarket_slack::pmarket::{methods, render};
+use pmarket_slack::schema::{markets, users};
+
+const OWNER: &str = "USNAPSHOTOWNER";
+const TRADER: &str = "USNAPSHOTTRADER";
+const CHANNEL: &str = "CSNAPSHOT";
+
+struct Markets {
+    binary: i32,
+    scalar: i32,
+    date: i32,
+    free_response: i32,
+    conditional: i32,
+    resolved: i32,
+}
+
+fn decimal(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+fn remind_at() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2099, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+}
+
+// every market gets posted, so reminders can link to it
+fn posted(market_id: i32, conn: &mut PgConnection) -> i32 {
+    let ts = format!("1700000000.{:06}", market_id);
+    methods::create_market_slack_msg(market_id, CHANNEL, &ts, true, conn).unwrap();
+    market_id
+}
+
+fn create_markets(conn: &mut PgConnection) -> Markets {
+    // only these markets are open, whatever else is in the database
+    diesel::update(markets::table.filter(markets::is_resolved.eq(false)))
+        .set(markets::remind_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+        .unwrap();
+    for user_id in [OWNER, TRADER] {
+        methods::try_create_user(user_id, conn).unwrap();
+        diesel::update(users::table.filter(users::id.eq(user_id)))
+            .set(users::balance.eq(decimal("10000")))
+            .execute(conn)
+            .unwrap();
+    }
+    let liquidity = decimal("100");
+    let fee_rate = decimal("0.02");
+    let no_fee = decimal("0");
+
+    let binary = posted(methods::create_market(
+        "Will it rain tomorrow?",
+        "Resolves YES if it rains anywhere in the city.",
+        OWNER, &liquidity, &remind_at(), Some(&fee_rate), None, conn,
+    ).unwrap(), conn);
+    methods::create_trade(binary, TRADER, &decimal("30"), 0, None, conn).unwrap();
+
+    let scalar = posted(methods::create_scalar_market(
+        "How many people will come to the meetup?",
+        "Resolves to the headcount at the door.",
+        OWNER, &liquidity, &remind_at(), Some(&no_fee), &decimal("0"), &decimal("200"), None, conn,
+    ).unwrap(), conn);
+    methods::create_trade(scalar, TRADER, &decimal("20"), 0, None, conn).unwrap();
+
+    let date = posted(methods::create_date_market(
+        "When will the new library open?",
+        "Resolves to its first day open to the public.",
+        OWNER, &liquidity, &remind_at(), Some(&no_fee),
+        NaiveDate::from_ymd_opt(2099, 1, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2099, 4, 30).unwrap(),
+        BucketSize::Month, None, conn,
+    ).unwrap(), conn);
+    methods::create_trade(date, TRADER, &decimal("15"), 2, None, conn).unwrap();
+
+    let answers = ["Rust".to_string(), "Python".to_string()];
+    let free_response = posted(methods::create_free_response_market(
+        "Which language will the next project use?",
+        "Resolves to the language most of it is written in.",
+        OWNER, &liquidity, &remind_at(), Some(&no_fee), &answers, None, conn,
+    ).unwrap(), conn);
+    methods::create_trade(free_response, TRADER, &decimal("25"), 1, None, conn).unwrap();
+
+    let conditional = posted(methods::create_conditional_market(
+        "If it rains, will the match be moved indoors?",
+        "Resolves YES if the match is played in the hall.",
+        OWNER, &liquidity, &remind_at(), Some(&no_fee), binary, 0, None, conn,
+    ).unwrap(), conn);
+    methods::create_trade(conditional, TRADER, &decimal("10"), 1, None, conn).unwrap();
+
+    let resolved = posted(methods::create_market(
+        "Will the bake sale raise 500 coins?",
+        "Resolves YES if the total is at least 500.",
+        OWNER, &liquidity, &remind_at(), Some(&no_fee), None, conn,
+    ).unwrap(), conn);
+    methods::create_trade(resolved, TRADER, &decimal("10"), 0, None, conn).unwrap();
+    methods::resolve_market(resolved, Some(1), OWNER, None, conn).unwrap();
+
+    Markets { binary, scalar, date, free_response, conditional, resolved }
+}
+
+// market ids come from a sequence, so they're left out of the snapshots
+fn snapshot_settings() -> insta::Settings {
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"resolve \d+", "resolve [market_id]");
+    settings.add_filter(r#"\\"market_id\\":\d+"#, r#"\"market_id\":[market_id]"#);
+    settings.add_filter(r"1700000000\.\d{6}", "[ts]");
+    settings.add_filter(r"p1700000000\d{6}", "p[ts]");
+    settings
+}
+
+#[test]
+fn views() {
+    if env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL isn't set, skipping");
+        return;
+    }
+    let mut conn = db::establish_connection();
+    conn.begin_test_transaction().unwrap();
+    let conn = &mut conn;
+    let markets = create_markets(conn);
+
+    snapshot_settings().bind(|| {
+        let kinds = [
+            ("binary", markets.binary),
+            ("scalar", markets.scalar),
+            ("date", markets.date),
+            ("free_response", markets.free_response),
+            ("conditional", markets.conditional),
+            ("resolved", markets.resolved),
+        ];
+        for (kind, market_id) in kinds {
+            insta::assert_json_snapshot!(format!("market_view_{}", kind), render::market_view(market_id, conn).unwrap());
+        }
+        for (kind, market_id) in &kinds[..5] {
+            insta::assert_json_snapshot!(format!("reminder_view_{}", kind), render::reminder_view(*market_id, conn).unwrap());
+        }
+
+        let ts = "1700000000.000000";
+        let trades = [
+            ("binary_buy", markets.binary, "10", true, 0),
+            ("scalar_sell", markets.scalar, "5", false, 0),
+            ("date_buy", markets.date, "10", true, 1),
+            ("free_response_buy", markets.free_response, "10", true, 2),
+            ("conditional_sell", markets.conditional, "4", false, 1),
+        ];
+        for (name, market_id, shares, buy, share_index) in trades {
+            insta::assert_json_snapshot!(
+                format!("trade_view_{}", name),
+                render::trade_view(market_id, TRADER, &decimal(shares), buy, share_index, CHANNEL, ts, conn).unwrap()
+            );
+        }
+
+        insta::assert_json_snapshot!("home_view", render::home_view(TRADER, conn).unwrap());
+    });
+}
\ No newline at end of file
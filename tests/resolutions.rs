@@ -0,0 +1,101 @@
+mod common;
+
+use chrono::{Days, NaiveDate};
+use common::{balance, connect, decimal, remind_at, user_with_balance};
+use diesel::prelude::*;
+use pmarket_slack::models::Market;
+use pmarket_slack::pmarket::dates::BucketSize;
+use pmarket_slack::pmarket::methods::{self, ResolutionKind};
+use pmarket_slack::pmarket::scalar::{LONG, SHORT};
+use pmarket_slack::schema::markets;
+
+const OWNER: &str = "URESOLVEOWNER";
+const TRADER: &str = "URESOLVETRADER";
+
+fn market(market_id: i32, conn: &mut PgConnection) -> Market {
+    markets::table
+        .filter(markets::id.eq(market_id))
+        .first::<Market>(conn)
+        .unwrap()
+}
+
+#[test]
+fn probability_resolutions_pay_each_outcome_its_probability() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(TRADER, "1000", conn);
+    let market_id = methods::create_market(
+        "How likely was it?", "", OWNER, &decimal("100"), &remind_at(), None, None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("40"), 0, None, conn).unwrap();
+    let after_trade = balance(TRADER, conn);
+
+    let probs = [decimal("0.25"), decimal("0.75")];
+    methods::resolve_market_prob(market_id, &probs, OWNER, Some("resolve-1"), conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &after_trade + decimal("10"));
+    assert_eq!(methods::resolution_kind(&market(market_id, conn)), Some(ResolutionKind::Probabilities));
+
+    // a retry returns without paying twice
+    methods::resolve_market_prob(market_id, &probs, OWNER, Some("resolve-1"), conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &after_trade + decimal("10"));
+
+    // N/A is its own kind, not probabilities without any
+    methods::unresolve_market(market_id, OWNER, conn).unwrap();
+    assert_eq!(methods::resolution_kind(&market(market_id, conn)), None);
+    methods::resolve_market(market_id, None, OWNER, None, conn).unwrap();
+    assert_eq!(methods::resolution_kind(&market(market_id, conn)), Some(ResolutionKind::NotApplicable));
+    assert_eq!(balance(TRADER, conn), decimal("1000"));
+}
+
+
+#[test]
+fn scalar_resolutions_pay_by_where_the_value_falls_in_the_range() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(TRADER, "1000", conn);
+    let market_id = methods::create_scalar_market(
+        "How many?", "", OWNER, &decimal("100"), &remind_at(), None, &decimal("0"), &decimal("200"), None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("40"), LONG, None, conn).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("20"), SHORT, None, conn).unwrap();
+    let after_trades = balance(TRADER, conn);
+
+    // LONG pays 0.75 and SHORT 0.25
+    methods::resolve_market_value(market_id, &decimal("150"), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &after_trades + decimal("35"));
+    assert_eq!(market(market_id, conn).resolution_value, Some(decimal("150")));
+
+    // values past the range resolve to its end
+    methods::unresolve_market(market_id, OWNER, conn).unwrap();
+    methods::resolve_market_value(market_id, &decimal("500"), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &after_trades + decimal("40"));
+    assert_eq!(market(market_id, conn).resolution_value, Some(decimal("200")));
+}
+
+#[test]
+fn date_resolutions_pay_the_bucket_the_date_falls_in() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(OWNER, "1000", conn);
+    user_with_balance(TRADER, "1000", conn);
+    let date = |month: u32, day: u32| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+    // January, February and March 1 or later
+    let market_id = methods::create_date_market(
+        "When?", "", OWNER, &decimal("100"), &remind_at(), None, date(1, 1), date(3, 1), BucketSize::Month, None, conn,
+    ).unwrap();
+    methods::create_trade(market_id, TRADER, &decimal("30"), 1, None, conn).unwrap();
+    let after_trade = balance(TRADER, conn);
+
+    methods::resolve_market_date(market_id, date(2, 14), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), &after_trade + decimal("30"));
+    assert_eq!(market(market_id, conn).resolution, Some(1));
+    assert_eq!(market(market_id, conn).resolution_date, Some(date(2, 14)));
+
+    // dates before the first bucket count towards it
+    methods::unresolve_market(market_id, OWNER, conn).unwrap();
+    methods::resolve_market_date(market_id, date(1, 1) - Days::new(10), OWNER, None, conn).unwrap();
+    assert_eq!(balance(TRADER, conn), after_trade);
+    assert_eq!(market(market_id, conn).resolution, Some(0));
+}
\ No newline at end of file
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{balance, connect, decimal, user_with_balance};
+use diesel::prelude::*;
+use pmarket_slack::models::Transfer;
+use pmarket_slack::pmarket::settings::get_settings;
+use pmarket_slack::pmarket::transfers::{TransferError, transfer};
+use pmarket_slack::schema::transfers;
+
+const SENDER: &str = "UTRANSFERSENDER";
+// created by the transfer, so each test's is new
+const RECIPIENT: &str = "UTRANSFERRECIPIENT";
+
+#[test]
+fn transfers_move_coins_and_are_recorded() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(SENDER, "100", conn);
+    let starting_balance = get_settings(conn).unwrap().starting_balance;
+
+    let transfer_id = transfer(SENDER, RECIPIENT, &decimal("40.5"), Some("lunch"), conn).unwrap();
+    assert_eq!(balance(SENDER, conn), decimal("59.5"));
+    assert_eq!(balance(RECIPIENT, conn), &starting_balance + decimal("40.5"));
+    let recorded = transfers::table
+        .filter(transfers::id.eq(transfer_id))
+        .first::<Transfer>(conn)
+        .unwrap();
+    assert_eq!(
+        (recorded.from_user_id.as_str(), recorded.to_user_id.as_str(), recorded.amount, recorded.memo.as_deref()),
+        (SENDER, RECIPIENT, decimal("40.5"), Some("lunch")),
+    );
+}
+
+#[test]
+fn failed_transfers_leave_balances_alone() {
+    let Some(mut conn) = connect() else { return };
+    let conn = &mut conn;
+    user_with_balance(SENDER, "100", conn);
+    let starting_balance = get_settings(conn).unwrap().starting_balance;
+
+    assert!(matches!(
+        transfer(SENDER, RECIPIENT, &decimal("100.0001"), None, conn),
+        Err(TransferError::InsufficientBalance { .. }),
+    ));
+    assert!(matches!(transfer(SENDER, RECIPIENT, &decimal("0"), None, conn), Err(TransferError::InvalidAmount)));
+    assert!(matches!(transfer(SENDER, SENDER, &decimal("1"), None, conn), Err(TransferError::SelfTransfer)));
+    assert_eq!(balance(SENDER, conn), decimal("100"));
+    assert_eq!(balance(RECIPIENT, conn), starting_balance);
+}
\ No newline at end of file
@@ -0,0 +1,142 @@
+// the delivery test needs a database with the migrations applied, so it only
+// runs when DATABASE_URL is set; it runs in a transaction that's rolled back
+use std::collections::HashMap;
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use pmarket_slack::db;
+use pmarket_slack::models::WebhookDelivery;
+use pmarket_slack::pmarket::webhooks::{add_webhook, deliver_due, new_agent, retry_delay, sign_payload};
+use pmarket_slack::schema::webhook_deliveries;
+
+struct Received {
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+// a receiver answering each request with the next of `statuses`
+fn stand_in(statuses: Vec<u16>) -> (String, mpsc::Receiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (sender, received) = mpsc::channel();
+    thread::spawn(move || {
+        for status in statuses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(": ") {
+                    headers.insert(name.to_lowercase(), value.to_string());
+                }
+            }
+            let length = headers.get("content-length").map_or(0, |length| length.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(reader.get_mut(), "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+            sender.send(Received { headers, body: String::from_utf8(body).unwrap() }).unwrap();
+        }
+    });
+    (url, received)
+}
+
+fn check_signature(received: &Received, secret: &str) {
+    let timestamp = received.headers["x-pmarket-timestamp"].parse::<i64>().unwrap();
+    assert_eq!(
+        received.headers["x-pmarket-signature"],
+        format!("sha256={}", sign_payload(secret, timestamp, &received.body)),
+    );
+}
+
+fn fetch(delivery_id: i32, conn: &mut PgConnection) -> WebhookDelivery {
+    webhook_deliveries::table
+        .filter(webhook_deliveries::id.eq(delivery_id))
+        .first::<WebhookDelivery>(conn)
+        .unwrap()
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+#[test]
+fn retry_delay_doubles_up_to_six_hours() {
+    assert_eq!(retry_delay(1), chrono::Duration::seconds(30));
+    assert_eq!(retry_delay(2), chrono::Duration::seconds(60));
+    assert_eq!(retry_delay(8), chrono::Duration::seconds(30 * 128));
+    assert_eq!(retry_delay(20), chrono::Duration::hours(6));
+}
+
+#[test]
+fn failed_delivery_is_signed_and_retried_after_the_delay() {
+    if env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL isn't set, skipping");
+        return;
+    }
+    let mut conn = db::establish_connection();
+    conn.begin_test_transaction().unwrap();
+    // only this test's delivery is due
+    diesel::update(webhook_deliveries::table
+        .filter(webhook_deliveries::delivered_at.is_null())
+        .filter(webhook_deliveries::failed_at.is_null()))
+        .set(webhook_deliveries::failed_at.eq(now()))
+        .execute(&mut conn)
+        .unwrap();
+
+    let (url, received) = stand_in(vec![500, 200]);
+    let (webhook_id, secret) = add_webhook(&url, &[], &mut conn).unwrap();
+    let payload = r#"{"event": "trade", "market_id": 1}"#;
+    let delivery_id = diesel::insert_into(webhook_deliveries::table)
+        .values((
+            webhook_deliveries::webhook_id.eq(webhook_id),
+            webhook_deliveries::event.eq("trade"),
+            webhook_deliveries::payload.eq(payload),
+            webhook_deliveries::next_attempt_at.eq(now()),
+        ))
+        .returning(webhook_deliveries::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+    let agent = new_agent();
+
+    // the receiver fails the first attempt, so it's retried after the first delay
+    let before = now();
+    assert_eq!(deliver_due(&agent, &mut conn).unwrap(), 1);
+    let after = now();
+    let first = received.recv().unwrap();
+    assert_eq!(first.body, payload);
+    assert_eq!(first.headers["x-pmarket-event"], "trade");
+    assert_eq!(first.headers["x-pmarket-delivery"], delivery_id.to_string());
+    check_signature(&first, &secret);
+    let delivery = fetch(delivery_id, &mut conn);
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.last_status, Some(500));
+    assert!(delivery.delivered_at.is_none() && delivery.failed_at.is_none());
+    assert!(delivery.next_attempt_at >= before + retry_delay(1));
+    assert!(delivery.next_attempt_at <= after + retry_delay(1));
+
+    // not due again until then
+    assert_eq!(deliver_due(&agent, &mut conn).unwrap(), 0);
+
+    diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq(delivery_id)))
+        .set(webhook_deliveries::next_attempt_at.eq(now()))
+        .execute(&mut conn)
+        .unwrap();
+    assert_eq!(deliver_due(&agent, &mut conn).unwrap(), 1);
+    let second = received.recv().unwrap();
+    assert_eq!(second.body, payload);
+    check_signature(&second, &secret);
+    let delivery = fetch(delivery_id, &mut conn);
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(delivery.last_status, Some(200));
+    assert!(delivery.last_error.is_none());
+    assert!(delivery.delivered_at.is_some());
+}